@@ -286,6 +286,7 @@ fn parse_class_feature(
         ["skill", "increase"] | ["skill", "increases"] => {
             let effect = effects::SkillIncreaseEffect {
                 common: effects::EffectCommon::default(),
+                choice: "skill".into(),
             };
             let res_choice_meta = ChoiceMeta {
                 kind: ChoiceKind::Skill,
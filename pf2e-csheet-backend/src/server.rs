@@ -1,5 +1,7 @@
 use anyhow::Result;
-use pf2e_csheet_shared::{Resource, ResourceRef, ResourceType};
+use pf2e_csheet_shared::{
+    validation::ValidationReport, Character, Resource, ResourceRef, ResourceType,
+};
 use rocket::{
     config::{Config, Environment},
     request::{FromQuery, Query},
@@ -9,7 +11,7 @@ use rocket::{
 use rocket_contrib::{json::Json, serve::StaticFiles};
 use smartstring::alias::String as SmartString;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::Arc,
 };
 use tokio::sync::RwLock;
@@ -82,6 +84,25 @@ async fn get_resources_by_trait(
     Json(vec![])
 }
 
+#[post("/characters/validate", data = "<character>")]
+async fn validate_character(
+    store: State<'_, ManagedResourceStore>,
+    character: Json<Character>,
+) -> Json<ValidationReport> {
+    let store_read = store.read().await;
+    Json(character.validate(&*store_read))
+}
+
+/// The values to fill in on the PDF character sheet, by field ID.
+#[post("/characters/pdf-fields", data = "<character>")]
+async fn character_pdf_fields(
+    store: State<'_, ManagedResourceStore>,
+    character: Json<Character>,
+) -> Json<BTreeMap<SmartString, SmartString>> {
+    let store_read = store.read().await;
+    Json(character.pdf_fields(&*store_read).into_iter().collect())
+}
+
 #[get("/")]
 async fn homepage() -> content::Html<&'static str> {
     let page = r###"<!doctype html>
@@ -125,7 +146,9 @@ pub async fn serve(storage: ResourceStore) -> Result<()> {
                 homepage,
                 get_resources,
                 get_resources_by_type,
-                get_resources_by_trait
+                get_resources_by_trait,
+                validate_character,
+                character_pdf_fields
             ],
         )
         .launch()
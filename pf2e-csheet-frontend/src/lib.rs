@@ -258,8 +258,9 @@ impl Component for App {
         };
 
         let debug_pane = |c: Rc<Character>| -> Html {
+            let r = Rc::clone(&self.resource_manager);
             html! {
-                <panes::DebugPane character=c />
+                <panes::DebugPane resources=r character=c />
             }
         };
         let save_load_pane = if let Pane::SaveAndLoad = self.current_pane {
@@ -1,12 +1,15 @@
 use pf2e_csheet_shared::{
     choices::{Choice, ChoiceMeta},
+    dice::{RollResult, Roller},
+    eligibility::{Eligibility, ResourceQuery},
     stats::{Alignment, Level},
+    validation::ValidationReport,
     Ancestry, Background, Character, Class, HasResourceType, Heritage, ResourceRef, ResourceType,
     TypedRef,
 };
 use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
-use std::{fmt, rc::Rc};
+use std::{collections::HashMap, fmt, rc::Rc};
 use yew::prelude::*;
 
 use crate::{resource_manager::ResourceManager, typed_select::TypedSelect, CharacterChange as CC};
@@ -16,11 +19,21 @@ pub struct CorePane {
     resources: Rc<ResourceManager>,
     character: Rc<Character>,
     on_character_change: Callback<CC>,
+    roller: Roller,
+    /// The last check rolled, and what it was for.
+    last_roll: Option<(&'static str, RollResult)>,
+    /// Validating works out the whole sheet, so it's done when the props
+    /// change rather than on every render.
+    problems: ValidationReport,
+    /// Which resources the character can pick for each resource choice.
+    eligibility: HashMap<(ResourceRef, Choice), Vec<Eligibility>>,
 }
 
 #[derive(Debug)]
 pub enum Msg {
     CC(CC),
+    /// Roll a check using the named modifier.
+    Roll(&'static str),
     NoOp,
 }
 
@@ -36,18 +49,25 @@ impl Component for CorePane {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self {
+        let mut pane = Self {
             link,
             resources: props.resources,
             character: props.character,
             on_character_change: props.on_character_change,
-        }
+            roller: Roller::from_entropy(),
+            last_roll: None,
+            problems: ValidationReport::default(),
+            eligibility: HashMap::new(),
+        };
+        pane.refresh();
+        pane
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         self.resources = props.resources;
         self.character = props.character;
         self.on_character_change = props.on_character_change;
+        self.refresh();
         true
     }
 
@@ -57,6 +77,11 @@ impl Component for CorePane {
                 self.on_character_change.emit(cc);
                 true
             }
+            Msg::Roll(label) => {
+                let modifier = self.character.get_modifier(label, None, &*self.resources);
+                self.last_roll = Some((label, self.roller.roll_check(&modifier)));
+                true
+            }
             Msg::NoOp => false,
         }
     }
@@ -65,10 +90,16 @@ impl Component for CorePane {
         let ability_scores = self.view_ability_scores();
         let core_info = self.view_core_info();
         let choices = self.view_choices();
+        let feat_slots = self.view_feat_slots();
+        let problems = self.view_problems();
+        let rolls = self.view_rolls();
         html! {
             <div id="core-pane">
                 { ability_scores }
+                { rolls }
                 { choices }
+                { feat_slots }
+                { problems }
                 { core_info }
             </div>
         }
@@ -76,6 +107,25 @@ impl Component for CorePane {
 }
 
 impl CorePane {
+    /// Work out what the view needs from the whole sheet. It's too slow to do
+    /// on every render, so it's only done when the props change.
+    fn refresh(&mut self) {
+        let character = &*self.character;
+        let resources = &*self.resources;
+        let level = character.level();
+        let eligibility = character
+            .all_choices(resources)
+            .filter_map(|(rref, choice, choice_meta)| {
+                let query = ResourceQuery::for_choice(&choice_meta.kind, level)?;
+                let candidates = resources.all_by_type_immediate(query.resource_type);
+                let checked = character.check_eligibility(candidates, &query, resources);
+                Some(((rref.clone(), choice), checked))
+            })
+            .collect();
+        self.problems = character.validate(resources);
+        self.eligibility = eligibility;
+    }
+
     fn view_choices(&self) -> Html {
         let mut all_choices = self
             .character
@@ -107,18 +157,122 @@ impl CorePane {
         }
     }
 
-    fn view_choice(&self, _rref: &ResourceRef, choice: Choice, choice_meta: ChoiceMeta) -> Html {
+    fn view_feat_slots(&self) -> Html {
+        let rows = self
+            .character
+            .feat_slots(&*self.resources)
+            .into_iter()
+            .map(|slot| {
+                let feat = match slot.chosen.as_ref() {
+                    Some(rref) => html! { { rref } },
+                    None => html! { <em>{ "Empty" }</em> },
+                };
+                html! {
+                    <li>{ format!("Level {} {} feat: ", slot.level, slot.category) }{ feat }</li>
+                }
+            })
+            .collect::<Vec<Html>>();
         html! {
-            <li>{ choice }{ ": " }{ choice_meta.kind }</li>
+            <div id="feat-slots">
+                <ul>{ for rows }</ul>
+            </div>
+        }
+    }
+
+    /// Rules the character breaks, followed by what the player still has to
+    /// do.
+    fn view_problems(&self) -> Html {
+        let report = &self.problems;
+        let errors = report
+            .errors()
+            .map(|d| html! { <li>{ d }</li> })
+            .collect::<Vec<Html>>();
+        let incomplete = report
+            .incomplete()
+            .map(|d| html! { <li>{ d }</li> })
+            .collect::<Vec<Html>>();
+        let content = if report.is_empty() {
+            html! { "No problems found" }
+        } else {
+            html! {
+                <>
+                    <ul class="errors">{ for errors }</ul>
+                    <ul class="incomplete">{ for incomplete }</ul>
+                </>
+            }
+        };
+        html! {
+            <div id="character-problems">
+                { content }
+            </div>
+        }
+    }
+
+    fn view_choice(&self, rref: &ResourceRef, choice: Choice, choice_meta: ChoiceMeta) -> Html {
+        let picker = match self.eligibility.get(&(rref.clone(), choice.clone())) {
+            Some(checked) => self.view_resource_picker(rref, &choice, &choice_meta, checked),
+            None => html! {},
+        };
+        html! {
+            <li>{ choice }{ ": " }{ choice_meta.kind }{ picker }</li>
+        }
+    }
+
+    /// A select for the resources the character can pick for a choice,
+    /// followed by the ones they can't and why.
+    fn view_resource_picker(
+        &self,
+        rref: &ResourceRef,
+        choice: &Choice,
+        choice_meta: &ChoiceMeta,
+        checked: &[Eligibility],
+    ) -> Html {
+        let (eligible, ineligible): (Vec<&Eligibility>, Vec<&Eligibility>) =
+            checked.iter().partition(|e| e.is_eligible());
+        let options: Vec<DW<ResourceRef>> =
+            eligible.into_iter().map(|e| DW(e.rref.clone())).collect();
+        let selected = if choice_meta.character_wide {
+            self.character.get_character_choice(choice)
+        } else {
+            self.character.get_choice(rref, choice)
+        };
+        let selected: Option<DW<ResourceRef>> = selected.map(DW);
+        let rref = rref.clone();
+        let choice = choice.clone();
+        let onselect = move |picked: Option<DW<ResourceRef>>| match picked {
+            Some(DW(picked)) => match serde_json::to_value(&picked) {
+                Ok(value) => Msg::CC(CC::SetChoice(rref.clone(), choice.clone(), value)),
+                Err(_) => Msg::NoOp,
+            },
+            None => Msg::CC(CC::RemoveChoice(rref.clone(), choice.clone())),
+        };
+        let unavailable = ineligible
+            .into_iter()
+            .map(|e| html! { <li>{ e.rref.name.clone() }{ ": " }{ e.unmet.join("; ") }</li> })
+            .collect::<Vec<Html>>();
+        html! {
+            <>
+                <TypedSelect::<DW<ResourceRef>>
+                     choices=options
+                     selected=selected
+                     onselect=self.link.callback(onselect)
+                />
+                <ul class="unavailable">{ for unavailable }</ul>
+            </>
         }
     }
 
     fn view_ability_score_row(&self, stat: &str) -> Html {
         let resources = &self.resources;
         let stat_value = self.character.get_modifier(stat, None, &**resources);
+        let bonus_label = format!("{} bonus", stat);
         let modifier = self
             .character
-            .get_modifier(&format!("{} bonus", stat), None, &**resources);
+            .get_modifier(&bonus_label, None, &**resources);
+        let stat_breakdown = self.character.modifier_breakdown(stat, None, &**resources);
+        let modifier_breakdown =
+            self.character
+                .modifier_breakdown(&bonus_label, None, &**resources);
         let remove_button = html! {
             <button disabled=true>{ "-" }</button>
         };
@@ -128,14 +282,40 @@ impl CorePane {
         html! {
             <tr>
                 <td>{ stat }</td>
-                <td style="font-weight: bold;">{ modifier }</td>
-                <td>{ stat_value.as_score() }</td>
+                <td style="font-weight: bold;" title=format!("{}", modifier_breakdown)>{ modifier }</td>
+                <td title=format!("{}", stat_breakdown)>{ stat_value.as_score() }</td>
                 <td>{ remove_button }</td>
                 <td>{ add_button }</td>
                 </tr>
         }
     }
 
+    /// Buttons to roll Perception and saving throws, and the last result
+    /// with its breakdown.
+    fn view_rolls(&self) -> Html {
+        let buttons = ["Perception", "FORT", "REF", "WILL"]
+            .iter()
+            .map(|label| {
+                let label = *label;
+                html! {
+                    <button onclick=self.link.callback(move |_| Msg::Roll(label))>{ label }</button>
+                }
+            })
+            .collect::<Vec<Html>>();
+        let result = match self.last_roll.as_ref() {
+            Some((label, roll)) => html! {
+                <span title=format!("{}", roll.breakdown)>{ format!("{}: {}", label, roll) }</span>
+            },
+            None => html! {},
+        };
+        html! {
+            <div id="rolls">
+                { for buttons }
+                { result }
+            </div>
+        }
+    }
+
     fn view_ability_scores(&self) -> Html {
         html! {
             <table class="ability-scores">
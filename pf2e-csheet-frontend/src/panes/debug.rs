@@ -2,9 +2,15 @@ use pf2e_csheet_shared::Character;
 use std::rc::Rc;
 use yew::prelude::*;
 
+use crate::resource_manager::ResourceManager;
+
+/// The modifiers to show a breakdown of.
+const BREAKDOWNS: &[&str] = &["AC", "FORT", "REF", "WILL", "Perception"];
+
 pub struct DebugPane {
     #[allow(unused)]
     link: ComponentLink<Self>,
+    resources: Rc<ResourceManager>,
     character: Rc<Character>,
 }
 
@@ -13,6 +19,7 @@ pub enum Msg {}
 
 #[derive(Clone, Debug, Properties)]
 pub struct Props {
+    pub resources: Rc<ResourceManager>,
     pub character: Rc<Character>,
 }
 
@@ -23,11 +30,13 @@ impl Component for DebugPane {
     fn create(props: Props, link: ComponentLink<Self>) -> Self {
         Self {
             link,
+            resources: props.resources,
             character: props.character,
         }
     }
 
     fn change(&mut self, props: Props) -> ShouldRender {
+        self.resources = props.resources;
         self.character = props.character;
         true
     }
@@ -38,8 +47,13 @@ impl Component for DebugPane {
 
     fn view(&self) -> Html {
         let c: &Character = &*self.character;
+        let breakdowns: Vec<String> = BREAKDOWNS
+            .iter()
+            .map(|name| format!("{}", c.modifier_breakdown(name, None, &*self.resources)))
+            .collect();
         html! {
             <div id="debug-pane">
+                <pre><code>{ breakdowns.join("\n\n") }</code></pre>
                 <pre><code>{ format!("{:#?}", c) }</code></pre>
             </div>
         }
@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::fmt;
+use thiserror::Error;
+
+use crate::{
+    choices::Choice,
+    common::ResourceRef,
+    stats::{Ability, AbilityBoost, Level},
+};
+
+/// Where a set of ability boosts comes from. Each source records the
+/// abilities picked for its boosts as a choice, see [`BoostSet::choice`].
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum BoostSource {
+    Ancestry(ResourceRef),
+    Background(ResourceRef),
+    KeyAbility(ResourceRef),
+    /// The four free boosts every character gets at 1st level. These are
+    /// recorded as a character-wide choice.
+    Creation,
+    /// The four free boosts a class grants at 5th, 10th, 15th and 20th level,
+    /// recorded on the class.
+    Level(ResourceRef, Level),
+}
+
+impl BoostSource {
+    /// The resource the choice is recorded on, or `None` if it's a
+    /// character-wide choice.
+    pub fn rref(&self) -> Option<&ResourceRef> {
+        match self {
+            Self::Ancestry(rref)
+            | Self::Background(rref)
+            | Self::KeyAbility(rref)
+            | Self::Level(rref, _) => Some(rref),
+            Self::Creation => None,
+        }
+    }
+
+    pub fn choice(&self) -> Choice {
+        match self {
+            Self::KeyAbility(_) => "Key Ability".into(),
+            Self::Level(_, level) => format!("Level {} Ability Boosts", level).as_str().into(),
+            _ => "Ability Boosts".into(),
+        }
+    }
+}
+
+impl fmt::Display for BoostSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ancestry(rref) | Self::Background(rref) => write!(f, "{}", rref),
+            Self::KeyAbility(rref) => write!(f, "{} key ability", rref),
+            Self::Creation => write!(f, "character creation"),
+            Self::Level(_, level) => write!(f, "level {}", level),
+        }
+    }
+}
+
+/// Boosts (and flaws) that are applied together. A single set may not boost
+/// the same ability more than once.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct BoostSet {
+    pub source: BoostSource,
+    pub boosts: SmallVec<[AbilityBoost; 4]>,
+    pub flaws: SmallVec<[Ability; 1]>,
+    /// The abilities picked for the boosts that aren't fixed, in order.
+    pub chosen: SmallVec<[Ability; 4]>,
+}
+
+impl BoostSet {
+    pub fn new(source: BoostSource, boosts: impl IntoIterator<Item = AbilityBoost>) -> Self {
+        Self {
+            source,
+            boosts: boosts.into_iter().collect(),
+            flaws: SmallVec::new(),
+            chosen: SmallVec::new(),
+        }
+    }
+
+    pub fn choice(&self) -> Choice {
+        self.source.choice()
+    }
+
+    /// How many of the boosts still need an ability picked for them.
+    pub fn missing(&self) -> usize {
+        self.open_boosts().count().saturating_sub(self.chosen.len())
+    }
+
+    fn open_boosts(&self) -> impl Iterator<Item = &AbilityBoost> + '_ {
+        self.boosts
+            .iter()
+            .filter(|b| !matches!(b, AbilityBoost::Fixed(_)))
+    }
+
+    /// The abilities this set boosts. Picks that aren't allowed by their
+    /// boost are left out.
+    pub fn applied(&self) -> SmallVec<[Ability; 4]> {
+        let mut chosen = self.chosen.iter().copied();
+        self.boosts
+            .iter()
+            .filter_map(|boost| match boost {
+                AbilityBoost::Fixed(a) => Some(*a),
+                _ => chosen.next().filter(|a| boost.allows(*a)),
+            })
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), AbilityBoostError> {
+        let open = self.open_boosts().count();
+        if self.chosen.len() > open {
+            return Err(AbilityBoostError::TooMany {
+                from: self.source.clone(),
+                allowed: open,
+                chosen: self.chosen.len(),
+            });
+        }
+        for (boost, ability) in self.open_boosts().zip(self.chosen.iter()) {
+            if !boost.allows(*ability) {
+                return Err(AbilityBoostError::NotAllowed {
+                    from: self.source.clone(),
+                    boost: boost.clone(),
+                    ability: *ability,
+                });
+            }
+        }
+        let applied = self.applied();
+        for (i, ability) in applied.iter().enumerate() {
+            if applied[..i].contains(ability) {
+                return Err(AbilityBoostError::Duplicate {
+                    from: self.source.clone(),
+                    ability: *ability,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Error, Deserialize, Serialize)]
+pub enum AbilityBoostError {
+    #[error("{from} boosts {ability} more than once")]
+    Duplicate { from: BoostSource, ability: Ability },
+    #[error("{from} can't boost {ability} with a {boost} boost")]
+    NotAllowed {
+        from: BoostSource,
+        boost: AbilityBoost,
+        ability: Ability,
+    },
+    #[error("{from} has {allowed} boosts to choose, but {chosen} were chosen")]
+    TooMany {
+        from: BoostSource,
+        allowed: usize,
+        chosen: usize,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AbilityScores([i16; 6]);
+
+impl Default for AbilityScores {
+    fn default() -> Self {
+        Self([10; 6])
+    }
+}
+
+fn index(ability: Ability) -> usize {
+    match ability {
+        Ability::STR => 0,
+        Ability::DEX => 1,
+        Ability::CON => 2,
+        Ability::INT => 3,
+        Ability::WIS => 4,
+        Ability::CHA => 5,
+    }
+}
+
+impl AbilityScores {
+    /// Apply each set in order, starting from 10 in every ability. Flaws in a
+    /// set are applied before its boosts.
+    pub fn from_boosts<'a>(sets: impl IntoIterator<Item = &'a BoostSet>) -> Self {
+        let mut scores = Self::default();
+        for set in sets {
+            for flaw in set.flaws.iter() {
+                scores.flaw(*flaw);
+            }
+            for boost in set.applied() {
+                scores.boost(boost);
+            }
+        }
+        scores
+    }
+
+    pub fn get(&self, ability: Ability) -> i16 {
+        self.0[index(ability)]
+    }
+
+    pub fn modifier(&self, ability: Ability) -> i16 {
+        let score = self.get(ability) - 10;
+        if score < 0 {
+            (score - 1) / 2
+        } else {
+            score / 2
+        }
+    }
+
+    /// A boost adds 2, or only 1 if the score is already 18 or higher.
+    pub fn boost(&mut self, ability: Ability) {
+        let score = &mut self.0[index(ability)];
+        *score += if *score >= 18 { 1 } else { 2 };
+    }
+
+    pub fn flaw(&mut self, ability: Ability) {
+        self.0[index(ability)] -= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smallvec::smallvec;
+
+    fn rref(name: &str) -> ResourceRef {
+        ResourceRef::new(name, None::<&str>)
+    }
+
+    #[test]
+    fn boosts_above_eighteen() {
+        let mut scores = AbilityScores::default();
+        for _ in 0..4 {
+            scores.boost(Ability::STR);
+        }
+        assert_eq!(scores.get(Ability::STR), 18);
+        scores.boost(Ability::STR);
+        assert_eq!(scores.get(Ability::STR), 19);
+        scores.boost(Ability::STR);
+        assert_eq!(scores.get(Ability::STR), 20);
+        assert_eq!(scores.modifier(Ability::STR), 5);
+        scores.flaw(Ability::CHA);
+        assert_eq!(scores.get(Ability::CHA), 8);
+        assert_eq!(scores.modifier(Ability::CHA), -1);
+        scores.flaw(Ability::CHA);
+        scores.boost(Ability::INT);
+        assert_eq!(scores.modifier(Ability::CHA), -2);
+        assert_eq!(scores.modifier(Ability::INT), 1);
+    }
+
+    #[test]
+    fn apply_sets() {
+        let mut ancestry = BoostSet::new(
+            BoostSource::Ancestry(rref("Dwarf")),
+            vec![
+                AbilityBoost::Fixed(Ability::CON),
+                AbilityBoost::Fixed(Ability::WIS),
+                AbilityBoost::Free,
+            ],
+        );
+        ancestry.flaws.push(Ability::CHA);
+        ancestry.chosen.push(Ability::STR);
+        let mut background = BoostSet::new(
+            BoostSource::Background(rref("Acolyte")),
+            vec![
+                AbilityBoost::Choice(smallvec![Ability::INT, Ability::WIS]),
+                AbilityBoost::Free,
+            ],
+        );
+        background.chosen.extend(vec![Ability::WIS, Ability::STR]);
+        let mut creation = BoostSet::new(BoostSource::Creation, vec![AbilityBoost::Free; 4]);
+        creation
+            .chosen
+            .extend(vec![Ability::STR, Ability::DEX, Ability::CON, Ability::WIS]);
+        let sets = vec![ancestry, background, creation];
+        for set in sets.iter() {
+            assert_eq!(set.validate(), Ok(()));
+            assert_eq!(set.missing(), 0);
+        }
+
+        let scores = AbilityScores::from_boosts(&sets);
+        assert_eq!(scores.get(Ability::STR), 16);
+        assert_eq!(scores.get(Ability::DEX), 12);
+        assert_eq!(scores.get(Ability::CON), 14);
+        assert_eq!(scores.get(Ability::INT), 10);
+        assert_eq!(scores.get(Ability::WIS), 16);
+        assert_eq!(scores.get(Ability::CHA), 8);
+    }
+
+    #[test]
+    fn reject_bad_boosts() {
+        let source = BoostSource::Ancestry(rref("Dwarf"));
+        let mut set = BoostSet::new(
+            source.clone(),
+            vec![AbilityBoost::Fixed(Ability::CON), AbilityBoost::Free],
+        );
+        assert_eq!(set.missing(), 1);
+        set.chosen.push(Ability::CON);
+        assert_eq!(
+            set.validate(),
+            Err(AbilityBoostError::Duplicate {
+                from: source.clone(),
+                ability: Ability::CON,
+            })
+        );
+        set.chosen[0] = Ability::DEX;
+        assert_eq!(set.validate(), Ok(()));
+        set.chosen.push(Ability::STR);
+        assert!(matches!(
+            set.validate(),
+            Err(AbilityBoostError::TooMany {
+                allowed: 1,
+                chosen: 2,
+                ..
+            })
+        ));
+
+        let mut set = BoostSet::new(
+            source,
+            vec![AbilityBoost::Choice(smallvec![Ability::INT, Ability::WIS])],
+        );
+        set.chosen.push(Ability::STR);
+        assert!(matches!(
+            set.validate(),
+            Err(AbilityBoostError::NotAllowed {
+                ability: Ability::STR,
+                ..
+            })
+        ));
+        assert!(set.applied().is_empty());
+    }
+}
@@ -459,12 +459,10 @@ impl FromStr for Penalty {
 
 impl std::cmp::PartialEq for Penalty {
     fn eq(&self, other: &Self) -> bool {
-        macro_rules! sum_eq {
-            ($field:ident) => {
-                self.$field.iter().sum::<i16>() == other.$field.iter().sum::<i16>()
-            };
-        }
-        sum_eq!(circumstance) && sum_eq!(item) && sum_eq!(status) && sum_eq!(untyped)
+        self.circumstance_total() == other.circumstance_total()
+            && self.item_total() == other.item_total()
+            && self.status_total() == other.status_total()
+            && self.untyped_total() == other.untyped_total()
     }
 }
 
@@ -546,10 +544,10 @@ impl Serialize for Penalty {
     where
         S: serde::ser::Serializer,
     {
-        let circumstance = self.circumstance.iter().sum::<i16>();
-        let item = self.item.iter().sum::<i16>();
-        let status = self.status.iter().sum::<i16>();
-        let untyped = self.untyped.iter().sum::<i16>();
+        let circumstance = self.circumstance_total();
+        let item = self.item_total();
+        let status = self.status_total();
+        let untyped = self.untyped_total();
 
         match (circumstance, item, status, untyped) {
             (0, 0, 0, 0) => serializer.serialize_i16(0),
@@ -629,13 +627,30 @@ impl Penalty {
         p
     }
 
+    /// Penalties of the same type don't stack, so only the worst of each
+    /// typed penalty counts. Untyped penalties all apply.
     fn total(&self) -> i16 {
-        let mut total: i16 = 0;
-        total += self.circumstance.iter().copied().sum::<i16>();
-        total += self.item.iter().copied().sum::<i16>();
-        total += self.status.iter().copied().sum::<i16>();
-        total += self.untyped.iter().copied().sum::<i16>();
-        total
+        self.circumstance_total() + self.item_total() + self.status_total() + self.untyped_total()
+    }
+
+    fn worst(penalties: &[i16]) -> i16 {
+        penalties.iter().copied().min().unwrap_or(0).min(0)
+    }
+
+    fn circumstance_total(&self) -> i16 {
+        Self::worst(&self.circumstance)
+    }
+
+    fn item_total(&self) -> i16 {
+        Self::worst(&self.item)
+    }
+
+    fn status_total(&self) -> i16 {
+        Self::worst(&self.status)
+    }
+
+    fn untyped_total(&self) -> i16 {
+        self.untyped.iter().copied().sum::<i16>()
     }
 }
 
@@ -716,24 +731,17 @@ pub struct Modifier {
 impl PartialEq for Modifier {
     fn eq(&self, other: &Self) -> bool {
         macro_rules! cmp {
-            ($field:ident, scalar) => {
-                (self.bonus.$field as i16 + cmp!(@iter self.penalty.$field)) == (other.bonus.$field as i16 + cmp!(@iter other.penalty.$field))
-            };
-            ($field:ident, iter) => {
-                (cmp!(@iter self.bonus.$field) + cmp!(@iter self.penalty.$field)) == (cmp!(@iter other.bonus.$field) + cmp!(@iter other.penalty.$field))
-            };
-            // (@iter $e:expr => i16) => {
-            //     ($e.iter().sum::<u16>() as i16)
-            // };
-            (@iter $e:expr) => {
-                $e.iter().sum::<i16>()
+            ($field:ident, $penalty:ident) => {
+                (self.bonus.$field as i16 + self.penalty.$penalty())
+                    == (other.bonus.$field as i16 + other.penalty.$penalty())
             };
         }
-        cmp!(circumstance, scalar)
-            && cmp!(item, scalar)
+        cmp!(circumstance, circumstance_total)
+            && cmp!(item, item_total)
             && self.bonus.proficiency == other.bonus.proficiency
-            && cmp!(status, scalar)
-            && cmp!(untyped, iter)
+            && cmp!(status, status_total)
+            && (self.bonus.untyped.iter().sum::<i16>() + self.penalty.untyped_total())
+                == (other.bonus.untyped.iter().sum::<i16>() + other.penalty.untyped_total())
     }
 }
 
@@ -838,6 +846,15 @@ impl Modifier {
         }
     }
 
+    /// An untyped bonus or penalty, depending on the sign of `value`.
+    pub fn untyped(value: i16) -> Self {
+        if value >= 0 {
+            Bonus::untyped(value).into()
+        } else {
+            Penalty::untyped(value).into()
+        }
+    }
+
     pub fn total(&self) -> i16 {
         let bonus = self.bonus.total();
         let penalty = self.penalty.total();
@@ -878,6 +895,32 @@ impl Modifier {
     pub fn as_score(&self) -> Score {
         Score { modifier: self }
     }
+
+    /// Every bonus and penalty that makes up this modifier, along with its
+    /// type, before the stacking rules are applied to them. Zeroes are left
+    /// out.
+    pub fn parts(&self) -> Vec<(BonusType, i16)> {
+        let b = &self.bonus;
+        let mut parts = vec![
+            (BonusType::Circumstance, b.circumstance as i16),
+            (BonusType::Item, b.item as i16),
+            (BonusType::Proficiency, b.proficiency as i16),
+            (BonusType::Status, b.status as i16),
+        ];
+        parts.extend(b.untyped.iter().map(|u| (BonusType::Untyped, *u)));
+        let p = &self.penalty;
+        let penalties = [
+            (BonusType::Circumstance, &p.circumstance),
+            (BonusType::Item, &p.item),
+            (BonusType::Status, &p.status),
+            (BonusType::Untyped, &p.untyped),
+        ];
+        for (bonus_type, values) in penalties.iter() {
+            parts.extend(values.iter().map(|v| (bonus_type.clone(), *v)));
+        }
+        parts.retain(|(_, v)| *v != 0);
+        parts
+    }
 }
 
 #[derive(Copy, Clone, Debug, Error, Deserialize, Serialize)]
@@ -924,11 +967,11 @@ impl From<(Bonus, Penalty)> for Modifier {
 
 impl fmt::Display for Modifier {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let c = (self.bonus.circumstance as i16) + self.penalty.circumstance.iter().sum::<i16>();
-        let i = (self.bonus.item as i16) + self.penalty.item.iter().sum::<i16>();
+        let c = (self.bonus.circumstance as i16) + self.penalty.circumstance_total();
+        let i = (self.bonus.item as i16) + self.penalty.item_total();
         let p = self.bonus.proficiency as i16;
-        let s = (self.bonus.status as i16) + self.penalty.status.iter().sum::<i16>();
-        let u = self.bonus.untyped.iter().sum::<i16>() + self.penalty.untyped.iter().sum::<i16>();
+        let s = (self.bonus.status as i16) + self.penalty.status_total();
+        let u = self.bonus.untyped.iter().sum::<i16>() + self.penalty.untyped_total();
         match (c, i, p, s, u) {
             (0, 0, 0, 0, 0) => write!(f, "0"),
             (c, 0, 0, 0, 0) => write!(f, "{} {}", c, BonusType::Circumstance),
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::fmt;
+
+use crate::{
+    bonuses::{BonusType, Modifier},
+    common::ResourceRef,
+    effects::Effect,
+};
+
+/// Where part of a modifier comes from.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum ModifierSource {
+    /// Built into the rules rather than granted by a resource, like ability
+    /// modifiers, proficiency or being encumbered.
+    Rules(String),
+    /// A resource that adds to the modifier without an effect, like a class's
+    /// hit points.
+    Resource(ResourceRef),
+    Effect {
+        resource: ResourceRef,
+        effect: Box<Effect>,
+    },
+}
+
+impl ModifierSource {
+    pub fn rules(label: impl Into<String>) -> Self {
+        Self::Rules(label.into())
+    }
+
+    pub fn resource(&self) -> Option<&ResourceRef> {
+        match self {
+            Self::Rules(_) => None,
+            Self::Resource(resource) | Self::Effect { resource, .. } => Some(resource),
+        }
+    }
+}
+
+impl fmt::Display for ModifierSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Rules(label) => write!(f, "{}", label),
+            Self::Resource(resource) | Self::Effect { resource, .. } => {
+                write!(f, "{}", resource.name)?;
+                if let Some(m) = resource.modifier.as_ref() {
+                    write!(f, " ({})", m)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A single bonus or penalty, and whether it counts towards the total.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ModifierPart {
+    pub source: ModifierSource,
+    pub bonus_type: BonusType,
+    pub value: i16,
+    /// Whether the stacking rules let this count. Only the biggest bonus and
+    /// worst penalty of each type apply, but untyped ones all do.
+    pub applied: bool,
+}
+
+impl fmt::Display for ModifierPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:+} {} from {}",
+            self.value, self.bonus_type, self.source
+        )?;
+        if !self.applied {
+            write!(f, " (doesn't stack)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything that goes into a modifier, see
+/// [`Character::modifier_breakdown`].
+///
+/// [`Character::modifier_breakdown`]: crate::Character::modifier_breakdown
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ModifierBreakdown {
+    pub name: String,
+    pub parts: Vec<ModifierPart>,
+}
+
+impl ModifierBreakdown {
+    pub fn new(
+        name: impl Into<String>,
+        sources: impl IntoIterator<Item = (ModifierSource, Modifier)>,
+    ) -> Self {
+        let mut parts: Vec<ModifierPart> = vec![];
+        for (source, modifier) in sources {
+            for (bonus_type, value) in modifier.parts() {
+                parts.push(ModifierPart {
+                    source: source.clone(),
+                    bonus_type,
+                    value,
+                    applied: true,
+                });
+            }
+        }
+
+        // The first of the biggest bonuses (or worst penalties) of each type
+        // is the one that applies.
+        for i in 0..parts.len() {
+            if parts[i].bonus_type == BonusType::Untyped {
+                continue;
+            }
+            let (value, bonus_type) = (parts[i].value, parts[i].bonus_type.clone());
+            let beaten = parts.iter().enumerate().any(|(j, other)| {
+                other.bonus_type == bonus_type
+                    && (other.value > 0) == (value > 0)
+                    && (other.value.abs() > value.abs() || (other.value == value && j < i))
+            });
+            parts[i].applied = !beaten;
+        }
+
+        Self {
+            name: name.into(),
+            parts,
+        }
+    }
+
+    pub fn total(&self) -> i16 {
+        self.parts
+            .iter()
+            .filter(|p| p.applied)
+            .map(|p| p.value)
+            .sum()
+    }
+
+    pub fn applied(&self) -> impl Iterator<Item = &ModifierPart> + '_ {
+        self.parts.iter().filter(|p| p.applied)
+    }
+
+    /// Bonuses and penalties that were overridden by bigger ones of the same
+    /// type.
+    pub fn suppressed(&self) -> impl Iterator<Item = &ModifierPart> + '_ {
+        self.parts.iter().filter(|p| !p.applied)
+    }
+}
+
+impl fmt::Display for ModifierBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:+}", self.name, self.total())?;
+        for part in self.parts.iter() {
+            write!(f, "\n  {}", part)?;
+        }
+        Ok(())
+    }
+}
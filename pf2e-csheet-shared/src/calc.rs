@@ -5,14 +5,15 @@ use proptest_derive::Arbitrary;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use smartstring::alias::String;
-use std::{fmt, str::FromStr};
+use std::{cell::RefCell, collections::BTreeMap, fmt, str::FromStr};
 use thiserror::Error;
 
 use crate::{
-    bonuses::{Bonus, Modifier, Penalty},
+    bonuses::Modifier,
     character::Character,
     choices::Choice,
-    common::ResourceRef,
+    common::{ResourceRef, ResourceType},
+    stats::{DieRoll, Level},
     storage::ResourceStorage,
 };
 
@@ -56,52 +57,287 @@ impl<'a> CalcContext<'a> {
     }
 }
 
+/// Something being worked out that can depend on other values, see
+/// [`checked_recurse`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum EvalStep {
+    /// A modifier, worked out for the item it's used with (if any).
+    Modifier {
+        label: String,
+        target: Option<ResourceRef>,
+    },
+    Proficiency {
+        target: String,
+        item: Option<ResourceRef>,
+    },
+}
+
+impl fmt::Display for EvalStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let item = match self {
+            Self::Modifier { label, target } => {
+                write!(f, "{}", label)?;
+                target
+            }
+            Self::Proficiency { target, item } => {
+                write!(f, "proficiency in {}", target)?;
+                item
+            }
+        };
+        match item {
+            Some(item) => write!(f, " for {}", item),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A value that ended up depending on itself. The path runs from the value
+/// that was asked for to the step that repeats.
+#[derive(Clone, Debug, Eq, PartialEq, Error, Deserialize, Serialize)]
+#[error("{} depends on itself ({})", self.repeated(), self.path_string())]
+pub struct EvalCycle {
+    pub path: Vec<EvalStep>,
+}
+
+impl EvalCycle {
+    /// The step that was reached a second time.
+    pub fn repeated(&self) -> &EvalStep {
+        self.path.last().expect("Cycles always have a path")
+    }
+
+    /// Just the part of the path that goes around in a circle.
+    pub fn loop_steps(&self) -> &[EvalStep] {
+        let repeated = self.repeated();
+        let start = self.path.iter().position(|s| s == repeated).unwrap_or(0);
+        &self.path[start..self.path.len() - 1]
+    }
+
+    /// Whether both cycles go through the same steps, even if they were
+    /// reached from different places.
+    pub fn is_same_loop(&self, other: &EvalCycle) -> bool {
+        let (a, b) = (self.loop_steps(), other.loop_steps());
+        a.len() == b.len() && a.iter().all(|step| b.contains(step))
+    }
+
+    fn path_string(&self) -> String {
+        let steps: Vec<String> = self.path.iter().map(|s| format!("{}", s)).collect();
+        steps.join(" → ").into()
+    }
+}
+
+thread_local! {
+    /// What's being worked out on this thread right now, outermost first.
+    static EVALUATING: RefCell<Vec<EvalStep>> = RefCell::new(vec![]);
+    /// The cycles found while [`collect_cycles`] is running.
+    static CYCLES: RefCell<Option<Vec<EvalCycle>>> = RefCell::new(None);
+}
+
+/// Work out `step` with `f`, unless it's already being worked out further up
+/// the stack. Effects can name any modifier in their values, so a bad
+/// resource could otherwise recurse until the stack overflows. Cycles are
+/// logged, and recorded if [`collect_cycles`] is running.
+pub(crate) fn checked_recurse<T>(step: EvalStep, f: impl FnOnce() -> T) -> Result<T, EvalCycle> {
+    let cycle = EVALUATING.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        if stack.contains(&step) {
+            let mut path = stack.clone();
+            path.push(step.clone());
+            Some(EvalCycle { path })
+        } else {
+            stack.push(step.clone());
+            None
+        }
+    });
+    if let Some(cycle) = cycle {
+        error!("Stopped evaluating: {}", cycle);
+        CYCLES.with(|cycles| {
+            if let Some(found) = cycles.borrow_mut().as_mut() {
+                found.push(cycle.clone());
+            }
+        });
+        return Err(cycle);
+    }
+
+    // Pop the step even if `f` panics, so it isn't mistaken for a cycle
+    // later.
+    struct Pop;
+    impl Drop for Pop {
+        fn drop(&mut self) {
+            EVALUATING.with(|stack| stack.borrow_mut().pop());
+        }
+    }
+    let _pop = Pop;
+    Ok(f())
+}
+
+/// Run `f`, returning every cycle [`checked_recurse`] stopped along the way.
+pub(crate) fn collect_cycles<T>(f: impl FnOnce() -> T) -> (T, Vec<EvalCycle>) {
+    let outer = CYCLES.with(|cycles| cycles.replace(Some(vec![])));
+    let result = f();
+    let found = CYCLES.with(|cycles| cycles.replace(outer));
+    (result, found.unwrap_or_default())
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum Op {
     Add,
-    // Subtract,
-    // Multiply,
-    // Divide,
+    Subtract,
+    Multiply,
+    Divide,
 }
 
 impl fmt::Display for Op {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Add => write!(f, "+"),
-            // Self::Subtract => write!(f, "-"),
-            // Self::Multiply => write!(f, "*"),
-            // Self::Divide => write!(f, "/"),
+            Self::Subtract => write!(f, "-"),
+            Self::Multiply => write!(f, "*"),
+            Self::Divide => write!(f, "/"),
         }
     }
 }
 
 impl Op {
-    // fn is_associative(self) -> bool {
-    //     match self {
-    //         Self::Add => true,
-    //         // Self::Subtract => false,
-    //         // Self::Multiply => true,
-    //         // Self::Divide => false,
-    //     }
-    // }
+    fn is_associative(self) -> bool {
+        match self {
+            Self::Add => true,
+            Self::Subtract => false,
+            Self::Multiply => true,
+            Self::Divide => false,
+        }
+    }
 
-    fn apply(self, x: i16, y: i16) -> i16 {
+    fn apply(self, x: i16, y: i16, rounding: Rounding) -> i16 {
         match self {
-            Self::Add => x + y,
-            // Self::Subtract => x - y,
-            // Self::Multiply => x * y,
-            // Self::Divide => x / y,
+            Self::Add => x.saturating_add(y),
+            Self::Subtract => x.saturating_sub(y),
+            Self::Multiply => x.saturating_mul(y),
+            Self::Divide if y == 0 => {
+                warn!("Attempted to divide {} by zero, using 0 instead", x);
+                0
+            }
+            Self::Divide => {
+                let q = x / y;
+                let inexact = x % y != 0;
+                let negative = (x < 0) != (y < 0);
+                match rounding {
+                    Rounding::Down if inexact && negative => q - 1,
+                    Rounding::Up if inexact && !negative => q + 1,
+                    _ => q,
+                }
+            }
         }
     }
 }
 
+/// How division rounds. Pathfinder rounds down unless told otherwise.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Rounding {
+    Down,
+    Up,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum BuiltinValue {
+    Level,
+    HalfLevel,
+    /// The value of the condition the calculation belongs to, like the 2 in
+    /// frightened 2. It's 0 outside of conditions.
+    ConditionValue,
+}
+
+impl fmt::Display for BuiltinValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Level => write!(f, "level"),
+            Self::HalfLevel => write!(f, "half level"),
+            Self::ConditionValue => write!(f, "condition value"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum Function {
+    Min,
+    Max,
+    Floor,
+    Ceil,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Min => write!(f, "min"),
+            Self::Max => write!(f, "max"),
+            Self::Floor => write!(f, "floor"),
+            Self::Ceil => write!(f, "ceil"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Less => write!(f, "<"),
+            Self::LessOrEqual => write!(f, "<="),
+            Self::Greater => write!(f, ">"),
+            Self::GreaterOrEqual => write!(f, ">="),
+            Self::Equal => write!(f, "=="),
+            Self::NotEqual => write!(f, "!="),
+        }
+    }
+}
+
+impl Comparison {
+    fn apply(self, x: i16, y: i16) -> bool {
+        match self {
+            Self::Less => x < y,
+            Self::LessOrEqual => x <= y,
+            Self::Greater => x > y,
+            Self::GreaterOrEqual => x >= y,
+            Self::Equal => x == y,
+            Self::NotEqual => x != y,
+        }
+    }
+}
+
+/// `if(left <cmp> right, then, otherwise)`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Conditional {
+    pub left: Calculation,
+    pub cmp: Comparison,
+    pub right: Calculation,
+    pub then: Calculation,
+    pub otherwise: Calculation,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
 #[serde(try_from = "smartstring::alias::String")]
 pub enum Calculation {
     Named(String),
     Choice(Choice),
     Modifier(Modifier),
+    Dice(DieRoll),
+    Builtin(BuiltinValue),
+    Function(Function, Vec<Calculation>),
+    If(Box<Conditional>),
+    /// Steps keyed by character level, using the highest step that has been
+    /// reached.
+    Table(BTreeMap<Level, Calculation>),
     Op(Op, Vec<Calculation>),
 }
 
@@ -114,18 +350,52 @@ impl Arbitrary for Calculation {
         let leaf = prop_oneof![
             prop::string::string_regex("[a-zA-Z][a-zA-Z_]+")
                 .unwrap()
+                .prop_filter("builtin values aren't names", |n| n != "level")
                 .prop_map_into()
                 .prop_map(Calculation::Named),
             any::<Choice>().prop_map(Calculation::Choice),
             any::<Modifier>().prop_map(Calculation::Modifier),
+            any::<DieRoll>().prop_map(Calculation::Dice),
+            any::<BuiltinValue>().prop_map(Calculation::Builtin),
         ];
         leaf.prop_recursive(
             3,  // levels deep
             20, // maximum node count
             5,  // items per collection
             |inner| {
-                (any::<Op>(), prop::collection::vec(inner.clone(), 2..10))
-                    .prop_map(|(op, calcs)| Self::Op(op, calcs).normalized())
+                prop_oneof![
+                    (any::<Op>(), prop::collection::vec(inner.clone(), 2..10))
+                        .prop_map(|(op, calcs)| Self::Op(op, calcs).normalized()),
+                    (
+                        prop_oneof![Just(Function::Min), Just(Function::Max)],
+                        prop::collection::vec(inner.clone(), 1..4)
+                    )
+                        .prop_map(|(f, args)| Self::Function(f, args).normalized()),
+                    (
+                        prop_oneof![Just(Function::Floor), Just(Function::Ceil)],
+                        inner.clone()
+                    )
+                        .prop_map(|(f, arg)| Self::Function(f, vec![arg]).normalized()),
+                    (
+                        inner.clone(),
+                        any::<Comparison>(),
+                        inner.clone(),
+                        inner.clone(),
+                        inner.clone()
+                    )
+                        .prop_map(|(left, cmp, right, then, otherwise)| {
+                            Self::If(Box::new(Conditional {
+                                left,
+                                cmp,
+                                right,
+                                then,
+                                otherwise,
+                            }))
+                            .normalized()
+                        }),
+                    prop::collection::btree_map(any::<Level>(), inner.clone(), 1..4)
+                        .prop_map(|table| Self::Table(table).normalized()),
+                ]
             },
         )
         .boxed()
@@ -140,44 +410,75 @@ impl Default for Calculation {
 
 impl Calculation {
     fn normalize(&mut self) {
+        match self {
+            Self::Function(_, args) => args.iter_mut().for_each(Self::normalize),
+            Self::If(c) => {
+                c.left.normalize();
+                c.right.normalize();
+                c.then.normalize();
+                c.otherwise.normalize();
+            }
+            Self::Table(table) => table.values_mut().for_each(Self::normalize),
+            _ => (),
+        }
         if let Self::Op(outer_op, outer_terms) = self {
             trace!("Normalizing Op({:?}, {:?})", outer_op, outer_terms);
-            if outer_terms.iter().all(|t| matches!(t, Self::Modifier(_))) {
-                {
-                    trace!(
-                        "Normalizing Op({:?}, {:?}) by combining modifiers",
-                        outer_op,
-                        outer_terms
-                    );
-                }
+            if *outer_op == Op::Add && outer_terms.iter().all(|t| matches!(t, Self::Modifier(_))) {
+                trace!(
+                    "Normalizing Op({:?}, {:?}) by combining modifiers",
+                    outer_op,
+                    outer_terms
+                );
                 let mut total = Modifier::new();
-                match outer_op {
-                    Op::Add => {
-                        for term in outer_terms.drain(..) {
-                            match term {
-                                Self::Modifier(m) => total += m,
-                                _ => unreachable!(),
-                            }
-                        }
+                for term in outer_terms.drain(..) {
+                    match term {
+                        Self::Modifier(m) => total += m,
+                        _ => unreachable!(),
                     }
                 }
                 *self = Self::Modifier(total);
                 return;
             }
-            // Flatten tree with matching ops
             let mut terms = vec![];
             std::mem::swap(&mut terms, outer_terms);
+            if !outer_op.is_associative() {
+                // Only the leftmost term can be merged into a chain like
+                // `a - b - c`, and the order of terms matters.
+                for term in terms.iter_mut() {
+                    term.normalize();
+                }
+                if let Some(Self::Op(inner_op, _)) = terms.first() {
+                    if inner_op == outer_op {
+                        let mut rest = terms.split_off(1);
+                        terms = match terms.pop() {
+                            Some(Self::Op(_, inner_terms)) => inner_terms,
+                            _ => unreachable!(),
+                        };
+                        terms.append(&mut rest);
+                    }
+                }
+                *self = Self::Op(*outer_op, terms);
+                return;
+            }
+            // Flatten tree with matching ops
             let mut flattened = true;
             let mut iterations = 0;
             while flattened {
                 trace!("Flattening op terms {:?}", terms);
                 flattened = false;
-                terms.sort_by_key(|term| match term {
-                    Self::Op(_, _) => 0,
-                    Self::Modifier(_) => 1,
-                    Self::Named(_) => 2,
-                    Self::Choice(_) => 3,
-                });
+                if *outer_op == Op::Add {
+                    terms.sort_by_key(|term| match term {
+                        Self::Op(_, _) => 0,
+                        Self::Dice(_) => 1,
+                        Self::Modifier(_) => 2,
+                        Self::Builtin(_) => 3,
+                        Self::Named(_) => 4,
+                        Self::Choice(_) => 5,
+                        Self::Function(_, _) => 6,
+                        Self::If(_) => 7,
+                        Self::Table(_) => 8,
+                    });
+                }
                 let mut new_terms = Vec::with_capacity(terms.len());
                 for mut term in terms.drain(..) {
                     term.normalize();
@@ -186,16 +487,16 @@ impl Calculation {
                             flattened = true;
                             new_terms.extend(inner_terms);
                         }
-                        Self::Modifier(m1) => match (*outer_op, new_terms.pop()) {
-                            (Op::Add, Some(Self::Modifier(m2))) => {
+                        Self::Modifier(m1) if *outer_op == Op::Add => match new_terms.pop() {
+                            Some(Self::Modifier(m2)) => {
                                 new_terms.push(Self::Modifier(m1 + m2));
                                 flattened = true;
                             }
-                            (Op::Add, Some(other)) => {
+                            Some(other) => {
                                 new_terms.push(other);
                                 new_terms.push(Self::Modifier(m1));
                             }
-                            (Op::Add, None) => {
+                            None => {
                                 new_terms.push(Self::Modifier(m1));
                             }
                         },
@@ -207,7 +508,10 @@ impl Calculation {
                 assert!(iterations < 20);
             }
             trace!("After flatten, terms = {:?}", terms);
-            *self = Self::Op(*outer_op, terms);
+            *self = match terms.len() {
+                1 => terms.pop().unwrap(),
+                _ => Self::Op(*outer_op, terms),
+            };
         }
     }
 
@@ -255,49 +559,213 @@ impl Calculation {
     // }
 
     pub fn from_number(n: i16) -> Self {
-        let m = if n >= 0 {
-            Bonus::untyped(n).into()
-        } else {
-            Penalty::untyped(-n).into()
-        };
-        Calculation::Modifier(m)
+        Calculation::Modifier(Modifier::untyped(n))
+    }
+
+    /// Evaluate everything that can be known ahead of time. Dice can't be, so
+    /// anything involving them comes back as a simplified formula.
+    pub fn evaluate(&self, ctx: CalcContext<'_>) -> CalcValue {
+        self.evaluate_rounding(ctx, Rounding::Down, &mut |roll| {
+            CalcValue::Formula(Self::Dice(roll))
+        })
     }
 
-    pub fn evaluate(&self, ctx: CalcContext<'_>) -> i16 {
+    /// Evaluate fully, using `roll_dice` to produce the total for each set of
+    /// dice that gets used.
+    pub fn evaluate_with_dice(
+        &self,
+        ctx: CalcContext<'_>,
+        roll_dice: &mut dyn FnMut(DieRoll) -> i16,
+    ) -> i16 {
+        let value = self.evaluate_rounding(ctx, Rounding::Down, &mut |roll| {
+            CalcValue::Number(roll_dice(roll))
+        });
+        match value {
+            CalcValue::Number(n) => n,
+            CalcValue::Formula(f) => {
+                error!("Calculation {} still had dice after rolling: {}", self, f);
+                0
+            }
+        }
+    }
+
+    fn evaluate_rounding(
+        &self,
+        ctx: CalcContext<'_>,
+        rounding: Rounding,
+        dice: &mut dyn FnMut(DieRoll) -> CalcValue,
+    ) -> CalcValue {
         match self {
-            Self::Named(name) => ctx
-                .character
-                .get_modifier(name, ctx.target, ctx.resources)
-                .total(),
+            Self::Named(name) => CalcValue::Number(
+                ctx.character
+                    .get_modifier(name, ctx.target, ctx.resources)
+                    .total(),
+            ),
             Self::Choice(choice) => {
                 let resource = match ctx.resources.lookup_immediate(ctx.rref) {
                     None => {
                         debug!("When attempting to evaluate choice, failed to look up resouce for reference {}", ctx.rref);
-                        return 0;
+                        return CalcValue::Number(0);
                     }
                     Some(r) => r,
                 };
                 match resource.common().get_choice(choice, ctx) {
                     None => {
                         debug!("When attempting to evaluate a choice, failed to find a numeric value set for resource {}", resource);
-                        0
+                        CalcValue::Number(0)
                     }
-                    Some(v) => v,
+                    Some(v) => CalcValue::Number(v),
                 }
             }
-            Self::Modifier(m) => m.total(),
-            Self::Op(op, terms) => {
-                let mut iter = terms.iter();
-                let mut value = match iter.next() {
-                    None => return 0,
-                    Some(t) => t.evaluate(ctx),
+            Self::Modifier(m) => CalcValue::Number(m.total()),
+            Self::Dice(roll) => dice(*roll),
+            Self::Builtin(BuiltinValue::Level) => {
+                CalcValue::Number(ctx.character.level().get() as i16)
+            }
+            Self::Builtin(BuiltinValue::HalfLevel) => {
+                CalcValue::Number(ctx.character.level().get() as i16 / 2)
+            }
+            Self::Builtin(BuiltinValue::ConditionValue) => match ctx.rref.resource_type {
+                Some(ResourceType::Condition) => CalcValue::Number(
+                    ctx.character
+                        .effective_condition_value(&ctx.rref.name, ctx.resources)
+                        as i16,
+                ),
+                _ => {
+                    debug!(
+                        "{} isn't a condition, so it has no condition value",
+                        ctx.rref
+                    );
+                    CalcValue::Number(0)
+                }
+            },
+            Self::Function(f, args) => {
+                let rounding = match f {
+                    Function::Floor => Rounding::Down,
+                    Function::Ceil => Rounding::Up,
+                    _ => rounding,
                 };
-                for term in iter {
-                    let next = term.evaluate(ctx);
-                    value = op.apply(value, next);
+                let values = args
+                    .iter()
+                    .map(|a| a.evaluate_rounding(ctx, rounding, dice))
+                    .collect::<SmallVec<[CalcValue; 4]>>();
+                match CalcValue::all_numbers(&values) {
+                    Some(numbers) => {
+                        let n = match f {
+                            Function::Min => numbers.into_iter().min(),
+                            Function::Max => numbers.into_iter().max(),
+                            Function::Floor | Function::Ceil => numbers.into_iter().next(),
+                        };
+                        CalcValue::Number(n.unwrap_or(0))
+                    }
+                    None => {
+                        let args = values.into_iter().map(CalcValue::into_calculation);
+                        CalcValue::Formula(Self::Function(*f, args.collect()).normalized())
+                    }
+                }
+            }
+            Self::If(c) => {
+                let left = c.left.evaluate_rounding(ctx, rounding, dice);
+                let right = c.right.evaluate_rounding(ctx, rounding, dice);
+                match (left.as_number(), right.as_number()) {
+                    (Some(l), Some(r)) if c.cmp.apply(l, r) => {
+                        c.then.evaluate_rounding(ctx, rounding, dice)
+                    }
+                    (Some(_), Some(_)) => c.otherwise.evaluate_rounding(ctx, rounding, dice),
+                    _ => CalcValue::Formula(
+                        Self::If(Box::new(Conditional {
+                            left: left.into_calculation(),
+                            cmp: c.cmp,
+                            right: right.into_calculation(),
+                            then: c
+                                .then
+                                .evaluate_rounding(ctx, rounding, dice)
+                                .into_calculation(),
+                            otherwise: c
+                                .otherwise
+                                .evaluate_rounding(ctx, rounding, dice)
+                                .into_calculation(),
+                        }))
+                        .normalized(),
+                    ),
                 }
-                value
             }
+            Self::Table(table) => {
+                let level = ctx.character.level();
+                match table.range(..=level).next_back() {
+                    Some((_, step)) => step.evaluate_rounding(ctx, rounding, dice),
+                    None => CalcValue::Number(0),
+                }
+            }
+            Self::Op(op, terms) => {
+                let values = terms
+                    .iter()
+                    .map(|t| t.evaluate_rounding(ctx, rounding, dice))
+                    .collect::<SmallVec<[CalcValue; 4]>>();
+                if let Some(numbers) = CalcValue::all_numbers(&values) {
+                    let mut iter = numbers.into_iter();
+                    let first = match iter.next() {
+                        None => return CalcValue::Number(0),
+                        Some(n) => n,
+                    };
+                    return CalcValue::Number(iter.fold(first, |x, y| op.apply(x, y, rounding)));
+                }
+                let terms = values
+                    .into_iter()
+                    .map(CalcValue::into_calculation)
+                    .collect();
+                CalcValue::Formula(Self::Op(*op, terms).normalized())
+            }
+        }
+    }
+
+    /// Evaluate a calculation that is expected to be a plain number, such as a
+    /// bonus value.
+    pub fn evaluate_number(&self, ctx: CalcContext<'_>) -> i16 {
+        match self.evaluate(ctx) {
+            CalcValue::Number(n) => n,
+            CalcValue::Formula(f) => {
+                warn!(
+                    "Expected calculation {} to produce a number, but got {}; using 0",
+                    self, f
+                );
+                0
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CalcValue {
+    Number(i16),
+    Formula(Calculation),
+}
+
+impl CalcValue {
+    pub fn as_number(&self) -> Option<i16> {
+        match self {
+            Self::Number(n) => Some(*n),
+            Self::Formula(_) => None,
+        }
+    }
+
+    fn all_numbers(values: &[CalcValue]) -> Option<SmallVec<[i16; 4]>> {
+        values.iter().map(Self::as_number).collect()
+    }
+
+    pub fn into_calculation(self) -> Calculation {
+        match self {
+            Self::Number(n) => Calculation::from_number(n),
+            Self::Formula(f) => f,
+        }
+    }
+}
+
+impl fmt::Display for CalcValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Formula(calc) => write!(f, "{}", calc),
         }
     }
 }
@@ -348,6 +816,33 @@ impl fmt::Display for Calculation {
             Self::Named(name) => write!(f, "{}", name),
             Self::Choice(choice) => write!(f, "${}", choice),
             Self::Modifier(m) => write!(f, "{}", m),
+            Self::Dice(roll) => write!(f, "{}", roll),
+            Self::Builtin(b) => write!(f, "{}", b),
+            Self::Function(func, args) => {
+                write!(f, "{}(", func)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Self::If(c) => write!(
+                f,
+                "if({} {} {}, {}, {})",
+                c.left, c.cmp, c.right, c.then, c.otherwise
+            ),
+            Self::Table(table) => {
+                write!(f, "table(")?;
+                for (i, (level, step)) in table.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", level, step)?;
+                }
+                write!(f, ")")
+            }
             Self::Op(op, terms) => {
                 for (i, t) in terms.iter().enumerate() {
                     let pt;
@@ -593,3 +1088,193 @@ impl FromStr for CalculatedString {
 }
 
 serialize_display!(CalculatedString);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common::ResourceType, stats::Die, test_helpers::TestStorage};
+
+    fn character_at(level: u8) -> Character {
+        let mut character = Character::new("Test Character");
+        let class = ResourceRef::new("Fighter", None::<&str>).with_type(Some(ResourceType::Class));
+        character
+            .set_choice(&class, "Level".into(), &Level::from(level))
+            .unwrap();
+        character.resources.insert(class);
+        character
+    }
+
+    fn eval_at(level: u8, s: &str) -> CalcValue {
+        let calc: Calculation = s.parse().unwrap();
+        let character = character_at(level);
+        let rref = ResourceRef::new("Test Resource", None::<&str>);
+        let storage = TestStorage::new(vec![]);
+        calc.evaluate(CalcContext::new(&character, &rref, &storage))
+    }
+
+    fn eval(s: &str) -> CalcValue {
+        eval_at(1, s)
+    }
+
+    #[test]
+    fn parse_operators() {
+        for (input, op) in &[
+            ("2 + STR", Op::Add),
+            ("2 - STR", Op::Subtract),
+            ("2 * STR", Op::Multiply),
+            ("2 / STR", Op::Divide),
+        ] {
+            let parsed: Calculation = input.parse().unwrap();
+            let expected = Calculation::Op(
+                *op,
+                vec![
+                    Calculation::from_number(2),
+                    Calculation::Named("STR".into()),
+                ],
+            );
+            assert_eq!(parsed, expected);
+            assert_eq!(format!("{}", parsed).as_str(), *input);
+        }
+    }
+
+    #[test]
+    fn mixed_operators_need_parens() {
+        assert!("1 + 2 * 3".parse::<Calculation>().is_err());
+        assert!("1 - 2 + 3".parse::<Calculation>().is_err());
+        let parsed: Calculation = "1 + (2 * level)".parse().unwrap();
+        assert_eq!(format!("{}", parsed).as_str(), "(2 * level) + 1");
+        let parsed: Calculation = "(level - 1) - 2".parse().unwrap();
+        assert_eq!(format!("{}", parsed).as_str(), "level - 1 - 2");
+        let parsed: Calculation = "level - (1 - 2)".parse().unwrap();
+        assert_eq!(format!("{}", parsed).as_str(), "level - (1 - 2)");
+    }
+
+    #[test]
+    fn parse_dice() {
+        let parsed: Calculation = "4 + 2d8".parse().unwrap();
+        let expected = Calculation::Op(
+            Op::Add,
+            vec![
+                Calculation::Dice(DieRoll {
+                    count: 2,
+                    size: Die::D8,
+                }),
+                Calculation::from_number(4),
+            ],
+        );
+        assert_eq!(parsed, expected);
+        assert_eq!(format!("{}", parsed).as_str(), "2d8 + 4");
+    }
+
+    #[test]
+    fn evaluate_numbers() {
+        assert_eq!(eval("1 + 2 + 3"), CalcValue::Number(6));
+        assert_eq!(eval("10 - 4 - 1"), CalcValue::Number(5));
+        assert_eq!(eval("(10 - 4) * 3"), CalcValue::Number(18));
+        assert_eq!(eval("7 / 2"), CalcValue::Number(3));
+        assert_eq!(eval("-7 / 2"), CalcValue::Number(-4));
+        assert_eq!(eval("5 / 0"), CalcValue::Number(0));
+        assert_eq!(eval("STR + 2"), CalcValue::Number(12));
+    }
+
+    #[test]
+    fn evaluate_keeps_dice() {
+        let value = eval("2d8 + 3 + 1");
+        assert_eq!(value.as_number(), None);
+        assert_eq!(format!("{}", value).as_str(), "2d8 + 4");
+        let value = eval("1d6 + (STR - 8)");
+        assert_eq!(format!("{}", value).as_str(), "1d6 + 2");
+        let value = eval("(1d6 + 1) * 2");
+        assert_eq!(format!("{}", value).as_str(), "(1d6 + 1) * 2");
+    }
+
+    #[test]
+    fn builtin_values() {
+        assert_eq!(eval_at(7, "level"), CalcValue::Number(7));
+        assert_eq!(eval_at(7, "half level"), CalcValue::Number(3));
+        assert_eq!(eval_at(1, "half level"), CalcValue::Number(0));
+        assert_eq!(eval_at(7, "ceil(level / 2)"), CalcValue::Number(4));
+        assert_eq!(eval_at(7, "floor(level / 2)"), CalcValue::Number(3));
+        assert_eq!(eval_at(7, "ceil(level)"), CalcValue::Number(7));
+        // Only conditions have a value, and it doesn't take over the name.
+        assert_eq!(eval_at(7, "condition value"), CalcValue::Number(0));
+        assert_eq!(
+            "value".parse::<Calculation>().unwrap(),
+            Calculation::Named("value".into())
+        );
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("max(1, CON bonus)"), CalcValue::Number(1));
+        assert_eq!(eval("max(1, STR - 5)"), CalcValue::Number(5));
+        assert_eq!(eval("min(3, 5, -1)"), CalcValue::Number(-1));
+        assert_eq!(eval("if(STR >= 10, 2, 1)"), CalcValue::Number(2));
+        assert_eq!(eval("if(STR < 10, 2, 1)"), CalcValue::Number(1));
+        assert!("floor(1, 2)".parse::<Calculation>().is_err());
+        assert!("min()".parse::<Calculation>().is_err());
+        let value = eval("max(1d4, 2)");
+        assert_eq!(format!("{}", value).as_str(), "max(1d4, 2)");
+    }
+
+    #[test]
+    fn step_tables() {
+        let table = "table(5: 1, 11: 2)";
+        assert_eq!(eval_at(1, table), CalcValue::Number(0));
+        assert_eq!(eval_at(5, table), CalcValue::Number(1));
+        assert_eq!(eval_at(10, table), CalcValue::Number(1));
+        assert_eq!(eval_at(20, table), CalcValue::Number(2));
+        assert!("table(5: 1, 5: 2)".parse::<Calculation>().is_err());
+
+        let dice = "table(1: 1d6, 9: 2d6) + level";
+        assert_eq!(format!("{}", eval_at(9, dice)).as_str(), "2d6 + 9");
+    }
+
+    #[test]
+    fn functions_display_roundtrip() {
+        for input in &[
+            "half level",
+            "max(1, CON)",
+            "ceil(level / 2)",
+            "if(level >= 5, 2d6, 1d6)",
+            "table(1: 0, 5: 1, 11: 2)",
+            "2 + table(5: 1)",
+        ] {
+            let parsed: Calculation = input.parse().unwrap();
+            assert_eq!(format!("{}", parsed).as_str(), *input);
+        }
+    }
+
+    #[test]
+    fn calculated_string_table() {
+        let s: CalculatedString = "You gain a +[[ table(1: 1, 5: 2) ]] bonus."
+            .parse()
+            .unwrap();
+        let rref = ResourceRef::new("Test Resource", None::<&str>);
+        let storage = TestStorage::new(vec![]);
+        let text = s.evaluate(&character_at(6), &rref, &storage);
+        assert_eq!(text.as_str(), "You gain a +2 bonus.");
+    }
+
+    #[test]
+    fn cycles_depend_on_the_target() {
+        let damage = |target: &str| EvalStep::Modifier {
+            label: "weapon damage".into(),
+            target: Some(ResourceRef::new(target, None::<&str>)),
+        };
+        let (inner, cycles) = collect_cycles(|| {
+            checked_recurse(damage("Longsword"), || {
+                checked_recurse(damage("Dagger"), || {
+                    checked_recurse(damage("Longsword"), || ()).is_err()
+                })
+            })
+        });
+        assert_eq!(inner, Ok(Ok(true)));
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(
+            cycles[0].to_string(),
+            "weapon damage for Longsword depends on itself \
+             (weapon damage for Longsword → weapon damage for Dagger → weapon damage for Longsword)"
+        );
+    }
+}
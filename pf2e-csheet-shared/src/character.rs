@@ -1,22 +1,58 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use smallvec::{smallvec, SmallVec};
 use smartstring::alias::String;
 use std::{
     borrow::Borrow,
+    cell::RefCell,
     collections::{HashMap, HashSet},
     fmt,
+    sync::Arc,
 };
 use uuid::Uuid;
 
 use crate::{
-    bonuses::{Bonus, Modifier},
-    calc::CalcContext,
-    choices::{Choice, ChoiceMeta, ChoiceRef},
-    common::{Class, ResourceRef, ResourceType, TypedRef},
-    stats::Level,
+    abilities::{AbilityBoostError, AbilityScores, BoostSet, BoostSource},
+    bonuses::{Bonus, Modifier, Penalty},
+    breakdown::{ModifierBreakdown, ModifierSource},
+    calc::{checked_recurse, collect_cycles, CalcContext, EvalStep},
+    checks::DegreeOfSuccess,
+    choices::{Choice, ChoiceKind, ChoiceMeta, ChoiceRef},
+    common::{Class, Item, Resource, ResourceRef, ResourceType, TypedRef},
+    defenses::{Damage, Defenses},
+    effects::Effect,
+    eligibility::{Eligibility, ResourceQuery},
+    feats::{FeatCategory, FeatSlot, FeatSlotError},
+    inventory::{EquippedItem, Inventory, InventoryItem, ItemState},
+    items::{Armor, ArmorCategory, Shield, WeaponTrait},
+    levels::{
+        ChoiceSlot, LevelChoice, LevelChoiceKind, LevelError, LevelRecord, LevelUp, XP_PER_LEVEL,
+    },
+    play::{DamageOutcome, PlayError, PlayState},
+    spells::{FocusError, FocusPool, FocusSpell, SpellList, Spellcasting, Tradition},
+    stats::{Ability, AbilityBoost, ArmorClass, BulkLimits, Gold, Level, Proficiency, Size, Skill},
     storage::ResourceStorage,
+    strikes::{Strike, StrikeKind, WeaponSlot},
+    validation::{Diagnostic, ValidationReport},
+    wealth::{starting_wealth, Ledger, LedgerError, Transaction, WealthCheck},
 };
 
+thread_local! {
+    /// What's been worked out for the character being evaluated on this
+    /// thread, see [`Character::evaluating`].
+    static EVAL_CACHE: RefCell<Option<EvalCache>> = RefCell::new(None);
+}
+
+/// Results that can't change during an evaluation, since the character is
+/// borrowed the whole time.
+struct EvalCache {
+    /// Which character this is for.
+    character: *const Character,
+    effect_sources: HashMap<Option<ResourceRef>, Vec<ResourceRef>>,
+    encumbered: Option<bool>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Character {
     #[serde(default)]
@@ -30,6 +66,30 @@ pub struct Character {
     pub choice_values: HashMap<ResourceRef, HashMap<Choice, Value>>,
     #[serde(default)]
     pub core_choices: HashMap<Choice, Value>,
+    /// Whether the character has Raised a Shield, which adds the shield's
+    /// bonus to their AC.
+    #[serde(default)]
+    pub shield_raised: bool,
+    #[serde(default)]
+    pub inventory: Inventory,
+    #[serde(default)]
+    pub ledger: Ledger,
+    /// Spell repertoires, spellbooks and prepared spells, by spellcasting
+    /// entry name.
+    #[serde(default)]
+    pub spell_lists: HashMap<String, SpellList>,
+    /// Hit Points, dying, Hero Points and Focus Points as they stand at the
+    /// table.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub play: PlayState,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub xp: u32,
+    /// What was gained at each level after the first, oldest first.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub levels: Vec<LevelRecord>,
 }
 
 impl Character {
@@ -41,6 +101,13 @@ impl Character {
             resources: HashSet::new(),
             choice_values: HashMap::new(),
             core_choices: HashMap::new(),
+            shield_raised: false,
+            inventory: Inventory::default(),
+            ledger: Ledger::default(),
+            spell_lists: HashMap::new(),
+            play: PlayState::default(),
+            xp: 0,
+            levels: vec![],
         }
     }
 
@@ -51,6 +118,1195 @@ impl Character {
             .collect()
     }
 
+    pub fn has_resource(&self, wanted: &ResourceRef) -> bool {
+        self.resources.iter().any(|rref| rref.matches(wanted))
+    }
+
+    /// The items the character is using: everything in the inventory that
+    /// isn't stowed, plus items granted as resources, like unarmed attacks.
+    pub fn loadout(&self, resources: &dyn ResourceStorage) -> Vec<EquippedItem> {
+        let mut loadout = vec![];
+        for rref in self.get_resouces_by_type(ResourceType::Item) {
+            if let Some(Resource::Item(item)) = resources.lookup_immediate(rref).as_deref() {
+                loadout.push(EquippedItem {
+                    rref: rref.clone(),
+                    entry: None,
+                    state: None,
+                    item: item.clone(),
+                });
+            }
+        }
+        for entry in self.inventory.equipped() {
+            if let Some(item) = entry.resolve(resources) {
+                loadout.push(EquippedItem {
+                    rref: entry.item.clone().with_type(Some(ResourceType::Item)),
+                    entry: Some(entry.id),
+                    state: Some(entry.state),
+                    item,
+                });
+            }
+        }
+        loadout
+    }
+
+    /// The heaviest armor the character is wearing.
+    pub fn worn_armor(&self, resources: &dyn ResourceStorage) -> Option<Armor> {
+        self.worn_armor_item(resources).map(|(_, armor)| armor)
+    }
+
+    pub fn shield(&self, resources: &dyn ResourceStorage) -> Option<Shield> {
+        self.shield_item(resources).map(|(_, shield)| shield)
+    }
+
+    /// The heaviest armor the character is wearing, with the item it comes
+    /// from.
+    fn worn_armor_item(&self, resources: &dyn ResourceStorage) -> Option<(ResourceRef, Armor)> {
+        self.loadout(resources)
+            .into_iter()
+            .filter(EquippedItem::worn)
+            .filter_map(|equipped| Some((equipped.rref, equipped.item.armor?)))
+            .max_by_key(|(_, armor)| armor.category)
+    }
+
+    fn shield_item(&self, resources: &dyn ResourceStorage) -> Option<(ResourceRef, Shield)> {
+        self.loadout(resources)
+            .into_iter()
+            .filter(EquippedItem::held)
+            .find_map(|equipped| Some((equipped.rref, equipped.item.shield?)))
+    }
+
+    /// The inventory entry picked for an owned item choice.
+    pub fn get_owned_item<C>(&self, rref: &ResourceRef, choice: C) -> Option<&InventoryItem>
+    where
+        C: Borrow<ChoiceRef>,
+    {
+        let id = self.get_choice::<Uuid, _>(rref, choice)?;
+        self.inventory.get(id)
+    }
+
+    /// The resources whose effects apply to the character, or to `target`
+    /// when there is one. Along with the character's own resources, this
+    /// includes worn items (if they're invested, when they need to be) and
+    /// their property runes. Weapons and held items, and the runes etched on
+    /// them, only count when they're the `target`.
+    fn effect_sources(
+        &self,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<ResourceRef> {
+        let key = target.cloned();
+        if let Some(sources) = self
+            .with_eval_cache(|cache| cache.effect_sources.get(&key).cloned())
+            .flatten()
+        {
+            return sources;
+        }
+        let sources = self.collect_effect_sources(target, resources);
+        self.with_eval_cache(|cache| cache.effect_sources.insert(key, sources.clone()));
+        sources
+    }
+
+    fn collect_effect_sources(
+        &self,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<ResourceRef> {
+        let mut sources: Vec<ResourceRef> = self
+            .resources
+            .iter()
+            .filter(|rref| rref.resource_type != Some(ResourceType::Item))
+            .cloned()
+            .collect();
+        for equipped in self.loadout(resources) {
+            if equipped.is_scoped() && !target.map_or(false, |t| equipped.rref.matches(t)) {
+                continue;
+            }
+            let entry = equipped.entry.and_then(|id| self.inventory.get(id));
+            if equipped.item.has_trait("invested") && !entry.map_or(false, |e| e.invested) {
+                continue;
+            }
+            sources.push(equipped.rref);
+            if let Some(entry) = entry {
+                sources.extend(entry.runes.property.iter().cloned());
+            }
+        }
+        sources.extend(self.active_conditions(resources));
+        sources
+    }
+
+    /// Run `f` as one evaluation of the character, so that what every
+    /// modifier needs, like the resources whose effects apply, is only worked
+    /// out once. Evaluations started inside `f` share its results.
+    fn evaluating<T>(&self, f: impl FnOnce() -> T) -> T {
+        if self.with_eval_cache(|_| ()).is_some() {
+            return f();
+        }
+        let cache = EvalCache {
+            character: self,
+            effect_sources: HashMap::new(),
+            encumbered: None,
+        };
+        let outer = EVAL_CACHE.with(|current| current.replace(Some(cache)));
+
+        // Put the outer cache back even if `f` panics.
+        struct Restore(Option<EvalCache>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let outer = self.0.take();
+                EVAL_CACHE.with(|current| current.replace(outer));
+            }
+        }
+        let _restore = Restore(outer);
+        f()
+    }
+
+    /// Run `f` on the cache for the evaluation of this character, if one is
+    /// running.
+    fn with_eval_cache<T>(&self, f: impl FnOnce(&mut EvalCache) -> T) -> Option<T> {
+        EVAL_CACHE.with(|current| {
+            current
+                .borrow_mut()
+                .as_mut()
+                .filter(|cache| std::ptr::eq(cache.character, self))
+                .map(f)
+        })
+    }
+
+    /// Buy `quantity` of an item from the store, paying for it out of the
+    /// ledger. Returns the new inventory entry.
+    pub fn buy_item(
+        &mut self,
+        rref: &ResourceRef,
+        quantity: u32,
+        state: ItemState,
+        resources: &dyn ResourceStorage,
+        time: DateTime<Utc>,
+        note: impl Into<String>,
+    ) -> Result<Uuid, LedgerError> {
+        let price = match resources.lookup_immediate(rref).as_deref() {
+            Some(Resource::Item(item)) => item.price,
+            _ => return Err(LedgerError::NotAnItem(rref.clone())),
+        };
+        let transaction = Transaction::Buy {
+            item: rref.clone(),
+            quantity,
+        };
+        self.ledger
+            .record(transaction, price.checked_mul(quantity)?, time, note)?;
+        let mut entry = InventoryItem::new(rref.clone());
+        entry.quantity = quantity;
+        entry.state = state;
+        Ok(self.inventory.add(entry))
+    }
+
+    /// Sell `quantity` from an inventory entry for half price, removing the
+    /// entry if none are left. Returns the money gained.
+    pub fn sell_item(
+        &mut self,
+        entry_id: Uuid,
+        quantity: u32,
+        resources: &dyn ResourceStorage,
+        time: DateTime<Utc>,
+        note: impl Into<String>,
+    ) -> Result<Gold, LedgerError> {
+        let entry = self
+            .inventory
+            .get(entry_id)
+            .ok_or(LedgerError::NoSuchEntry(entry_id))?;
+        if entry.quantity < quantity {
+            return Err(LedgerError::NotEnoughOwned {
+                wanted: quantity,
+                owned: entry.quantity,
+            });
+        }
+        let item = entry
+            .resolve(resources)
+            .ok_or_else(|| LedgerError::NotAnItem(entry.item.clone()))?;
+        let amount = item.price.half().checked_mul(quantity)?;
+        let transaction = Transaction::Sell {
+            item: entry.item.clone(),
+            quantity,
+        };
+        self.ledger.record(transaction, amount, time, note)?;
+        if entry.quantity == quantity {
+            self.inventory.remove(entry_id);
+        } else if let Some(entry) = self.inventory.get_mut(entry_id) {
+            entry.quantity -= quantity;
+        }
+        Ok(amount)
+    }
+
+    /// Compare the character's gear and money to the starting wealth for
+    /// their level.
+    pub fn wealth_check(&self, resources: &dyn ResourceStorage) -> WealthCheck {
+        let level = self.level();
+        let items = self
+            .inventory
+            .iter()
+            .filter_map(|entry| {
+                let item = entry.resolve(resources)?;
+                let property: Gold = entry
+                    .runes
+                    .property
+                    .iter()
+                    .filter_map(|rune| match resources.lookup_immediate(rune).as_deref() {
+                        Some(Resource::Item(rune)) => Some(rune.price),
+                        _ => None,
+                    })
+                    .sum();
+                let each = item.price + entry.runes.fundamental_price(&item) + property;
+                Some(each.saturating_mul(entry.quantity))
+            })
+            .sum();
+        WealthCheck {
+            level,
+            allowed: starting_wealth(level),
+            items,
+            currency: self.ledger.balance(),
+        }
+    }
+
+    /// Every spellcasting entry the character's resources grant, sorted by
+    /// name.
+    pub fn spellcasting(&self, resources: &dyn ResourceStorage) -> Vec<Spellcasting> {
+        let mut entries = vec![];
+        for rref in self.effect_sources(None, resources) {
+            let resource = match resources.lookup_immediate(&rref) {
+                Some(r) => r,
+                None => continue,
+            };
+            let ctx = CalcContext::new(self, &rref, resources);
+            for effect in resource.get_spellcasting(ctx) {
+                match Spellcasting::new(self, &rref, &effect, resources) {
+                    Some(entry) => entries.push(entry),
+                    None => debug!(
+                        "Spellcasting entry {} from {} has no tradition yet",
+                        effect.name, rref
+                    ),
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries
+    }
+
+    /// The focus spells the character's resources grant, sorted by name.
+    pub fn focus_spells(&self, resources: &dyn ResourceStorage) -> Vec<FocusSpell> {
+        let mut spells = vec![];
+        for rref in self.effect_sources(None, resources) {
+            let resource = match resources.lookup_immediate(&rref) {
+                Some(r) => r,
+                None => continue,
+            };
+            let ctx = CalcContext::new(self, &rref, resources);
+            for effect in resource.get_focus_spells(ctx) {
+                spells.extend(FocusSpell::new(self, &rref, &effect, resources));
+            }
+        }
+        spells.sort_by(|a, b| a.name.cmp(&b.name));
+        spells
+    }
+
+    pub fn focus_pool(&self, resources: &dyn ResourceStorage) -> FocusPool {
+        let size = self
+            .get_modifier("Focus Pool Size", None, resources)
+            .total();
+        let max = size.max(0).min(FocusPool::MAX as i16) as u8;
+        FocusPool {
+            max,
+            current: max.saturating_sub(self.play.focus_spent),
+        }
+    }
+
+    /// Spend a Focus Point, returning how many are left.
+    pub fn spend_focus_point(&mut self, resources: &dyn ResourceStorage) -> Result<u8, FocusError> {
+        let pool = self.focus_pool(resources);
+        if pool.current == 0 {
+            return Err(FocusError::Empty);
+        }
+        self.play.focus_spent = pool.max - pool.current + 1;
+        Ok(pool.current - 1)
+    }
+
+    /// Refocus to regain a Focus Point, returning how many there are now.
+    pub fn refocus(&mut self, resources: &dyn ResourceStorage) -> u8 {
+        let pool = self.focus_pool(resources);
+        self.play.focus_spent = (pool.max - pool.current).saturating_sub(1);
+        self.focus_pool(resources).current
+    }
+
+    pub fn max_hp(&self, resources: &dyn ResourceStorage) -> u16 {
+        self.get_modifier("Max HP", None, resources).total().max(0) as u16
+    }
+
+    pub fn current_hp(&self, resources: &dyn ResourceStorage) -> u16 {
+        self.play.current_hp(self.max_hp(resources))
+    }
+
+    /// The character's resistances, weaknesses and immunities.
+    pub fn defenses(&self, resources: &dyn ResourceStorage) -> Defenses {
+        let mut defenses = Defenses::default();
+        for rref in self.effect_sources(None, resources) {
+            let resource = match resources.lookup_immediate(&rref) {
+                Some(r) => r,
+                None => continue,
+            };
+            let ctx = CalcContext::new(self, &rref, resources);
+            for (defense_type, kind, value) in resource.get_defenses(ctx) {
+                defenses.add(defense_type, kind, value);
+            }
+        }
+        defenses
+    }
+
+    /// Take damage after the character's resistances, weaknesses and
+    /// immunities apply to each part of it.
+    pub fn take_damage(
+        &mut self,
+        damage: &[Damage],
+        critical: bool,
+        resources: &dyn ResourceStorage,
+    ) -> DamageOutcome {
+        let defenses = self.defenses(resources);
+        let amount = damage
+            .iter()
+            .map(|d| defenses.apply(d))
+            .fold(0u16, u16::saturating_add);
+        let max_hp = self.max_hp(resources);
+        self.play.take_damage(amount, critical, max_hp)
+    }
+
+    pub fn heal(&mut self, amount: u16, resources: &dyn ResourceStorage) {
+        let max_hp = self.max_hp(resources);
+        self.play.heal(amount, max_hp)
+    }
+
+    fn lookup_condition(
+        rref: &ResourceRef,
+        resources: &dyn ResourceStorage,
+    ) -> Option<Arc<Resource>> {
+        let rref = rref.clone().with_type(Some(ResourceType::Condition));
+        resources
+            .lookup_immediate(&rref)
+            .filter(|r| matches!(**r, Resource::Condition(_)))
+    }
+
+    /// Put the character under a condition. `value` is ignored for
+    /// conditions without one.
+    pub fn apply_condition(
+        &mut self,
+        rref: &ResourceRef,
+        value: u8,
+        resources: &dyn ResourceStorage,
+    ) -> Result<(), PlayError> {
+        let resource = Self::lookup_condition(rref, resources)
+            .ok_or_else(|| PlayError::NotACondition(rref.clone()))?;
+        let valued = match &*resource {
+            Resource::Condition(c) => c.valued,
+            _ => false,
+        };
+        let value = if valued { value } else { 0 };
+        if valued && value == 0 {
+            return Ok(());
+        }
+        self.play
+            .apply_condition(resource.make_rref_no_mod(), value);
+        Ok(())
+    }
+
+    /// The value of a condition the character has, or 0.
+    pub fn condition_value(&self, name: &str) -> u8 {
+        self.play.condition(name).map_or(0, |c| c.value)
+    }
+
+    /// The value of a condition the character has, counting the conditions
+    /// the rules give them as well as the applied ones.
+    pub fn effective_condition_value(&self, name: &str, resources: &dyn ResourceStorage) -> u8 {
+        self.rules_conditions(resources)
+            .into_iter()
+            .filter(|(rref, _)| rref.name.eq_ignore_ascii_case(name))
+            .fold(self.condition_value(name), |value, (_, v)| value.max(v))
+    }
+
+    /// Conditions the character is under because of the rules rather than
+    /// the play state, with their values.
+    fn rules_conditions(&self, resources: &dyn ResourceStorage) -> Vec<(ResourceRef, u8)> {
+        let mut conditions = vec![];
+        // Being encumbered makes you clumsy 1.
+        if self.is_encumbered(resources) {
+            conditions.push((
+                ResourceRef::new("Clumsy", None::<&str>).with_type(Some(ResourceType::Condition)),
+                1,
+            ));
+        }
+        conditions
+    }
+
+    /// Whether the character is carrying more than their encumbered limit.
+    /// Conditions come into every modifier, so this is only worked out once
+    /// per evaluation.
+    fn is_encumbered(&self, resources: &dyn ResourceStorage) -> bool {
+        if let Some(encumbered) = self.with_eval_cache(|cache| cache.encumbered).flatten() {
+            return encumbered;
+        }
+        let encumbered = self.bulk_limits(resources).is_encumbered();
+        self.with_eval_cache(|cache| cache.encumbered = Some(encumbered));
+        encumbered
+    }
+
+    /// Every condition the character is under, including the ones implied
+    /// by other conditions or given by the rules.
+    pub fn active_conditions(&self, resources: &dyn ResourceStorage) -> Vec<ResourceRef> {
+        let mut active: Vec<ResourceRef> = vec![];
+        let mut pending: Vec<ResourceRef> = self
+            .play
+            .conditions
+            .iter()
+            .map(|c| c.condition.clone())
+            .chain(
+                self.rules_conditions(resources)
+                    .into_iter()
+                    .map(|(rref, _)| rref),
+            )
+            .collect();
+        while let Some(rref) = pending.pop() {
+            if active
+                .iter()
+                .any(|a| a.name.eq_ignore_ascii_case(&rref.name))
+            {
+                continue;
+            }
+            match Self::lookup_condition(&rref, resources).as_deref() {
+                Some(Resource::Condition(c)) => pending.extend(c.implies.iter().cloned()),
+                _ => debug!("Failed to find condition {}", rref),
+            }
+            active.push(rref.with_type(Some(ResourceType::Condition)));
+        }
+        active
+    }
+
+    /// Count down conditions like frightened at the end of the character's
+    /// turn, returning the ones that ran out.
+    pub fn end_turn(&mut self, resources: &dyn ResourceStorage) -> Vec<ResourceRef> {
+        let mut ended = vec![];
+        for active in self.play.conditions.iter_mut() {
+            let decreases = match Self::lookup_condition(&active.condition, resources).as_deref() {
+                Some(Resource::Condition(c)) => c.decreases,
+                _ => false,
+            };
+            if decreases {
+                active.value = active.value.saturating_sub(1);
+                if active.value == 0 {
+                    ended.push(active.condition.clone());
+                }
+            }
+        }
+        for rref in ended.iter() {
+            self.play.remove_condition(&rref.name);
+        }
+        ended
+    }
+
+    /// The ability chosen as the class's key ability.
+    pub fn key_ability(&self, resources: &dyn ResourceStorage) -> Option<Ability> {
+        self.ability_boost_sets(resources)
+            .into_iter()
+            .filter(|set| matches!(set.source, BoostSource::KeyAbility(_)))
+            .find_map(|set| set.applied().first().copied())
+    }
+
+    /// The level cantrips and focus spells are heightened to: half the
+    /// character's level, rounded up.
+    pub fn cantrip_level(&self) -> u8 {
+        (self.level().get() + 1) / 2
+    }
+
+    /// The character's size, from their ancestry.
+    pub fn size(&self, resources: &dyn ResourceStorage) -> Size {
+        self.get_resouces_by_type(ResourceType::Ancestry)
+            .into_iter()
+            .find_map(|rref| match resources.lookup_immediate(rref).as_deref() {
+                Some(Resource::Ancestry(ancestry)) => Some(ancestry.size),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// How much the character is carrying, and how much they can carry
+    /// before they're encumbered or can't carry any more.
+    pub fn bulk_limits(&self, resources: &dyn ResourceStorage) -> BulkLimits {
+        let str_mod = self.ability_scores(resources).modifier(Ability::STR);
+        BulkLimits::new(
+            self.inventory.total_bulk(resources),
+            str_mod,
+            self.size(resources),
+        )
+    }
+
+    /// The category of the worn armor, or unarmored if there isn't any.
+    pub fn armor_category(&self, resources: &dyn ResourceStorage) -> ArmorCategory {
+        self.worn_armor(resources)
+            .map_or(ArmorCategory::Unarmored, |armor| armor.category)
+    }
+
+    pub fn armor_class(&self, resources: &dyn ResourceStorage) -> ArmorClass {
+        let proficiency = match self.proficiency_target("AC", None, resources) {
+            Some(target) => self.get_proficiency(&target, None, resources).0,
+            None => Proficiency::Untrained,
+        };
+        ArmorClass {
+            dex_cap: self.worn_armor(resources).and_then(|armor| armor.dex_cap),
+            proficiency,
+            modifier: self.get_modifier("AC", None, resources),
+        }
+    }
+
+    /// Every Strike the character can make, sorted by weapon name. Melee
+    /// weapons that can be thrown get a ranged Strike too.
+    pub fn strikes(&self, resources: &dyn ResourceStorage) -> Vec<Strike> {
+        let mut strikes = vec![];
+        for EquippedItem { rref, mut item, .. } in self.loadout(resources) {
+            let added = self.added_weapon_traits(&rref, &item, resources);
+            let weapon = match item.weapon.as_mut() {
+                Some(w) => w,
+                None => continue,
+            };
+            for t in added {
+                if !weapon.has_trait(&t) {
+                    weapon.traits.push(t);
+                }
+            }
+            let mut kinds: SmallVec<[StrikeKind; 2]> = smallvec![];
+            if !weapon.is_ranged() {
+                kinds.push(StrikeKind::Melee);
+            }
+            if weapon.is_ranged() || weapon.thrown_range().is_some() {
+                kinds.push(StrikeKind::Ranged);
+            }
+            for kind in kinds {
+                let name = &item.common.name;
+                strikes.push(Strike::new(self, &rref, name, weapon, kind, resources));
+            }
+        }
+        strikes.sort_by(|a, b| a.name.cmp(&b.name));
+        strikes
+    }
+
+    /// The traits and materials the character's resources add to a weapon,
+    /// like the monk's unarmed attacks counting as magical.
+    fn added_weapon_traits(
+        &self,
+        rref: &ResourceRef,
+        item: &Item,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<WeaponTrait> {
+        let weapon = match &item.weapon {
+            Some(w) => w,
+            None => return vec![],
+        };
+        let mut traits = vec![];
+        for source in self.effect_sources(Some(rref), resources) {
+            let resource = match resources.lookup_immediate(&source) {
+                Some(r) => r,
+                None => continue,
+            };
+            let ctx = CalcContext::new(self, &source, resources).with_target(rref);
+            traits.extend(resource.get_weapon_traits(&item.common.name, weapon, ctx));
+        }
+        traits
+    }
+
+    /// The Strikes that fit on the character sheet, which has room for three
+    /// melee and three ranged weapons.
+    pub fn weapon_slots(&self, resources: &dyn ResourceStorage) -> Vec<(WeaponSlot, Strike)> {
+        let strikes = self.strikes(resources);
+        let mut slotted = vec![];
+        for kind in [StrikeKind::Melee, StrikeKind::Ranged].iter().copied() {
+            let of_kind = strikes.iter().filter(|s| s.kind == kind).cloned();
+            slotted.extend(WeaponSlot::slots(kind).iter().copied().zip(of_kind));
+        }
+        slotted
+    }
+
+    /// Values for the fields on the PDF character sheet, by field ID. Weapon
+    /// fields start with their row, like "Melee1WeaponName".
+    pub fn pdf_fields(&self, resources: &dyn ResourceStorage) -> Vec<(String, String)> {
+        self.evaluating(|| {
+            let numbers = self
+                .armor_class(resources)
+                .pdf_fields()
+                .into_iter()
+                .chain(self.play.pdf_fields(self.max_hp(resources)));
+            let mut fields: Vec<(String, String)> = numbers
+                .map(|(id, value)| (id.into(), format!("{}", value).into()))
+                .collect();
+            fields.extend(
+                self.defenses(resources)
+                    .pdf_fields()
+                    .into_iter()
+                    .map(|(id, value)| (id.into(), value)),
+            );
+            for (slot, strike) in self.weapon_slots(resources) {
+                fields.extend(
+                    strike
+                        .pdf_fields()
+                        .into_iter()
+                        .map(|(id, value)| (format!("{}{}", slot, id).into(), value)),
+                );
+            }
+            fields
+        })
+    }
+
+    /// 10 + DEX (up to the armor's cap), the armor's item bonus, and the
+    /// shield's bonus if it's raised.
+    fn base_armor_class(&self, resources: &dyn ResourceStorage) -> Vec<(ModifierSource, Modifier)> {
+        let armor = self.worn_armor_item(resources);
+        let mut dex = self.ability_scores(resources).modifier(Ability::DEX);
+        if let Some(cap) = armor.as_ref().and_then(|(_, a)| a.dex_cap) {
+            dex = dex.min(cap as i16);
+        }
+        let mut parts = vec![
+            (ModifierSource::rules("base"), Modifier::untyped(10)),
+            (
+                ModifierSource::rules("DEX modifier"),
+                Modifier::untyped(dex),
+            ),
+        ];
+        if let Some((rref, armor)) = armor {
+            parts.push((
+                ModifierSource::Resource(rref),
+                Bonus::item(armor.item_bonus()).into(),
+            ));
+        }
+        if self.shield_raised {
+            if let Some((rref, shield)) = self.shield_item(resources) {
+                parts.push((
+                    ModifierSource::Resource(rref),
+                    Bonus::circumstance(shield.ac_bonus).into(),
+                ));
+            }
+        }
+        parts
+    }
+
+    fn armor_check_penalty(
+        &self,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<(ModifierSource, Modifier)> {
+        let str_score = self.ability_scores(resources).get(Ability::STR);
+        match self.worn_armor_item(resources) {
+            Some((rref, armor)) if !armor.strong_enough(str_score) => vec![(
+                ModifierSource::Resource(rref),
+                Penalty::untyped(-(armor.check_penalty as i16)).into(),
+            )],
+            _ => vec![],
+        }
+    }
+
+    /// Armor slows you down less if you're strong enough for it, but shields
+    /// always do.
+    fn speed_penalty(&self, resources: &dyn ResourceStorage) -> Vec<(ModifierSource, Modifier)> {
+        let str_score = self.ability_scores(resources).get(Ability::STR);
+        let penalty = |value: u16| Penalty::untyped(-(value as i16)).into();
+        let mut parts = vec![];
+        if let Some((rref, armor)) = self.worn_armor_item(resources) {
+            let value = if armor.strong_enough(str_score) {
+                armor.speed_penalty.saturating_sub(5)
+            } else {
+                armor.speed_penalty
+            };
+            parts.push((ModifierSource::Resource(rref), penalty(value)));
+        }
+        if let Some((rref, shield)) = self.shield_item(resources) {
+            parts.push((
+                ModifierSource::Resource(rref),
+                penalty(shield.speed_penalty),
+            ));
+        }
+        if self.is_encumbered(resources) {
+            parts.push((ModifierSource::rules("encumbered"), penalty(10)));
+        }
+        parts
+    }
+
+    /// The ability a check or DC is based on, for effects that apply to all
+    /// checks using an ability (like clumsy).
+    fn check_ability(&self, label: &str, resources: &dyn ResourceStorage) -> Option<Ability> {
+        match label {
+            "AC" | "REF" => Some(Ability::DEX),
+            "FORT" => Some(Ability::CON),
+            "WILL" | "Perception" => Some(Ability::WIS),
+            "class DC" => self.key_ability(resources),
+            _ => label
+                .parse::<Skill>()
+                .ok()
+                .map(|skill| skill.base_ability()),
+        }
+    }
+
+    /// The modifier to every check and DC based on `ability`, from the
+    /// "DEX-based checks" style labels and "all checks".
+    pub fn ability_check_modifier(
+        &self,
+        ability: Ability,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Modifier {
+        self.get_modifier(&format!("{}-based checks", ability), target, resources)
+            + self.get_modifier("all checks", target, resources)
+    }
+
+    /// Every set of ability boosts the character gets, in the order they
+    /// apply, along with the abilities chosen for them so far.
+    pub fn ability_boost_sets(&self, resources: &dyn ResourceStorage) -> Vec<BoostSet> {
+        let mut sets = vec![];
+        for rref in self.get_resouces_by_type(ResourceType::Ancestry) {
+            if let Some(Resource::Ancestry(a)) = resources.lookup_immediate(rref).as_deref() {
+                let source = BoostSource::Ancestry(rref.clone());
+                let mut set = BoostSet::new(source, a.ability_boosts.iter().cloned());
+                set.flaws = a.ability_flaws.clone();
+                sets.push(set);
+            }
+        }
+        for rref in self.get_resouces_by_type(ResourceType::Background) {
+            if let Some(Resource::Background(b)) = resources.lookup_immediate(rref).as_deref() {
+                let source = BoostSource::Background(rref.clone());
+                sets.push(BoostSet::new(source, b.ability_boosts.iter().cloned()));
+            }
+        }
+        let class_rrefs = self.get_resouces_by_type(ResourceType::Class);
+        for rref in class_rrefs.iter().copied() {
+            if let Some(Resource::Class(c)) = resources.lookup_immediate(rref).as_deref() {
+                let key_ability = match c.key_ability.as_slice() {
+                    [] => continue,
+                    [ability] => AbilityBoost::Fixed(*ability),
+                    options => AbilityBoost::Choice(options.into()),
+                };
+                let source = BoostSource::KeyAbility(rref.clone());
+                sets.push(BoostSet::new(source, Some(key_ability)));
+            }
+        }
+        sets.push(BoostSet::new(
+            BoostSource::Creation,
+            vec![AbilityBoost::Free; 4],
+        ));
+        let level = self.level();
+        for rref in class_rrefs {
+            for boost_level in [5, 10, 15, 20].iter().copied().map(Level::from) {
+                if boost_level <= level {
+                    let source = BoostSource::Level(rref.clone(), boost_level);
+                    sets.push(BoostSet::new(source, vec![AbilityBoost::Free; 4]));
+                }
+            }
+        }
+        for set in sets.iter_mut() {
+            let choice = set.choice();
+            set.chosen = match &set.source {
+                BoostSource::Creation => self
+                    .get_character_choice::<Vec<Ability>, _>(choice)
+                    .unwrap_or_default()
+                    .into(),
+                BoostSource::KeyAbility(rref) => self
+                    .get_choice::<Ability, _>(rref, choice)
+                    .into_iter()
+                    .collect(),
+                BoostSource::Ancestry(rref)
+                | BoostSource::Background(rref)
+                | BoostSource::Level(rref, _) => self
+                    .get_choice::<Vec<Ability>, _>(rref, choice)
+                    .unwrap_or_default()
+                    .into(),
+            };
+        }
+        sets
+    }
+
+    /// The feat slots the character's ancestry, background and class give
+    /// them up to their level, along with the feats picked for them.
+    pub fn feat_slots(&self, resources: &dyn ResourceStorage) -> Vec<FeatSlot> {
+        let mut slots = vec![];
+        let ancestries = self.get_resouces_by_type(ResourceType::Ancestry);
+        let ancestry_trait: Option<String> = ancestries
+            .first()
+            .map(|rref| rref.name.to_lowercase().as_str().into());
+        for rref in ancestries {
+            slots.push(FeatSlot::new(
+                rref.clone(),
+                FeatCategory::Ancestry,
+                Level::from(1),
+                ancestry_trait.clone(),
+            ));
+        }
+        for rref in self.get_resouces_by_type(ResourceType::Background) {
+            if let Some(Resource::Background(b)) = resources.lookup_immediate(rref).as_deref() {
+                if let Some(feat) = b.skill_feat.as_ref() {
+                    let mut slot = FeatSlot::new(
+                        rref.clone(),
+                        FeatCategory::Skill,
+                        Level::from(1),
+                        Some("skill".into()),
+                    );
+                    slot.chosen = Some(feat.clone().as_runtime());
+                    slot.fixed = true;
+                    slots.push(slot);
+                }
+            }
+        }
+        let level = self.level();
+        for rref in self.get_resouces_by_type(ResourceType::Class) {
+            let class = match resources.lookup_immediate(rref).as_deref() {
+                Some(Resource::Class(c)) => c.clone(),
+                _ => continue,
+            };
+            let class_name = class.common.name.as_str();
+            for (slot_level, features) in class.advancement.range(..=level) {
+                for feature in features {
+                    let category = match FeatCategory::from_feature_name(&feature.name, class_name)
+                    {
+                        Some(category) => category,
+                        None => continue,
+                    };
+                    let feat_trait = match category {
+                        FeatCategory::Ancestry => ancestry_trait.clone(),
+                        FeatCategory::Class => Some(class_name.to_lowercase().as_str().into()),
+                        FeatCategory::Skill => Some("skill".into()),
+                        FeatCategory::General => Some("general".into()),
+                    };
+                    slots.push(FeatSlot::new(
+                        rref.clone(),
+                        category,
+                        *slot_level,
+                        feat_trait,
+                    ));
+                }
+            }
+        }
+        // A source can grant more than one slot of a kind at a level, and each
+        // needs its own choice.
+        for i in 0..slots.len() {
+            let index = slots[..i]
+                .iter()
+                .filter(|other| {
+                    other.source == slots[i].source
+                        && other.level == slots[i].level
+                        && other.category == slots[i].category
+                })
+                .count();
+            slots[i].index = index as u8;
+        }
+        for slot in slots.iter_mut().filter(|slot| !slot.fixed) {
+            slot.chosen = self.get_choice::<ResourceRef, _>(&slot.source, slot.choice());
+        }
+        slots.sort_by_key(|slot| (slot.level, slot.category));
+        slots
+    }
+
+    /// Pick `feat` for a feat slot, replacing the feat that was there before.
+    pub fn choose_feat(
+        &mut self,
+        slot: &FeatSlot,
+        feat: &ResourceRef,
+        resources: &dyn ResourceStorage,
+    ) -> Result<(), FeatSlotError> {
+        if slot.fixed {
+            return Err(FeatSlotError::Fixed(format!("{}", slot)));
+        }
+        match resources.lookup_immediate(feat).as_deref() {
+            Some(Resource::Feat(f)) => slot.check(feat, f)?,
+            _ => return Err(FeatSlotError::NotAFeat(feat.clone())),
+        }
+        let old = self.get_choice::<ResourceRef, _>(&slot.source, slot.choice());
+        self.set_choice(&slot.source, slot.choice(), feat)
+            .expect("Resource references always serialize");
+        if let Some(old) = old {
+            if !self.feat_held_elsewhere(&old, resources) {
+                self.resources.remove(&old);
+                self.choice_values.remove(&old);
+            }
+        }
+        self.resources.insert(feat.clone());
+        self.normalize_resources(resources);
+        Ok(())
+    }
+
+    /// Whether `feat` is still picked for a feat slot or granted by one of the
+    /// character's resources, so replacing it in one slot shouldn't remove it.
+    fn feat_held_elsewhere(&self, feat: &ResourceRef, resources: &dyn ResourceStorage) -> bool {
+        if self
+            .feat_slots(resources)
+            .iter()
+            .any(|slot| slot.chosen.as_ref() == Some(feat))
+        {
+            return true;
+        }
+        self.resources.iter().any(|rref| {
+            let r = match resources.lookup_immediate(rref) {
+                Some(r) => r,
+                None => return false,
+            };
+            let ctx = CalcContext {
+                character: self,
+                rref,
+                target: None,
+                resources,
+            };
+            r.granted_resources(ctx).contains(feat)
+        })
+    }
+
+    pub fn ability_scores(&self, resources: &dyn ResourceStorage) -> AbilityScores {
+        AbilityScores::from_boosts(&self.ability_boost_sets(resources))
+    }
+
+    pub fn validate_ability_boosts(
+        &self,
+        resources: &dyn ResourceStorage,
+    ) -> Result<(), Vec<AbilityBoostError>> {
+        let errors: Vec<AbilityBoostError> = self
+            .ability_boost_sets(resources)
+            .iter()
+            .filter_map(|set| set.validate().err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Whether `rref` is an entry in the class's advancement that the sheet
+    /// models as a slot or choice rather than a class feature, like "monk
+    /// feat" or "ability boosts". See [`Class::grants_feature`].
+    fn is_builtin_advancement(&self, rref: &ResourceRef, resources: &dyn ResourceStorage) -> bool {
+        if rref.resource_type != Some(ResourceType::ClassFeature) {
+            return false;
+        }
+        self.get_resouces_by_type(ResourceType::Class)
+            .into_iter()
+            .any(
+                |class_rref| match resources.lookup_immediate(class_rref).as_deref() {
+                    Some(Resource::Class(class)) => class
+                        .advancement
+                        .values()
+                        .flatten()
+                        .any(|f| f.name == rref.name && !class.grants_feature(f)),
+                    _ => false,
+                },
+            )
+    }
+
+    /// Check the character against the rules and the resources they're built
+    /// from, reporting everything that's wrong or still to be chosen.
+    pub fn validate(&self, resources: &dyn ResourceStorage) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        let mut classes: Vec<ResourceRef> = self
+            .get_resouces_by_type(ResourceType::Class)
+            .into_iter()
+            .cloned()
+            .collect();
+        if classes.len() > 1 {
+            classes.sort();
+            report.push(Diagnostic::MultipleClasses { classes });
+        }
+
+        let mut rrefs: Vec<&ResourceRef> = self.resources.iter().collect();
+        rrefs.sort();
+        for rref in rrefs {
+            let resource = match resources.lookup_immediate(rref) {
+                Some(r) => r,
+                // Characters saved before feat slots and boosts were tracked
+                // separately can still have them as class features.
+                None if self.is_builtin_advancement(rref, resources) => continue,
+                None => {
+                    report.push(Diagnostic::MissingResource {
+                        resource: rref.clone(),
+                    });
+                    continue;
+                }
+            };
+            let ctx = CalcContext::new(self, rref, resources);
+            if resource.resource_type() == ResourceType::Feat
+                && resource.common().prerequisites.reject(ctx)
+            {
+                report.push(Diagnostic::UnmetPrerequisites {
+                    resource: rref.clone(),
+                });
+            }
+
+            let mut choices: Vec<(&Choice, &ChoiceMeta)> = resource.all_choices().collect();
+            choices.sort_by_key(|(choice, _)| *choice);
+            for (choice, meta) in choices {
+                let value = if meta.character_wide {
+                    self.core_choices.get(choice)
+                } else {
+                    self.choice_values.get(rref).and_then(|m| m.get(choice))
+                };
+                let value = match value {
+                    Some(v) => v,
+                    None => {
+                        if meta.key {
+                            report.push(Diagnostic::MissingChoice {
+                                resource: rref.clone(),
+                                choice: choice.clone(),
+                                kind: meta.kind(),
+                            });
+                        }
+                        continue;
+                    }
+                };
+                if let Err(e) = meta.kind.check_value(value) {
+                    report.push(Diagnostic::InvalidChoice {
+                        resource: rref.clone(),
+                        choice: choice.clone(),
+                        expected: meta.kind(),
+                        value: value.clone(),
+                        message: format!("{}", e),
+                    });
+                    continue;
+                }
+                if let ChoiceKind::Resource { .. } = meta.kind {
+                    if let Ok(chosen) = serde_json::from_value::<ResourceRef>(value.clone()) {
+                        if resources.lookup_immediate(&chosen).is_none() {
+                            report.push(Diagnostic::MissingResource { resource: chosen });
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Err(errors) = self.validate_ability_boosts(resources) {
+            for error in errors {
+                report.push(Diagnostic::AbilityBoost(error));
+            }
+        }
+
+        for slot in self.feat_slots(resources) {
+            let chosen = match slot.chosen.as_ref() {
+                Some(rref) => rref,
+                None => {
+                    report.push(Diagnostic::EmptyFeatSlot(slot));
+                    continue;
+                }
+            };
+            match resources.lookup_immediate(chosen).as_deref() {
+                Some(Resource::Feat(feat)) => {
+                    if let Err(e) = slot.check(chosen, feat) {
+                        report.push(Diagnostic::FeatSlot(e));
+                    }
+                }
+                Some(_) => report.push(Diagnostic::FeatSlot(FeatSlotError::NotAFeat(
+                    chosen.clone(),
+                ))),
+                None => report.push(Diagnostic::MissingResource {
+                    resource: chosen.clone(),
+                }),
+            }
+        }
+
+        let level = self.level();
+        let max = Proficiency::max_skill_rank(level);
+        let mut increased: Vec<Skill> = vec![];
+        for (_, skill) in self.skill_increases(resources) {
+            if !increased.iter().any(|s| s.is_same(&skill)) {
+                increased.push(skill);
+            }
+        }
+        for skill in increased {
+            let name = format!("{}", skill);
+            let base = self
+                .proficiency_aliases(&name)
+                .iter()
+                .map(|name| self.get_proficiency_rank(name, None, resources))
+                .max()
+                .unwrap_or_default();
+            let rank = self
+                .skill_increases(resources)
+                .into_iter()
+                .filter(|(_, s)| s.is_same(&skill))
+                .fold(base, |r, _| r.step_up());
+            if rank > max && rank > base {
+                report.push(Diagnostic::SkillRankTooHigh {
+                    skill,
+                    rank,
+                    max,
+                    level,
+                });
+            }
+        }
+
+        // Work out everything on the sheet, so that resources whose values
+        // depend on themselves get reported rather than just logged.
+        let (_, cycles) = collect_cycles(|| self.evaluating(|| self.evaluate_sheet(resources)));
+        for cycle in cycles {
+            let known = report.diagnostics.iter().any(|d| match d {
+                Diagnostic::Cycle(c) => c.is_same_loop(&cycle),
+                _ => false,
+            });
+            if !known {
+                report.push(Diagnostic::Cycle(cycle));
+            }
+        }
+
+        report
+    }
+
+    /// Calculate every modifier and proficiency that's on the sheet or that
+    /// the character's resources change.
+    fn evaluate_sheet(&self, resources: &dyn ResourceStorage) {
+        let mut labels: Vec<String> = [
+            "AC",
+            "FORT",
+            "REF",
+            "WILL",
+            "Perception",
+            "class DC",
+            "Max HP",
+            "Speed",
+            "Focus Pool Size",
+        ]
+        .iter()
+        .map(|&label| label.into())
+        .collect();
+        labels.extend(self.skills(resources).iter().map(|s| format!("{}", s)));
+        // Weapons and held items only change things while they're being used.
+        let mut targets: Vec<Option<ResourceRef>> = vec![None];
+        targets.extend(
+            self.loadout(resources)
+                .into_iter()
+                .filter(EquippedItem::is_scoped)
+                .map(|equipped| Some(equipped.rref)),
+        );
+        for target in targets.iter().map(Option::as_ref) {
+            let mut labels = match target {
+                Some(_) => vec![],
+                None => labels.clone(),
+            };
+            let mut proficiencies: Vec<String> = vec![];
+            for rref in self.effect_sources(target, resources).iter() {
+                let resource = match resources.lookup_immediate(rref) {
+                    Some(r) => r,
+                    None => continue,
+                };
+                for effect in resource.common().effects() {
+                    match effect {
+                        Effect::AddBonus(e) => labels.push(e.target.clone()),
+                        Effect::AddPenalty(e) => labels.push(e.target.clone()),
+                        Effect::IncreaseProficiency(e) => proficiencies.push(e.target.clone()),
+                        _ => (),
+                    }
+                }
+            }
+            for label in labels.iter() {
+                self.get_modifier(label, target, resources);
+            }
+            for name in proficiencies.iter() {
+                self.get_proficiency(name, target, resources);
+            }
+        }
+    }
+
     pub fn normalize_resources(&mut self, resources: &dyn ResourceStorage) {
         debug!("Normalizing character {}", self);
         let mut changes = true;
@@ -92,30 +1348,387 @@ impl Character {
 
     pub fn get_modifier(
         &self,
-        name: &str,
+        name: &str,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Modifier {
+        match target {
+            Some(t) => trace!(
+                "Asking character {} for modifier {} targeting {}",
+                self.name,
+                name,
+                t
+            ),
+            None => trace!("Asking character {} for modifier {}", self.name, name),
+        }
+        let mut m = Modifier::new();
+        for (_, part) in self.modifier_contributions(name, target, resources) {
+            m += part;
+        }
+        m
+    }
+
+    /// Every bonus and penalty that goes into the modifier `name`, with where
+    /// it comes from and whether the stacking rules let it apply. The total
+    /// is always the same as [`Character::get_modifier`].
+    pub fn modifier_breakdown(
+        &self,
+        name: &str,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> ModifierBreakdown {
+        ModifierBreakdown::new(name, self.modifier_contributions(name, target, resources))
+    }
+
+    /// The parts of the modifier `name` from each source, before they're
+    /// added together. A modifier that depends on itself counts as nothing
+    /// inside its own calculation.
+    fn modifier_contributions(
+        &self,
+        name: &str,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<(ModifierSource, Modifier)> {
+        let step = EvalStep::Modifier {
+            label: name.into(),
+            target: target.cloned(),
+        };
+        self.evaluating(|| {
+            checked_recurse(step, || {
+                self.unchecked_modifier_contributions(name, target, resources)
+            })
+            .unwrap_or_default()
+        })
+    }
+
+    fn unchecked_modifier_contributions(
+        &self,
+        name: &str,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<(ModifierSource, Modifier)> {
+        let mut parts = match name {
+            "STR" | "DEX" | "CON" | "INT" | "WIS" | "CHA" => {
+                let ability: Ability = name.parse().expect("Failed to parse ability name");
+                let score = self.ability_scores(resources).get(ability);
+                vec![(
+                    ModifierSource::rules("ability score"),
+                    Bonus::untyped(score).into(),
+                )]
+            }
+            "STR bonus" | "DEX bonus" | "CON bonus" | "INT bonus" | "WIS bonus" | "CHA bonus" => {
+                let ability: Ability = name[..3].parse().expect("Failed to parse ability name");
+                let scores = self.ability_scores(resources);
+                vec![(
+                    ModifierSource::rules(format!("{} {}", ability, scores.get(ability))),
+                    Modifier::untyped(scores.modifier(ability)),
+                )]
+            }
+            "AC" => self.base_armor_class(resources),
+            "FORT" | "REF" | "WILL" | "Perception" => {
+                let ability = self
+                    .check_ability(name, resources)
+                    .expect("Saves and Perception are based on an ability");
+                let mut parts = vec![self.ability_modifier_part(ability, resources)];
+                if name != "Perception" {
+                    if let Some((rref, armor)) = self.worn_armor_item(resources) {
+                        parts.push((
+                            ModifierSource::Resource(rref),
+                            Bonus::item(armor.resilient as u16).into(),
+                        ));
+                    }
+                }
+                parts
+            }
+            "class DC" => {
+                let mut parts = vec![(ModifierSource::rules("base"), Modifier::untyped(10))];
+                if let Some(ability) = self.key_ability(resources) {
+                    parts.push(self.ability_modifier_part(ability, resources));
+                }
+                parts
+            }
+            "Max HP" => {
+                let con = self.ability_scores(resources).modifier(Ability::CON);
+                let level = self.level().get() as i16;
+                vec![(
+                    ModifierSource::rules(format!("CON modifier at level {}", level)),
+                    Modifier::untyped(con * level),
+                )]
+            }
+            "armor check penalty" => self.armor_check_penalty(resources),
+            "Speed" => self.speed_penalty(resources),
+            _ => vec![],
+        };
+        if let Some(ability) = self.check_ability(name, resources) {
+            let ability_label = format!("{}-based checks", ability);
+            parts.extend(self.modifier_contributions(&ability_label, target, resources));
+            parts.extend(self.modifier_contributions("all checks", target, resources));
+        }
+        if let Ok(skill) = name.parse::<Skill>() {
+            let ability = skill.base_ability();
+            parts.push(self.ability_modifier_part(ability, resources));
+            if ability == Ability::STR || ability == Ability::DEX {
+                let acp = self.modifier_contributions("armor check penalty", target, resources);
+                parts.extend(
+                    acp.into_iter()
+                        .map(|(source, m)| (source, m.penalty_part().into())),
+                );
+            }
+        }
+        if let Some(prof_target) = self.proficiency_target(name, target, resources) {
+            let (rank, bonus) = self.get_proficiency(&prof_target, target, resources);
+            trace!(
+                "{} is {:?} in {} for {}",
+                self.name,
+                rank,
+                prof_target,
+                name
+            );
+            let label = format!("{} in {}", rank, prof_target);
+            parts.push((ModifierSource::rules(label), bonus.into()));
+        }
+        for rref in self.effect_sources(target, resources).iter() {
+            let mut ctx = CalcContext::new(self, rref, resources);
+            if let Some(target) = target {
+                ctx = ctx.with_target(target);
+            }
+            let resource = match resources.lookup_immediate(rref) {
+                Some(r) => r,
+                None => {
+                    debug!(
+                        "Failed to find resource {} when looking up a modifier",
+                        rref
+                    );
+                    continue;
+                }
+            };
+            trace!("Checking resource {}", rref);
+            for (effect, resource_mod) in resource.modifier_parts(name, ctx) {
+                trace!("Got modifier value {}", resource_mod);
+                let source = match effect {
+                    Some(effect) => ModifierSource::Effect {
+                        resource: rref.clone(),
+                        effect: Box::new(effect.clone()),
+                    },
+                    None => ModifierSource::Resource(rref.clone()),
+                };
+                parts.push((source, resource_mod));
+            }
+        }
+        parts
+    }
+
+    fn ability_modifier_part(
+        &self,
+        ability: Ability,
+        resources: &dyn ResourceStorage,
+    ) -> (ModifierSource, Modifier) {
+        let ability_mod = self.ability_scores(resources).modifier(ability);
+        (
+            ModifierSource::rules(format!("{} modifier", ability)),
+            Modifier::untyped(ability_mod),
+        )
+    }
+
+    /// Which proficiency feeds into the modifier `label`, if any. Attacks
+    /// need a `target` weapon to know which proficiency to use.
+    fn proficiency_target(
+        &self,
+        label: &str,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Option<String> {
+        match label {
+            "AC" => {
+                let category = match self.armor_category(resources) {
+                    ArmorCategory::Unarmored => "unarmored defense",
+                    ArmorCategory::LightArmor => "light armor",
+                    ArmorCategory::MediumArmor => "medium armor",
+                    ArmorCategory::HeavyArmor => "heavy armor",
+                };
+                Some(category.into())
+            }
+            "FORT" | "REF" | "WILL" | "Perception" | "class DC" => Some(label.into()),
+            "attack" => {
+                let weapon = match resources.lookup_immediate(target?).as_deref() {
+                    Some(Resource::Item(item)) => item.clone(),
+                    _ => return None,
+                };
+                if let Some(w) = &weapon.weapon {
+                    return Some(w.category.proficiency().into());
+                }
+                if weapon.has_trait("unarmed") {
+                    return Some("unarmed attacks".into());
+                }
+                ["simple", "martial", "advanced"]
+                    .iter()
+                    .find(|category| weapon.has_trait(category))
+                    .map(|category| format!("{} weapons", category))
+            }
+            _ if spell_label_tradition(label).is_some() => Some(label.into()),
+            _ => label
+                .parse::<Skill>()
+                .ok()
+                .map(|skill| format!("{}", skill)),
+        }
+    }
+
+    /// Other names the same proficiency goes by in resources.
+    fn proficiency_aliases(&self, target: &str) -> SmallVec<[String; 2]> {
+        let mut names: SmallVec<[String; 2]> = smallvec![target.into()];
+        match target {
+            "FORT" => names.push("Fortitude".into()),
+            "REF" => names.push("Reflex".into()),
+            "class DC" => {
+                if let Some((class, _)) = self.get_class_and_level() {
+                    names.push(format!("{} class DC", class.name));
+                }
+            }
+            _ => {
+                // One proficiency covers both spell attacks and spell DCs.
+                if let Some(tradition) = spell_label_tradition(target) {
+                    names.push(format!("{} spellcasting", tradition));
+                }
+            }
+        }
+        names
+    }
+
+    /// The highest proficiency rank any of the character's resources grant in
+    /// `target` (like "Perception", "FORT", "simple weapons" or "athletics"),
+    /// along with the bonus it gives at the character's level. A proficiency
+    /// that depends on itself counts as untrained inside its own calculation.
+    pub fn get_proficiency(
+        &self,
+        target: &str,
+        item: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> (Proficiency, Bonus) {
+        let step = EvalStep::Proficiency {
+            target: target.into(),
+            item: item.cloned(),
+        };
+        self.evaluating(|| {
+            checked_recurse(step, || self.unchecked_proficiency(target, item, resources))
+                .unwrap_or_else(|_| (Proficiency::Untrained, Bonus::none()))
+        })
+    }
+
+    fn unchecked_proficiency(
+        &self,
+        target: &str,
+        item: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> (Proficiency, Bonus) {
+        let level = self.level();
+        let mut rank = self
+            .proficiency_aliases(target)
+            .iter()
+            .map(|name| self.get_proficiency_rank(name, item, resources))
+            .max()
+            .unwrap_or_default();
+        if let Ok(skill) = target.parse::<Skill>() {
+            let increased = self
+                .skill_increases(resources)
+                .into_iter()
+                .filter(|(_, s)| s.is_same(&skill))
+                .fold(rank, |r, _| r.step_up());
+            rank = rank.max(increased.min(Proficiency::max_skill_rank(level)));
+        }
+        (rank, Bonus::proficiency(rank, level))
+    }
+
+    /// The rank used to attack with `weapon`, or untrained if it isn't a
+    /// weapon.
+    pub fn weapon_proficiency(
+        &self,
+        weapon: &ResourceRef,
+        resources: &dyn ResourceStorage,
+    ) -> Proficiency {
+        match self.proficiency_target("attack", Some(weapon), resources) {
+            Some(target) => self.get_proficiency(&target, Some(weapon), resources).0,
+            None => Proficiency::Untrained,
+        }
+    }
+
+    /// Every skill increase the character has chosen a skill for, along with
+    /// the resource it was chosen on.
+    pub fn skill_increases(&self, resources: &dyn ResourceStorage) -> Vec<(ResourceRef, Skill)> {
+        let mut increases = vec![];
+        for rref in self.resources.iter() {
+            let ctx = CalcContext::new(self, rref, resources);
+            if let Some(resource) = resources.lookup_immediate(rref) {
+                for skill in resource.get_skill_increases(ctx) {
+                    increases.push((rref.clone(), skill));
+                }
+            }
+        }
+        increases
+    }
+
+    /// All the standard skills, followed by each lore skill the character is
+    /// trained in or has increased.
+    pub fn skills(&self, resources: &dyn ResourceStorage) -> Vec<Skill> {
+        let mut skills: Vec<Skill> = Skill::standard().collect();
+        let mut lores: Vec<Skill> = vec![];
+        for rref in self.resources.iter() {
+            let ctx = CalcContext::new(self, rref, resources);
+            if let Some(resource) = resources.lookup_immediate(rref) {
+                let trained = resource
+                    .get_proficiency_grants(ctx)
+                    .into_iter()
+                    .filter_map(|(target, _)| target.parse::<Skill>().ok());
+                lores.extend(trained);
+            }
+        }
+        lores.extend(self.skill_increases(resources).into_iter().map(|(_, s)| s));
+        for lore in lores {
+            if let Skill::Lore(_) = lore {
+                if !skills.iter().any(|s| s.is_same(&lore)) {
+                    skills.push(lore);
+                }
+            }
+        }
+        skills
+    }
+
+    pub fn get_proficiency_rank(
+        &self,
+        name: &str,
+        target: Option<&ResourceRef>,
+        resources: &dyn ResourceStorage,
+    ) -> Proficiency {
+        let mut rank = Proficiency::Untrained;
+        for rref in self.effect_sources(target, resources).iter() {
+            let mut ctx = CalcContext::new(self, rref, resources);
+            if let Some(target) = target {
+                ctx = ctx.with_target(target);
+            }
+            let resource = match resources.lookup_immediate(rref) {
+                Some(r) => r,
+                None => {
+                    debug!(
+                        "Failed to find resource {} when looking up proficiency",
+                        rref
+                    );
+                    continue;
+                }
+            };
+            if let Some(p) = resource.get_proficiency(name, ctx) {
+                rank = rank.max(p);
+            }
+        }
+        rank
+    }
+
+    pub fn get_degree_overrides(
+        &self,
+        check: &str,
         target: Option<&ResourceRef>,
         resources: &dyn ResourceStorage,
-    ) -> Modifier {
-        match target {
-            Some(t) => trace!(
-                "Asking character {} for modifier {} targeting {}",
-                self.name,
-                name,
-                t
-            ),
-            None => trace!("Asking character {} for modifier {}", self.name, name),
-        }
-        let mut m = match name {
-            "STR" | "DEX" | "CON" | "INT" | "WIS" | "CHA" => Bonus::untyped(10).into(),
-            "STR bonus" | "DEX bonus" | "CON bonus" | "INT bonus" | "WIS bonus" | "CHA bonus" => {
-                let base = self.get_modifier(&name[..3], target, resources);
-                let total = base.total();
-                let bonus = Bonus::untyped((total - 10) / 2);
-                bonus.into()
-            }
-            _ => Modifier::new(),
-        };
-        for rref in self.resources.iter() {
+    ) -> Vec<(DegreeOfSuccess, DegreeOfSuccess)> {
+        let mut overrides = vec![];
+        for rref in self.effect_sources(target, resources).iter() {
             let mut ctx = CalcContext::new(self, rref, resources);
             if let Some(target) = target {
                 ctx = ctx.with_target(target);
@@ -124,18 +1737,40 @@ impl Character {
                 Some(r) => r,
                 None => {
                     debug!(
-                        "Failed to find resource {} when looking up a modifier",
+                        "Failed to find resource {} when looking up degree of success overrides",
                         rref
                     );
                     continue;
                 }
             };
-            trace!("Checking resource {}", rref);
-            let resource_mod = resource.get_modifier(name, ctx);
-            trace!("Got modifier value {}", resource_mod);
-            m += resource_mod;
+            overrides.extend(resource.get_degree_overrides(check, ctx));
         }
-        m
+        overrides
+    }
+
+    /// Resolve a check against `dc`, where `natural` is the number showing on
+    /// the d20. The modifier comes from `check`, and any of the character's
+    /// effects that change the degree of success for `check` are applied.
+    pub fn degree_of_success(
+        &self,
+        check: &str,
+        target: Option<&ResourceRef>,
+        dc: i16,
+        natural: u16,
+        resources: &dyn ResourceStorage,
+    ) -> DegreeOfSuccess {
+        let modifier = self.get_modifier(check, target, resources);
+        let degree = DegreeOfSuccess::resolve(&modifier, dc, natural);
+        trace!(
+            "{} rolled a natural {} with {} on {} against DC {}: {}",
+            self.name,
+            natural,
+            modifier,
+            check,
+            dc,
+            degree
+        );
+        degree.with_overrides(self.get_degree_overrides(check, target, resources))
     }
 
     pub fn set_character_choice<T>(
@@ -237,6 +1872,26 @@ impl Character {
         }
     }
 
+    /// The value of a choice an effect names (like "$save"): the one made on
+    /// `rref` if there is one, or else the character-wide choice of that name
+    /// another resource declares.
+    pub fn get_effect_choice<T, C>(&self, rref: &ResourceRef, choice: C) -> Option<T>
+    where
+        T: serde::de::DeserializeOwned,
+        C: Borrow<ChoiceRef>,
+    {
+        let choice = choice.borrow();
+        let on_resource = self
+            .choice_values
+            .get(rref)
+            .map_or(false, |map| map.contains_key(choice));
+        if on_resource {
+            self.get_choice(rref, choice)
+        } else {
+            self.get_character_choice(choice)
+        }
+    }
+
     pub fn remove_choice(&mut self, rref: &ResourceRef, choice: impl Borrow<ChoiceRef>) {
         let resource_map = match self.choice_values.get_mut(rref) {
             Some(map) => map,
@@ -263,6 +1918,44 @@ impl Character {
                 },
             )
     }
+
+    /// Check each of `candidates` against the character's prerequisites for
+    /// it. Candidates that don't match `query`, or can't be found, are left
+    /// out.
+    pub fn check_eligibility(
+        &self,
+        candidates: impl IntoIterator<Item = ResourceRef>,
+        query: &ResourceQuery,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<Eligibility> {
+        let mut results: Vec<Eligibility> = candidates
+            .into_iter()
+            .filter_map(|rref| {
+                let resource = resources.lookup_immediate(&rref)?;
+                if !query.matches(&resource) {
+                    return None;
+                }
+                let ctx = CalcContext::new(self, &rref, resources);
+                let unmet = resource.common().prerequisites.unmet(ctx);
+                Some(Eligibility { rref, unmet })
+            })
+            .collect();
+        results.sort_by(|a, b| a.rref.cmp(&b.rref));
+        results
+    }
+
+    /// Every resource in storage that matches `query`, marked with whether
+    /// the character meets its prerequisites.
+    pub async fn eligible_resources(
+        &self,
+        query: &ResourceQuery,
+        resources: &dyn ResourceStorage,
+    ) -> Vec<Eligibility> {
+        let candidates = resources.all_by_type(query.resource_type).await;
+        let rrefs: Vec<&ResourceRef> = candidates.iter().collect();
+        resources.lookup_async(&rrefs).await;
+        self.check_eligibility(candidates.iter().cloned(), query, resources)
+    }
 }
 
 impl fmt::Display for Character {
@@ -273,8 +1966,8 @@ impl fmt::Display for Character {
 
 impl Character {
     pub fn get_class_and_level(&self) -> Option<(TypedRef<Class>, Level)> {
+        // Having more than one class is reported by `validate`.
         let class_rrefs = self.get_resouces_by_type(ResourceType::Class);
-        debug_assert!(class_rrefs.len() < 2);
         let class_rref_dyn: &ResourceRef = class_rrefs.get(0)?;
         let class_rref: TypedRef<Class> = class_rref_dyn.clone().as_typed().ok()?;
         let level = self
@@ -282,4 +1975,1446 @@ impl Character {
             .unwrap_or(1.into());
         Some((class_rref, level))
     }
+
+    fn class_rref(&self) -> Result<ResourceRef, LevelError> {
+        self.get_resouces_by_type(ResourceType::Class)
+            .first()
+            .map(|rref| (*rref).clone())
+            .ok_or(LevelError::NoClass)
+    }
+
+    fn set_level(&mut self, class: &ResourceRef, level: Level) {
+        self.set_choice(class, "Level".into(), &level)
+            .expect("Levels always serialize");
+    }
+
+    /// Spend XP to gain a level, adding the new class features and reporting
+    /// the choices the new level needs.
+    pub fn level_up(&mut self, resources: &dyn ResourceStorage) -> Result<LevelUp, LevelError> {
+        let class = self.class_rref()?;
+        let level = self.level();
+        if level.get() >= 20 {
+            return Err(LevelError::MaxLevel);
+        }
+        if self.xp < XP_PER_LEVEL {
+            return Err(LevelError::NotEnoughXp(self.xp));
+        }
+        let level = Level::from(level.get() + 1);
+        self.xp -= XP_PER_LEVEL;
+        self.set_level(&class, level);
+        let before = self.resources.clone();
+        self.normalize_resources(resources);
+        let mut granted: Vec<ResourceRef> = self.resources.difference(&before).cloned().collect();
+        granted.sort();
+
+        let mut choices = vec![];
+        for rref in granted.iter() {
+            let resource = match resources.lookup_immediate(rref) {
+                Some(r) => r,
+                None => continue,
+            };
+            for (choice, meta) in resource.all_choices() {
+                choices.push(LevelChoice {
+                    slot: ChoiceSlot::new(rref.clone(), choice.clone()),
+                    kind: LevelChoiceKind::Declared(meta.clone()),
+                });
+            }
+            for effect in resource.common().effects() {
+                if let Effect::SkillIncrease(e) = effect {
+                    choices.push(LevelChoice {
+                        slot: ChoiceSlot::new(rref.clone(), e.choice.clone()),
+                        kind: LevelChoiceKind::SkillIncrease,
+                    });
+                }
+            }
+        }
+        for slot in self.feat_slots(resources) {
+            if slot.level == level && !slot.fixed {
+                choices.push(LevelChoice {
+                    slot: ChoiceSlot::new(slot.source.clone(), slot.choice()),
+                    kind: LevelChoiceKind::Feat(slot.category),
+                });
+            }
+        }
+        if level.get() % 5 == 0 {
+            let source = BoostSource::Level(class.clone(), level);
+            choices.push(LevelChoice {
+                slot: ChoiceSlot::new(class, source.choice()),
+                kind: LevelChoiceKind::AbilityBoosts(source),
+            });
+        }
+
+        let up = LevelUp {
+            level,
+            granted,
+            choices,
+        };
+        self.levels.push(LevelRecord::new(&up));
+        Ok(up)
+    }
+
+    /// Undo the last level gained, removing what it granted and the choices
+    /// made for it, and refunding its XP.
+    pub fn level_down(
+        &mut self,
+        resources: &dyn ResourceStorage,
+    ) -> Result<LevelRecord, LevelError> {
+        let class = self.class_rref()?;
+        let level = self.level();
+        if level.get() <= 1 {
+            return Err(LevelError::MinLevel);
+        }
+        let record = match self.levels.iter().rposition(|r| r.level == level) {
+            Some(index) => self.levels.remove(index),
+            None => {
+                // Leveled by hand, so only the class features and the feat
+                // slots are known.
+                let granted = match resources.lookup_immediate(&class).as_deref() {
+                    Some(Resource::Class(c)) => c.features_at(level),
+                    _ => vec![],
+                };
+                let feat_choices: Vec<ChoiceSlot> = self
+                    .feat_slots(resources)
+                    .into_iter()
+                    .filter(|slot| slot.level == level && !slot.fixed)
+                    .map(|slot| ChoiceSlot::new(slot.source.clone(), slot.choice()))
+                    .collect();
+                LevelRecord {
+                    level,
+                    granted,
+                    choices: feat_choices.clone(),
+                    resource_choices: feat_choices,
+                }
+            }
+        };
+        let chosen: Vec<ResourceRef> = record
+            .resource_choices
+            .iter()
+            .filter_map(|slot| {
+                self.get_choice::<ResourceRef, _>(&slot.resource, slot.choice.clone())
+            })
+            .collect();
+        for slot in record.choices.iter() {
+            self.remove_choice(&slot.resource, slot.choice.clone());
+        }
+        for rref in chosen.iter() {
+            if !self.feat_held_elsewhere(rref, resources) && self.resources.remove(rref) {
+                self.choice_values.remove(rref);
+            }
+        }
+        for rref in record.granted.iter() {
+            self.resources.remove(rref);
+            self.choice_values.remove(rref);
+        }
+        self.set_level(&class, Level::from(level.get() - 1));
+        self.xp += XP_PER_LEVEL;
+        Ok(record)
+    }
+
+    /// The character's level, treating characters without a class as first
+    /// level.
+    pub fn level(&self) -> Level {
+        self.get_class_and_level()
+            .map(|(_, level)| level)
+            .unwrap_or(1.into())
+    }
+}
+
+/// The tradition in a spell attack or spell DC label, like "arcane spell
+/// attack".
+fn spell_label_tradition(label: &str) -> Option<Tradition> {
+    label
+        .strip_suffix(" spell attack")
+        .or_else(|| label.strip_suffix(" spell DC"))?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bonuses::BonusType,
+        calc::Calculation,
+        common::{Ancestry, Background, ClassFeature, Condition, Feat, Item, ResourceCommon},
+        cond::{Conditions, ProficiencyCondition, SingleCondition},
+        defenses::DamageKind,
+        effects::{
+            BonusEffect, DefenseEffect, Effect, EffectCommon, IncreaseProficiencyEffect,
+            PenaltyEffect, SkillIncreaseEffect,
+        },
+        items::{ItemType, Weapon, WeaponCategory},
+        stats::{DamageType, Die},
+        test_helpers::{
+            character_with, crb_conditions, crb_monk, proficiency_feat, traited_feat, TestStorage,
+        },
+    };
+
+    #[test]
+    fn highest_rank_wins() {
+        let resources = vec![
+            proficiency_feat("Initial", &[("Perception", Proficiency::Trained)]),
+            proficiency_feat("Alertness", &[("perception", Proficiency::Expert)]),
+        ];
+        let character = character_with(&resources);
+        let storage = TestStorage::new(resources);
+
+        let (rank, bonus) = character.get_proficiency("Perception", None, &storage);
+        assert_eq!(rank, Proficiency::Expert);
+        assert_eq!(bonus, Bonus::proficiency(Proficiency::Expert, 1.into()));
+        let (rank, bonus) = character.get_proficiency("WILL", None, &storage);
+        assert_eq!(rank, Proficiency::Untrained);
+        assert_eq!(bonus, Bonus::default());
+        assert_eq!(
+            character.get_modifier("Perception", None, &storage).total(),
+            5
+        );
+    }
+
+    #[test]
+    fn saves_skills_and_ac() {
+        let resources = vec![proficiency_feat(
+            "Initial",
+            &[
+                ("Fortitude", Proficiency::Expert),
+                ("athletics", Proficiency::Trained),
+                ("unarmored defense", Proficiency::Master),
+            ],
+        )];
+        let character = character_with(&resources);
+        let storage = TestStorage::new(resources);
+
+        assert_eq!(character.get_modifier("FORT", None, &storage).total(), 5);
+        assert_eq!(character.get_modifier("REF", None, &storage).total(), 0);
+        assert_eq!(
+            character.get_modifier("Athletics", None, &storage).total(),
+            3
+        );
+        assert_eq!(character.get_modifier("AC", None, &storage).total(), 17);
+    }
+
+    #[test]
+    fn chosen_target() {
+        let path = proficiency_feat("Path to Perfection", &[("$save", Proficiency::Master)]);
+        let path_rref = path.make_rref_no_mod();
+        let mut character = character_with(&[path.clone()]);
+        let storage = TestStorage::new(vec![path]);
+        assert_eq!(
+            character.get_proficiency("REF", None, &storage).0,
+            Proficiency::Untrained
+        );
+
+        character
+            .set_choice(&path_rref, "save".into(), &"REF")
+            .unwrap();
+        assert_eq!(
+            character.get_proficiency("REF", None, &storage).0,
+            Proficiency::Master
+        );
+        assert_eq!(
+            character.get_proficiency("FORT", None, &storage).0,
+            Proficiency::Untrained
+        );
+    }
+
+    #[test]
+    fn ability_scores_from_boosts() {
+        let dwarf = Resource::Ancestry(Ancestry {
+            common: ResourceCommon::new("Dwarf"),
+            ability_boosts: smallvec![
+                AbilityBoost::Fixed(Ability::CON),
+                AbilityBoost::Fixed(Ability::WIS),
+                AbilityBoost::Free,
+            ],
+            ability_flaws: smallvec![Ability::CHA],
+            hp: 10,
+            size: Size::Medium,
+        });
+        let acolyte = Resource::Background(Background {
+            common: ResourceCommon::new("Acolyte"),
+            ability_boosts: smallvec![
+                AbilityBoost::Choice(smallvec![Ability::INT, Ability::WIS]),
+                AbilityBoost::Free,
+            ],
+            skill_feat: None,
+        });
+        let monk = Resource::Class(Class {
+            common: ResourceCommon::new("Monk"),
+            key_ability: smallvec![Ability::STR, Ability::DEX],
+            hp_per_level: Calculation::from_number(10),
+            advancement: Default::default(),
+        });
+        let resources = vec![dwarf, acolyte, monk];
+        let mut character = character_with(&resources);
+        let storage = TestStorage::new(resources.clone());
+        let rrefs: Vec<ResourceRef> = resources.iter().map(|r| r.make_rref_no_mod()).collect();
+
+        assert_eq!(character.get_modifier("STR", None, &storage).total(), 10);
+        assert_eq!(character.get_modifier("CHA", None, &storage).total(), 8);
+
+        let boosts = |abilities: &[Ability]| abilities.to_vec();
+        character
+            .set_choice(&rrefs[0], "Ability Boosts".into(), &boosts(&[Ability::STR]))
+            .unwrap();
+        character
+            .set_choice(
+                &rrefs[1],
+                "Ability Boosts".into(),
+                &boosts(&[Ability::WIS, Ability::STR]),
+            )
+            .unwrap();
+        character
+            .set_choice(&rrefs[2], "Key Ability".into(), &Ability::STR)
+            .unwrap();
+        character
+            .set_character_choice(
+                "Ability Boosts".into(),
+                &boosts(&[Ability::STR, Ability::DEX, Ability::CON, Ability::WIS]),
+            )
+            .unwrap();
+        assert_eq!(character.validate_ability_boosts(&storage), Ok(()));
+
+        let scores = character.ability_scores(&storage);
+        assert_eq!(scores.get(Ability::STR), 18);
+        assert_eq!(scores.get(Ability::DEX), 12);
+        assert_eq!(scores.get(Ability::CON), 14);
+        assert_eq!(scores.get(Ability::INT), 10);
+        assert_eq!(scores.get(Ability::WIS), 16);
+        assert_eq!(scores.get(Ability::CHA), 8);
+        assert_eq!(
+            character.get_modifier("STR bonus", None, &storage).total(),
+            4
+        );
+        assert_eq!(
+            character.get_modifier("CHA bonus", None, &storage).total(),
+            -1
+        );
+        let totals: Vec<i16> = ["FORT", "REF", "WILL", "Perception", "class DC"]
+            .iter()
+            .map(|label| character.get_modifier(label, None, &storage).total())
+            .collect();
+        assert_eq!(totals, vec![2, 1, 3, 3, 14]);
+        // The dwarf's 10 HP, plus the monk's 10 and CON +2 each level.
+        assert_eq!(character.max_hp(&storage), 22);
+
+        character
+            .set_choice(&rrefs[2], "Level".into(), &Level::from(5))
+            .unwrap();
+        assert_eq!(character.max_hp(&storage), 70);
+        character
+            .set_choice(
+                &rrefs[2],
+                "Level 5 Ability Boosts".into(),
+                &boosts(&[Ability::STR, Ability::STR]),
+            )
+            .unwrap();
+        let errors = character.validate_ability_boosts(&storage).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![AbilityBoostError::Duplicate {
+                from: BoostSource::Level(rrefs[2].clone(), 5.into()),
+                ability: Ability::STR,
+            }]
+        );
+
+        character
+            .set_choice(
+                &rrefs[2],
+                "Level 5 Ability Boosts".into(),
+                &boosts(&[Ability::STR, Ability::DEX, Ability::CON, Ability::WIS]),
+            )
+            .unwrap();
+        assert_eq!(character.validate_ability_boosts(&storage), Ok(()));
+        let scores = character.ability_scores(&storage);
+        assert_eq!(scores.get(Ability::STR), 19);
+        assert_eq!(scores.get(Ability::WIS), 18);
+    }
+
+    #[test]
+    fn skill_increases_respect_caps() {
+        let mut common = ResourceCommon::new("Skill Increase");
+        common.add_effect(SkillIncreaseEffect {
+            common: EffectCommon::default(),
+            choice: "skill".into(),
+        });
+        let increase = Resource::ClassFeature(ClassFeature {
+            common,
+            class: TypedRef::new("Monk", None::<&str>),
+        });
+        let monk = Resource::Class(Class {
+            common: ResourceCommon::new("Monk"),
+            key_ability: smallvec![Ability::STR],
+            hp_per_level: Calculation::from_number(10),
+            advancement: Default::default(),
+        });
+        let training = proficiency_feat(
+            "Initial",
+            &[
+                ("athletics", Proficiency::Trained),
+                ("Lore (Circus)", Proficiency::Trained),
+            ],
+        );
+        let mut character = character_with(&[monk.clone(), training.clone()]);
+        let monk_rref = monk.make_rref_no_mod();
+        let storage = TestStorage::new(vec![increase, monk, training]);
+        for level in &["level 3", "level 5"] {
+            let rref = ResourceRef::new("Skill Increase", Some(*level))
+                .with_type(Some(ResourceType::ClassFeature));
+            character.resources.insert(rref.clone());
+            character
+                .set_choice(&rref, "skill".into(), &Skill::Athletics)
+                .unwrap();
+        }
+
+        let rank = |character: &Character| character.get_proficiency("Athletics", None, &storage).0;
+        assert_eq!(character.skill_increases(&storage).len(), 2);
+        assert_eq!(rank(&character), Proficiency::Trained);
+        character
+            .set_choice(&monk_rref, "Level".into(), &Level::from(5))
+            .unwrap();
+        assert_eq!(rank(&character), Proficiency::Expert);
+        character
+            .set_choice(&monk_rref, "Level".into(), &Level::from(7))
+            .unwrap();
+        assert_eq!(rank(&character), Proficiency::Master);
+        assert_eq!(
+            character.get_modifier("Athletics", None, &storage).total(),
+            14
+        );
+
+        let skills = character.skills(&storage);
+        assert_eq!(skills.len(), 17);
+        assert_eq!(skills[16], Skill::Lore("Circus".into()));
+        assert_eq!(
+            character
+                .get_modifier("lore (circus)", None, &storage)
+                .total(),
+            9
+        );
+        assert_eq!(
+            character
+                .get_proficiency("Lore (Sailing)", None, &storage)
+                .0,
+            Proficiency::Untrained
+        );
+    }
+
+    #[test]
+    fn armor_check_penalty() {
+        let mut common = ResourceCommon::new("Heavy Pack");
+        common.add_effect(PenaltyEffect {
+            common: EffectCommon::default(),
+            penalty_type: BonusType::Untyped,
+            target: "armor check penalty".into(),
+            value: Calculation::from_number(-2),
+        });
+        let pack = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let character = character_with(&[pack.clone()]);
+        let storage = TestStorage::new(vec![pack]);
+        assert_eq!(
+            character.get_modifier("Athletics", None, &storage).total(),
+            -2
+        );
+        assert_eq!(
+            character.get_modifier("Stealth", None, &storage).total(),
+            -2
+        );
+        assert_eq!(character.get_modifier("Arcana", None, &storage).total(), 0);
+    }
+
+    fn armor_item(name: &str, armor: Option<Armor>, shield: Option<Shield>) -> Resource {
+        Resource::Item(Item {
+            armor,
+            shield,
+            ..Item::new(ResourceCommon::new(name), ItemType::Armor)
+        })
+    }
+
+    #[test]
+    fn armor_class() {
+        let full_plate = Armor {
+            ac_bonus: 6,
+            dex_cap: Some(0),
+            check_penalty: 3,
+            speed_penalty: 10,
+            strength: Some(18),
+            potency: 1,
+            ..Armor::new(ArmorCategory::HeavyArmor)
+        };
+        let shield = Shield {
+            ac_bonus: 2,
+            hardness: 5,
+            hp: 20,
+            broken_threshold: 10,
+            speed_penalty: 0,
+        };
+        let resources = vec![
+            armor_item("Full Plate", Some(full_plate), None),
+            armor_item("Steel Shield", None, Some(shield)),
+            proficiency_feat(
+                "Initial",
+                &[
+                    ("heavy armor", Proficiency::Trained),
+                    ("unarmored defense", Proficiency::Expert),
+                ],
+            ),
+        ];
+        let mut character = character_with(&resources);
+        character
+            .set_character_choice(
+                "Ability Boosts".into(),
+                &vec![Ability::DEX, Ability::STR, Ability::CON, Ability::WIS],
+            )
+            .unwrap();
+        let storage = TestStorage::new(resources);
+
+        let ac = character.armor_class(&storage);
+        assert_eq!(ac.dex_cap, Some(0));
+        assert_eq!(ac.proficiency, Proficiency::Trained);
+        assert_eq!(ac.item_bonus(), 7);
+        assert_eq!(ac.proficiency_bonus(), 3);
+        assert_eq!(ac.total(), 20);
+        assert_eq!(
+            ac.pdf_fields(),
+            vec![
+                ("ACDexCap", 0),
+                ("ACItemBonus", 7),
+                ("ACArmorProficiency", 3),
+                ("TotalAC", 20),
+            ]
+        );
+
+        character.shield_raised = true;
+        assert_eq!(character.armor_class(&storage).total(), 22);
+        let fields = character.pdf_fields(&storage);
+        assert!(fields.contains(&("TotalAC".into(), "22".into())));
+
+        // STR 12 is below full plate's threshold.
+        assert_eq!(
+            character.get_modifier("Athletics", None, &storage).total(),
+            -2
+        );
+        assert_eq!(
+            character.get_modifier("Acrobatics", None, &storage).total(),
+            -2
+        );
+        assert_eq!(character.get_modifier("Arcana", None, &storage).total(), 0);
+        assert_eq!(character.get_modifier("Speed", None, &storage).total(), -10);
+
+        let sources = |label: &str| {
+            character
+                .modifier_breakdown(label, None, &storage)
+                .parts
+                .into_iter()
+                .filter_map(|part| part.source.resource().map(|r| r.name.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sources("AC"), vec!["Full Plate", "Steel Shield"]);
+        assert_eq!(sources("Athletics"), vec!["Full Plate"]);
+        assert_eq!(sources("Speed"), vec!["Full Plate"]);
+    }
+
+    #[test]
+    fn strength_threshold() {
+        let full_plate = Armor {
+            check_penalty: 3,
+            speed_penalty: 10,
+            strength: Some(12),
+            ..Armor::new(ArmorCategory::HeavyArmor)
+        };
+        let resources = vec![armor_item("Full Plate", Some(full_plate), None)];
+        let mut character = character_with(&resources);
+        character
+            .set_character_choice("Ability Boosts".into(), &vec![Ability::STR])
+            .unwrap();
+        let storage = TestStorage::new(resources);
+        assert_eq!(
+            character.get_modifier("Athletics", None, &storage).total(),
+            1
+        );
+        assert_eq!(character.get_modifier("Speed", None, &storage).total(), -5);
+        assert_eq!(character.armor_class(&storage).dex_cap, None);
+    }
+
+    #[test]
+    fn attacks_use_weapon_category() {
+        let mut common = ResourceCommon::new("Club");
+        common.traits.push("Simple".into());
+        let club = Resource::Item(Item::new(common, ItemType::Weapon));
+        let club_rref = club.make_rref_no_mod();
+        let resources = vec![
+            club,
+            proficiency_feat(
+                "Initial",
+                &[
+                    ("simple weapons", Proficiency::Trained),
+                    ("martial weapons", Proficiency::Expert),
+                ],
+            ),
+        ];
+        let character = character_with(&resources);
+        let storage = TestStorage::new(resources);
+
+        let attack = character.get_modifier("attack", Some(&club_rref), &storage);
+        assert_eq!(attack.total(), 3);
+        assert_eq!(character.get_modifier("attack", None, &storage).total(), 0);
+    }
+
+    fn condition(
+        name: &str,
+        valued: bool,
+        penalty_type: BonusType,
+        target: &str,
+        value: &str,
+    ) -> Condition {
+        let mut common = ResourceCommon::new(name);
+        common.add_effect(PenaltyEffect {
+            common: EffectCommon::default(),
+            penalty_type,
+            target: target.into(),
+            value: value.parse().unwrap(),
+        });
+        let mut condition = Condition::new(common);
+        condition.valued = valued;
+        condition
+    }
+
+    #[test]
+    fn conditions() {
+        let mut frightened = condition(
+            "Frightened",
+            true,
+            BonusType::Status,
+            "all checks",
+            "condition value",
+        );
+        frightened.decreases = true;
+        let clumsy = condition(
+            "Clumsy",
+            true,
+            BonusType::Status,
+            "DEX-based checks",
+            "condition value",
+        );
+        let off_guard = condition("Off-Guard", false, BonusType::Circumstance, "AC", "2");
+        let mut grabbed = Condition::new(ResourceCommon::new("Grabbed"));
+        grabbed
+            .implies
+            .push(ResourceRef::new("Off-Guard", None::<&str>));
+        let resources: Vec<Resource> = vec![frightened, clumsy, off_guard, grabbed]
+            .into_iter()
+            .map(Resource::Condition)
+            .collect();
+        let rrefs: Vec<ResourceRef> = resources.iter().map(|r| r.make_rref_no_mod()).collect();
+        let not_a_condition = proficiency_feat("Toughness", &[]);
+        let storage = TestStorage::new(
+            resources
+                .into_iter()
+                .chain(std::iter::once(not_a_condition.clone())),
+        );
+        let mut character = Character::new("Test Character");
+        let ac = |c: &Character| c.get_modifier("AC", None, &storage).total();
+        let base_ac = ac(&character);
+
+        character.apply_condition(&rrefs[0], 2, &storage).unwrap();
+        assert_eq!(
+            character.get_modifier("Perception", None, &storage).total(),
+            -2
+        );
+        assert_eq!(ac(&character), base_ac - 2);
+
+        // Status penalties don't stack, so only the worst applies.
+        character.apply_condition(&rrefs[1], 1, &storage).unwrap();
+        assert_eq!(character.get_modifier("REF", None, &storage).total(), -2);
+        character.apply_condition(&rrefs[1], 3, &storage).unwrap();
+        assert_eq!(character.get_modifier("REF", None, &storage).total(), -3);
+        character.apply_condition(&rrefs[1], 1, &storage).unwrap();
+        assert_eq!(character.condition_value("clumsy"), 3);
+
+        character.apply_condition(&rrefs[3], 5, &storage).unwrap();
+        assert_eq!(character.condition_value("Grabbed"), 0);
+        assert_eq!(character.active_conditions(&storage).len(), 4);
+        assert_eq!(ac(&character), base_ac - 5);
+
+        assert!(character.end_turn(&storage).is_empty());
+        assert_eq!(character.condition_value("Frightened"), 1);
+        assert_eq!(character.end_turn(&storage), vec![rrefs[0].clone()]);
+        assert_eq!(ac(&character), base_ac - 5);
+        assert_eq!(
+            character.get_modifier("Perception", None, &storage).total(),
+            0
+        );
+
+        let err = character
+            .apply_condition(&not_a_condition.make_rref_no_mod(), 1, &storage)
+            .unwrap_err();
+        assert!(matches!(err, PlayError::NotACondition(_)));
+    }
+
+    #[test]
+    fn crb_condition_effects() {
+        let fist = Resource::Item(Item {
+            weapon: Some(Weapon::new(WeaponCategory::Unarmed, Die::D4, DamageType::B)),
+            ..Item::new(ResourceCommon::new("Fist"), ItemType::Weapon)
+        });
+        let wizard = Resource::Class(Class {
+            common: ResourceCommon::new("Wizard"),
+            key_ability: smallvec![Ability::INT],
+            hp_per_level: Calculation::from_number(6),
+            advancement: Default::default(),
+        });
+        let mut character = character_with(&[fist.clone(), wizard.clone()]);
+        let storage = TestStorage::new(crb_conditions().into_iter().chain(vec![fist, wizard]));
+        let rref = |name: &str| ResourceRef::new(name, None::<&str>);
+        let total = |c: &Character, label: &str| c.get_modifier(label, None, &storage).total();
+        let base_ac = total(&character, "AC");
+        assert_eq!(total(&character, "class DC"), 11);
+
+        character
+            .apply_condition(&rref("Frightened"), 2, &storage)
+            .unwrap();
+        assert_eq!(total(&character, "AC"), base_ac - 2);
+        assert_eq!(total(&character, "class DC"), 9);
+        for label in ["FORT", "REF", "Perception", "Athletics"].iter() {
+            assert_eq!(total(&character, label), -2, "{}", label);
+        }
+
+        // Clumsy only affects DEX-based checks, and doesn't stack with
+        // frightened.
+        character
+            .apply_condition(&rref("Clumsy"), 3, &storage)
+            .unwrap();
+        assert_eq!(total(&character, "REF"), -3);
+        assert_eq!(total(&character, "Stealth"), -3);
+        assert_eq!(total(&character, "FORT"), -2);
+        character.play.remove_condition("Frightened");
+        assert_eq!(total(&character, "FORT"), 0);
+        assert_eq!(total(&character, "AC"), base_ac - 3);
+
+        // Enfeebled only affects Strength-based damage.
+        character
+            .apply_condition(&rref("Enfeebled"), 2, &storage)
+            .unwrap();
+        assert_eq!(total(&character, "Athletics"), -2);
+        assert_eq!(total(&character, "weapon damage"), 0);
+        assert_eq!(character.strikes(&storage)[0].damage_bonus(), -2);
+
+        // Fatigued's penalty is written as 1, but it's still a penalty.
+        character
+            .apply_condition(&rref("Fatigued"), 0, &storage)
+            .unwrap();
+        assert_eq!(total(&character, "WILL"), -1);
+        assert_eq!(total(&character, "REF"), -3);
+
+        character
+            .apply_condition(&rref("Grabbed"), 0, &storage)
+            .unwrap();
+        let active = character.active_conditions(&storage);
+        assert!(active.iter().any(|c| c.name == "Off-Guard"));
+        assert!(active.iter().any(|c| c.name == "Immobilized"));
+        assert_eq!(total(&character, "AC"), base_ac - 5);
+    }
+
+    #[test]
+    fn resistances_apply_to_damage() {
+        let mut common = ResourceCommon::new("Troll Blood");
+        common.add_effect(BonusEffect {
+            common: EffectCommon::default(),
+            bonus_type: BonusType::Untyped,
+            target: "Max HP".into(),
+            value: Calculation::from_number(20),
+        });
+        let defense = |against: &str, value: i16| DefenseEffect {
+            common: EffectCommon::default(),
+            against: against.into(),
+            value: Calculation::from_number(value),
+        };
+        common.add_effect(Effect::Resistance(defense("physical", 3)));
+        common.add_effect(Effect::Resistance(defense("slashing", 1)));
+        common.add_effect(Effect::Weakness(defense("fire", 2)));
+        common.add_effect(Effect::Immunity(DamageKind::from("poison").into()));
+        let feat = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut character = character_with(&[feat.clone()]);
+        let storage = TestStorage::new(vec![feat]);
+
+        let defenses = character.defenses(&storage);
+        assert_eq!(
+            defenses.pdf_fields(),
+            vec![(
+                "ResistancesAndImmunities",
+                "immune poison; resist physical 3; resist slashing 1; weak fire 2".into()
+            )]
+        );
+        let damage = [
+            Damage::new(8, DamageType::S),
+            Damage::new(3, "fire"),
+            Damage::new(10, "poison"),
+        ];
+        assert_eq!(
+            character.take_damage(&damage, false, &storage),
+            DamageOutcome::Damaged
+        );
+        assert_eq!(character.current_hp(&storage), 10);
+    }
+
+    #[test]
+    fn level_up_and_down() {
+        let mut common = ResourceCommon::new("Skill Increase");
+        common.add_effect(SkillIncreaseEffect {
+            common: EffectCommon::default(),
+            choice: "skill".into(),
+        });
+        let increase = Resource::ClassFeature(ClassFeature {
+            common,
+            class: TypedRef::new("Monk", None::<&str>),
+        });
+        let mut advancement = std::collections::BTreeMap::new();
+        advancement.insert(
+            Level::from(2),
+            vec![TypedRef::new("skill feat", None::<&str>)],
+        );
+        for level in [3, 5].iter().copied() {
+            advancement.insert(
+                Level::from(level),
+                vec![TypedRef::new("Skill Increase", None::<&str>)],
+            );
+        }
+        let monk = Resource::Class(Class {
+            common: ResourceCommon::new("Monk"),
+            key_ability: smallvec![Ability::STR],
+            hp_per_level: Calculation::from_number(10),
+            advancement,
+        });
+        let mut common = ResourceCommon::new("Assurance");
+        common.add_traits(&["general", "skill"]);
+        let assurance = Resource::Feat(Feat {
+            common,
+            level: Level::from(1),
+        });
+        let mut character = character_with(&[monk.clone()]);
+        let monk_rref = monk.make_rref_no_mod();
+        let storage = TestStorage::new(vec![monk, increase, assurance.clone()]);
+        character.xp = 4000;
+
+        // The skill feat is only a slot, not a class feature.
+        let up = character.level_up(&storage).unwrap();
+        assert_eq!(up.level, Level::from(2));
+        assert_eq!(up.granted, vec![]);
+        assert_eq!(up.choices.len(), 1);
+        assert_eq!(
+            up.choices[0].kind,
+            LevelChoiceKind::Feat(FeatCategory::Skill)
+        );
+        let slot = character
+            .feat_slots(&storage)
+            .into_iter()
+            .find(|slot| slot.level == Level::from(2))
+            .unwrap();
+        assert_eq!(up.choices[0].slot.choice, slot.choice());
+
+        let assurance_rref = assurance.make_rref_no_mod();
+        character
+            .choose_feat(&slot, &assurance_rref, &storage)
+            .unwrap();
+        assert!(character.resources.contains(&assurance_rref));
+
+        let up = character.level_up(&storage).unwrap();
+        assert_eq!(up.level, Level::from(3));
+        assert_eq!(up.granted[0].modifier.as_deref(), Some("level 3"));
+        assert_eq!(up.choices[0].kind, LevelChoiceKind::SkillIncrease);
+        let slot = &up.choices[0].slot;
+        character
+            .set_choice(&slot.resource, slot.choice.clone(), &Skill::Athletics)
+            .unwrap();
+        assert_eq!(character.skill_increases(&storage).len(), 1);
+        assert_eq!(character.xp, 2000);
+
+        // Gaining 5th level brings ability boosts.
+        let up = character.level_up(&storage).unwrap();
+        assert_eq!(up.level, Level::from(4));
+        let up = character.level_up(&storage).unwrap();
+        let boosts = up.choices.last().unwrap();
+        assert!(matches!(
+            boosts.kind,
+            LevelChoiceKind::AbilityBoosts(BoostSource::Level(_, l)) if l == Level::from(5)
+        ));
+        character
+            .set_choice(
+                &boosts.slot.resource,
+                boosts.slot.choice.clone(),
+                &vec![Ability::STR, Ability::DEX, Ability::CON, Ability::WIS],
+            )
+            .unwrap();
+        assert_eq!(
+            character.level_up(&storage),
+            Err(LevelError::NotEnoughXp(0))
+        );
+        assert_eq!(character.levels.len(), 4);
+
+        let record = character.level_down(&storage).unwrap();
+        assert_eq!(record.level, Level::from(5));
+        assert_eq!(record.granted.len(), 1);
+        let record = character.level_down(&storage).unwrap();
+        assert_eq!(record.level, Level::from(4));
+
+        let record = character.level_down(&storage).unwrap();
+        assert_eq!(record.level, Level::from(3));
+        assert_eq!(character.level(), Level::from(2));
+        assert_eq!(character.skill_increases(&storage).len(), 0);
+        character.level_down(&storage).unwrap();
+        assert_eq!(character.level(), Level::from(1));
+        assert_eq!(character.xp, 4000);
+        assert_eq!(character.resources.len(), 1);
+        assert!(character.choice_values[&monk_rref].len() == 1);
+        assert_eq!(character.level_down(&storage), Err(LevelError::MinLevel));
+    }
+
+    #[test]
+    fn level_down_by_hand() {
+        let mut advancement = std::collections::BTreeMap::new();
+        advancement.insert(
+            Level::from(2),
+            vec![TypedRef::new("skill feat", None::<&str>)],
+        );
+        let monk = Resource::Class(Class {
+            common: ResourceCommon::new("Monk"),
+            key_ability: smallvec![Ability::STR],
+            hp_per_level: Calculation::from_number(10),
+            advancement,
+        });
+        let skill_feat = |name: &str| traited_feat(name, &["general", "skill"], 1);
+        let assurance = skill_feat("Assurance");
+        let cat_fall = skill_feat("Cat Fall");
+        let background = Resource::Background(Background {
+            common: ResourceCommon::new("Martial Disciple"),
+            ability_boosts: smallvec![],
+            skill_feat: Some(TypedRef::new("Assurance", None::<&str>)),
+        });
+        let mut character = character_with(&[monk.clone(), background.clone()]);
+        let monk_rref = monk.make_rref_no_mod();
+        let storage = TestStorage::new(vec![monk, background, assurance.clone(), cat_fall.clone()]);
+        character.normalize_resources(&storage);
+        let slot_at_2 = |character: &Character| {
+            character
+                .feat_slots(&storage)
+                .into_iter()
+                .find(|slot| slot.level == Level::from(2))
+                .unwrap()
+        };
+
+        // Without a level record, the feat picked at the level still goes.
+        let cat_fall_rref = cat_fall.make_rref_no_mod();
+        character.set_level(&monk_rref, Level::from(2));
+        character
+            .choose_feat(&slot_at_2(&character), &cat_fall_rref, &storage)
+            .unwrap();
+        let record = character.level_down(&storage).unwrap();
+        assert_eq!(record.resource_choices.len(), 1);
+        assert!(!character.has_resource(&cat_fall_rref));
+        character.set_level(&monk_rref, Level::from(2));
+        assert_eq!(slot_at_2(&character).chosen, None);
+
+        // The background grants Assurance too, so it stays.
+        let assurance_rref = assurance.make_rref_no_mod();
+        character
+            .choose_feat(&slot_at_2(&character), &assurance_rref, &storage)
+            .unwrap();
+        character.level_down(&storage).unwrap();
+        assert!(character.has_resource(&assurance_rref));
+    }
+
+    #[test]
+    fn validate_character() {
+        let mut common = ResourceCommon::new("Dedication");
+        common.add_prerequisite(
+            SingleCondition::HaveResource(ResourceRef::new("Missing Feat", None::<&str>)).into(),
+        );
+        common.add_choice(
+            "deity",
+            ChoiceMeta {
+                kind: ChoiceKind::Resource {
+                    resource_type: ResourceType::Feat,
+                    trait_filter: None,
+                },
+                from: None,
+                key: true,
+                character_wide: false,
+                description: None,
+            },
+        );
+        common.add_choice(
+            "lore",
+            ChoiceMeta {
+                kind: ChoiceKind::Skill,
+                from: None,
+                key: false,
+                character_wide: false,
+                description: None,
+            },
+        );
+        for choice in ["first", "second"].iter() {
+            common.add_effect(SkillIncreaseEffect {
+                common: EffectCommon::default(),
+                choice: (*choice).into(),
+            });
+        }
+        let dedication = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let monk = Resource::Class(Class {
+            common: ResourceCommon::new("Monk"),
+            key_ability: smallvec![Ability::STR],
+            hp_per_level: Calculation::from_number(10),
+            advancement: Default::default(),
+        });
+        let storage = TestStorage::new(vec![dedication.clone(), monk.clone()]);
+
+        let mut character = character_with(&[monk.clone()]);
+        assert_eq!(character.validate(&storage), ValidationReport::default());
+
+        let rref = dedication.make_rref_no_mod();
+        let ranger = ResourceRef::new("Ranger", None::<&str>).with_type(Some(ResourceType::Class));
+        character.resources.insert(rref.clone());
+        character.resources.insert(ranger.clone());
+        character.set_choice(&rref, "lore".into(), &5).unwrap();
+        for choice in ["first", "second"].iter() {
+            character
+                .set_choice(&rref, (*choice).into(), &Skill::Athletics)
+                .unwrap();
+        }
+
+        let report = character.validate(&storage);
+        let expected = vec![
+            Diagnostic::MultipleClasses {
+                classes: {
+                    let mut classes = vec![ranger.clone(), monk.make_rref_no_mod()];
+                    classes.sort();
+                    classes
+                },
+            },
+            Diagnostic::UnmetPrerequisites {
+                resource: rref.clone(),
+            },
+            Diagnostic::MissingChoice {
+                resource: rref.clone(),
+                choice: "deity".into(),
+                kind: ChoiceKind::Resource {
+                    resource_type: ResourceType::Feat,
+                    trait_filter: None,
+                },
+            },
+            Diagnostic::InvalidChoice {
+                resource: rref.clone(),
+                choice: "lore".into(),
+                expected: ChoiceKind::Skill,
+                value: serde_json::json!(5),
+                message: "invalid type: integer `5`, expected a string".into(),
+            },
+            Diagnostic::MissingResource { resource: ranger },
+            Diagnostic::SkillRankTooHigh {
+                skill: Skill::Athletics,
+                rank: Proficiency::Expert,
+                max: Proficiency::Trained,
+                level: Level::from(1),
+            },
+        ];
+        for diagnostic in expected.iter() {
+            assert!(
+                report.diagnostics.contains(diagnostic),
+                "Missing {:?} from {:#?}",
+                diagnostic,
+                report
+            );
+        }
+        assert_eq!(report.diagnostics.len(), expected.len());
+        assert!(!report.is_legal());
+        assert_eq!(report.incomplete().count(), 1);
+    }
+
+    #[test]
+    fn validate_crb_monk() {
+        let resources = crb_monk();
+        let monk = resources
+            .iter()
+            .find(|r| r.resource_type() == ResourceType::Class)
+            .unwrap()
+            .clone();
+        let monk_rref = monk.make_rref_no_mod();
+        let mut character = character_with(&[monk]);
+        let storage = TestStorage::new(resources);
+        character.set_level(&monk_rref, Level::from(20));
+        character.normalize_resources(&storage);
+        assert!(character.has_resource(&ResourceRef::new("Fist", None::<&str>)));
+        let report = character.validate(&storage);
+        assert!(report.is_legal(), "{}", report);
+
+        // Older characters were granted feat slots as class features.
+        character.resources.insert(
+            ResourceRef::new("monk feat", None::<&str>).with_type(Some(ResourceType::ClassFeature)),
+        );
+        assert_eq!(character.validate(&storage), report);
+    }
+
+    #[test]
+    fn feat_slots() {
+        let dwarf = Resource::Ancestry(Ancestry {
+            common: ResourceCommon::new("Dwarf"),
+            ability_boosts: smallvec![],
+            ability_flaws: smallvec![],
+            hp: 10,
+            size: Size::Medium,
+        });
+        let acolyte = Resource::Background(Background {
+            common: ResourceCommon::new("Acolyte"),
+            ability_boosts: smallvec![],
+            skill_feat: Some(TypedRef::new("Student of the Canon", None::<&str>)),
+        });
+        let mut advancement = std::collections::BTreeMap::new();
+        let features = |names: &[&str]| -> Vec<TypedRef<ClassFeature>> {
+            names
+                .iter()
+                .map(|name| TypedRef::new(*name, None::<&str>))
+                .collect()
+        };
+        advancement.insert(Level::from(1), features(&["flurry of blows", "monk feat"]));
+        advancement.insert(Level::from(2), features(&["monk feat", "skill feat"]));
+        advancement.insert(Level::from(3), features(&["general feat"]));
+        let monk = Resource::Class(Class {
+            common: ResourceCommon::new("Monk"),
+            key_ability: smallvec![Ability::STR],
+            hp_per_level: Calculation::from_number(10),
+            advancement,
+        });
+        let crane_stance = traited_feat("Crane Stance", &["monk"], 1);
+        let wholeness = traited_feat("Wholeness of Body", &["monk"], 4);
+        let dwarven_lore = traited_feat("Dwarven Lore", &["dwarf"], 1);
+        let canon = traited_feat("Student of the Canon", &["general", "skill"], 1);
+        let resources = vec![dwarf.clone(), acolyte.clone(), monk.clone()];
+        let mut character = character_with(&resources);
+        let monk_rref = monk.make_rref_no_mod();
+        character.set_level(&monk_rref, Level::from(2));
+        let mut all = resources.clone();
+        all.extend(vec![
+            crane_stance.clone(),
+            wholeness.clone(),
+            dwarven_lore.clone(),
+            canon.clone(),
+        ]);
+        let storage = TestStorage::new(all);
+        character.normalize_resources(&storage);
+        assert!(character.has_resource(&canon.make_rref_no_mod()));
+
+        let slots = character.feat_slots(&storage);
+        let summary: Vec<(u8, FeatCategory, Option<&str>, bool)> = slots
+            .iter()
+            .map(|s| (s.level.get(), s.category, s.feat_trait.as_deref(), s.fixed))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, FeatCategory::Ancestry, Some("dwarf"), false),
+                (1, FeatCategory::Class, Some("monk"), false),
+                (1, FeatCategory::Skill, Some("skill"), true),
+                (2, FeatCategory::Class, Some("monk"), false),
+                (2, FeatCategory::Skill, Some("skill"), false),
+            ]
+        );
+        assert_eq!(slots[2].chosen, Some(canon.make_rref_no_mod()));
+
+        let crane_rref = crane_stance.make_rref_no_mod();
+        let wholeness_rref = wholeness.make_rref_no_mod();
+        assert!(matches!(
+            character.choose_feat(&slots[3], &wholeness_rref, &storage),
+            Err(FeatSlotError::TooHighLevel { .. })
+        ));
+        assert!(matches!(
+            character.choose_feat(&slots[1], &dwarven_lore.make_rref_no_mod(), &storage),
+            Err(FeatSlotError::MissingTrait { .. })
+        ));
+        assert!(matches!(
+            character.choose_feat(&slots[2], &crane_rref, &storage),
+            Err(FeatSlotError::Fixed(_))
+        ));
+        character
+            .choose_feat(&slots[1], &crane_rref, &storage)
+            .unwrap();
+        assert!(character.has_resource(&crane_rref));
+
+        let slots = character.feat_slots(&storage);
+        assert_eq!(slots[1].chosen, Some(crane_rref));
+        let report = character.validate(&storage);
+        let empty: Vec<&Diagnostic> = report.incomplete().collect();
+        assert_eq!(empty.len(), 3);
+        // Flurry of Blows isn't in storage, but the feat slots aren't class
+        // features at all.
+        let errors: Vec<&Diagnostic> = report.errors().collect();
+        assert_eq!(
+            errors,
+            vec![&Diagnostic::MissingResource {
+                resource: ResourceRef::new("flurry of blows", None::<&str>)
+                    .with_type(Some(ResourceType::ClassFeature)),
+            }]
+        );
+    }
+
+    #[test]
+    fn repeated_feat_slots() {
+        let mut advancement = std::collections::BTreeMap::new();
+        advancement.insert(
+            Level::from(1),
+            vec![
+                TypedRef::new("monk feat", None::<&str>),
+                TypedRef::new("monk feat", None::<&str>),
+            ],
+        );
+        let monk = Resource::Class(Class {
+            common: ResourceCommon::new("Monk"),
+            key_ability: smallvec![Ability::STR],
+            hp_per_level: Calculation::from_number(10),
+            advancement,
+        });
+        let feat = |name: &str| traited_feat(name, &["monk"], 1);
+        let crane = feat("Crane Stance");
+        let ki_strike = feat("Ki Strike");
+        let wolf = feat("Wolf Stance");
+        let crane_rref = crane.make_rref_no_mod();
+        let wolf_rref = wolf.make_rref_no_mod();
+        // The background hands out Wolf Stance too, so it stays when it's
+        // swapped out of a slot.
+        let background = Resource::Background(Background {
+            common: ResourceCommon::new("Martial Disciple"),
+            ability_boosts: smallvec![],
+            skill_feat: Some(TypedRef::new("Wolf Stance", None::<&str>)),
+        });
+        let mut character = character_with(&[monk.clone(), background.clone()]);
+        character.set_level(&monk.make_rref_no_mod(), Level::from(1));
+        let storage = TestStorage::new(vec![monk, background, crane, ki_strike.clone(), wolf]);
+        character.normalize_resources(&storage);
+
+        let slots: Vec<FeatSlot> = character
+            .feat_slots(&storage)
+            .into_iter()
+            .filter(|slot| !slot.fixed)
+            .collect();
+        assert_eq!(slots.len(), 2);
+        assert_ne!(slots[0].choice(), slots[1].choice());
+        character
+            .choose_feat(&slots[0], &crane_rref, &storage)
+            .unwrap();
+        character
+            .choose_feat(&slots[1], &crane_rref, &storage)
+            .unwrap();
+        let chosen: Vec<Option<ResourceRef>> = character
+            .feat_slots(&storage)
+            .into_iter()
+            .filter(|slot| !slot.fixed)
+            .map(|slot| slot.chosen)
+            .collect();
+        assert_eq!(
+            chosen,
+            vec![Some(crane_rref.clone()), Some(crane_rref.clone())]
+        );
+
+        // Crane Stance is still in the other slot.
+        character
+            .choose_feat(&slots[0], &wolf_rref, &storage)
+            .unwrap();
+        assert!(character.has_resource(&crane_rref));
+        character
+            .choose_feat(&slots[1], &ki_strike.make_rref_no_mod(), &storage)
+            .unwrap();
+        assert!(!character.has_resource(&crane_rref));
+        // Wolf Stance is still granted by the background.
+        character
+            .choose_feat(&slots[0], &crane_rref, &storage)
+            .unwrap();
+        assert!(character.has_resource(&wolf_rref));
+    }
+
+    #[test]
+    fn eligible_resources() {
+        let mut common = ResourceCommon::new("Crane Stance");
+        common.add_traits(&["monk", "stance"]);
+        let crane = Resource::Feat(Feat {
+            common,
+            level: Level::from(1),
+        });
+        let mut common = ResourceCommon::new("Crane Flutter");
+        common.add_traits(&["monk"]);
+        common.add_prerequisite(SingleCondition::HaveResource(crane.make_rref_no_mod()).into());
+        let flutter = Resource::Feat(Feat {
+            common,
+            level: Level::from(1),
+        });
+        let mut common = ResourceCommon::new("Wholeness of Body");
+        common.add_traits(&["monk"]);
+        let wholeness = Resource::Feat(Feat {
+            common,
+            level: Level::from(4),
+        });
+        let mut common = ResourceCommon::new("Cat Fall");
+        common.add_traits(&["general", "skill"]);
+        let cat_fall = Resource::Feat(Feat {
+            common,
+            level: Level::from(1),
+        });
+        let storage = TestStorage::new(vec![crane.clone(), flutter.clone(), wholeness, cat_fall]);
+        let mut character = Character::new("Test Character");
+
+        let kind = ChoiceKind::Resource {
+            resource_type: ResourceType::Feat,
+            trait_filter: Some("monk".into()),
+        };
+        let query = ResourceQuery::for_choice(&kind, Level::from(2)).unwrap();
+        let found = futures::executor::block_on(character.eligible_resources(&query, &storage));
+        assert_eq!(
+            found,
+            vec![
+                Eligibility {
+                    rref: flutter.make_rref_no_mod(),
+                    unmet: vec!["requires Crane Stance".into()],
+                },
+                Eligibility {
+                    rref: crane.make_rref_no_mod(),
+                    unmet: vec![],
+                },
+            ]
+        );
+
+        character.resources.insert(crane.make_rref_no_mod());
+        let found = character.check_eligibility(vec![flutter.make_rref_no_mod()], &query, &storage);
+        assert!(found[0].is_eligible());
+    }
+
+    #[test]
+    fn modifier_breakdown() {
+        let bonus = |bonus_type: BonusType, value: i16| BonusEffect {
+            common: EffectCommon::default(),
+            bonus_type,
+            target: "AC".into(),
+            value: Calculation::from_number(value),
+        };
+        let mut common = ResourceCommon::new("Bracers of Armor");
+        common.add_effect(bonus(BonusType::Item, 2));
+        let bracers = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut common = ResourceCommon::new("Shield Ward");
+        common.add_effect(bonus(BonusType::Item, 1));
+        common.add_effect(bonus(BonusType::Status, 1));
+        let ward = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut common = ResourceCommon::new("Clumsy");
+        common.add_effect(PenaltyEffect {
+            common: EffectCommon::default(),
+            penalty_type: BonusType::Status,
+            target: "DEX-based checks".into(),
+            value: Calculation::from_number(-1),
+        });
+        let clumsy = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let resources = vec![bracers.clone(), ward.clone(), clumsy.clone()];
+        let character = character_with(&resources);
+        let storage = TestStorage::new(resources);
+
+        let breakdown = character.modifier_breakdown("AC", None, &storage);
+        assert_eq!(breakdown.total(), 12);
+        assert_eq!(
+            breakdown.total(),
+            character.get_modifier("AC", None, &storage).total()
+        );
+        let suppressed: Vec<_> = breakdown.suppressed().collect();
+        assert_eq!(suppressed.len(), 1);
+        assert_eq!(suppressed[0].bonus_type, BonusType::Item);
+        assert_eq!(suppressed[0].value, 1);
+        assert_eq!(
+            suppressed[0].source.resource(),
+            Some(&ward.make_rref_no_mod())
+        );
+        let penalty = breakdown.parts.iter().find(|p| p.value < 0).unwrap();
+        assert!(penalty.applied);
+        assert_eq!(penalty.source.resource(), Some(&clumsy.make_rref_no_mod()));
+        let text = format!("{}", breakdown);
+        assert!(text.starts_with("AC +12\n  +10 untyped from base"));
+        assert!(text.contains("\n  +1 item from Shield Ward (doesn't stack)"));
+    }
+
+    #[test]
+    fn modifier_cycles() {
+        let bonus = |target: &str, name: &str| BonusEffect {
+            common: EffectCommon::default(),
+            bonus_type: BonusType::Untyped,
+            target: target.into(),
+            value: Calculation::Named(name.into()),
+        };
+        let mut common = ResourceCommon::new("Mirror Armor");
+        common.add_effect(bonus("AC", "AC"));
+        let mirror = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut common = ResourceCommon::new("Echo");
+        common.add_effect(bonus("Ping", "Pong"));
+        common.add_effect(bonus("Pong", "Ping"));
+        common.add_effect(bonus("Perception", "Ping"));
+        let echo = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut conditions = Conditions::default();
+        conditions &= SingleCondition::Proficiency(ProficiencyCondition::AtLeast {
+            target: "Athletics".into(),
+            at_least: Proficiency::Trained,
+        })
+        .into();
+        let mut common = ResourceCommon::new("Self-Taught");
+        common.add_effect(IncreaseProficiencyEffect {
+            common: EffectCommon { conditions },
+            target: "Athletics".into(),
+            level: Proficiency::Trained,
+        });
+        let self_taught = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let resources = vec![mirror, echo, self_taught];
+        let character = character_with(&resources);
+        let storage = TestStorage::new(resources);
+
+        assert_eq!(character.get_modifier("AC", None, &storage).total(), 10);
+        assert_eq!(character.get_modifier("Ping", None, &storage).total(), 0);
+        assert_eq!(
+            character.get_proficiency("Athletics", None, &storage).0,
+            Proficiency::Untrained
+        );
+
+        let report = character.validate(&storage);
+        let cycles: Vec<std::string::String> = report
+            .diagnostics
+            .iter()
+            .filter(|d| matches!(d, Diagnostic::Cycle(_)))
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            cycles,
+            vec![
+                "proficiency in Athletics depends on itself \
+                 (proficiency in Athletics → proficiency in Athletics)",
+                "AC depends on itself (AC → AC)",
+                "Ping depends on itself (Perception → Ping → Pong → Ping)",
+            ]
+        );
+    }
 }
@@ -0,0 +1,212 @@
+#[cfg(test)]
+use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{bonuses::Modifier, dice::RollResult};
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum DegreeOfSuccess {
+    #[serde(rename = "critical failure")]
+    CriticalFailure,
+    #[serde(rename = "failure")]
+    Failure,
+    #[serde(rename = "success")]
+    Success,
+    #[serde(rename = "critical success")]
+    CriticalSuccess,
+}
+
+impl fmt::Display for DegreeOfSuccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CriticalFailure => write!(f, "critical failure"),
+            Self::Failure => write!(f, "failure"),
+            Self::Success => write!(f, "success"),
+            Self::CriticalSuccess => write!(f, "critical success"),
+        }
+    }
+}
+
+impl DegreeOfSuccess {
+    /// The degree of success from the total alone, without accounting for
+    /// natural 20s and 1s.
+    pub fn from_total(total: i16, dc: i16) -> Self {
+        if total >= dc + 10 {
+            Self::CriticalSuccess
+        } else if total >= dc {
+            Self::Success
+        } else if total <= dc - 10 {
+            Self::CriticalFailure
+        } else {
+            Self::Failure
+        }
+    }
+
+    pub fn step_up(self) -> Self {
+        match self {
+            Self::CriticalFailure => Self::Failure,
+            Self::Failure => Self::Success,
+            Self::Success | Self::CriticalSuccess => Self::CriticalSuccess,
+        }
+    }
+
+    pub fn step_down(self) -> Self {
+        match self {
+            Self::CriticalFailure | Self::Failure => Self::CriticalFailure,
+            Self::Success => Self::Failure,
+            Self::CriticalSuccess => Self::Success,
+        }
+    }
+
+    /// Resolve a check where `natural` is the number showing on the d20.
+    pub fn resolve(modifier: &Modifier, dc: i16, natural: u16) -> Self {
+        let total = natural as i16 + modifier.total();
+        let degree = Self::from_total(total, dc);
+        match natural {
+            20 => degree.step_up(),
+            1 => degree.step_down(),
+            _ => degree,
+        }
+    }
+
+    /// Resolve an already rolled check. Rolls that aren't a single d20 are
+    /// judged on their total alone.
+    pub fn of_roll(roll: &RollResult, dc: i16) -> Self {
+        match roll.natural() {
+            Some(natural) => Self::resolve(&roll.breakdown, dc, natural),
+            None => Self::from_total(roll.total(), dc),
+        }
+    }
+
+    /// Apply overrides of the form "when you roll `from`, you get `to`
+    /// instead". Only overrides for the original degree apply, and the best
+    /// one wins if there are several.
+    pub fn with_overrides(
+        self,
+        overrides: impl IntoIterator<Item = (DegreeOfSuccess, DegreeOfSuccess)>,
+    ) -> Self {
+        overrides
+            .into_iter()
+            .filter(|(from, _)| *from == self)
+            .map(|(_, to)| to)
+            .max()
+            .unwrap_or(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bonuses::Bonus,
+        common::{Feat, Resource, ResourceCommon},
+        effects::{DegreeOfSuccessEffect, Effect, EffectCommon},
+        test_helpers::TestStorage,
+        Character,
+    };
+
+    use DegreeOfSuccess::*;
+
+    fn resolve(bonus: i16, dc: i16, natural: u16) -> DegreeOfSuccess {
+        DegreeOfSuccess::resolve(&Bonus::untyped(bonus).into(), dc, natural)
+    }
+
+    #[test]
+    fn thresholds() {
+        assert_eq!(resolve(5, 20, 15), Success);
+        assert_eq!(resolve(5, 20, 14), Failure);
+        assert_eq!(resolve(5, 20, 19), Success);
+        assert_eq!(resolve(15, 20, 15), CriticalSuccess);
+        assert_eq!(resolve(0, 20, 10), CriticalFailure);
+        assert_eq!(resolve(0, 20, 11), Failure);
+    }
+
+    #[test]
+    fn natural_twenty_and_one() {
+        assert_eq!(resolve(0, 30, 20), Failure);
+        assert_eq!(resolve(0, 20, 20), CriticalSuccess);
+        assert_eq!(resolve(15, 10, 20), CriticalSuccess);
+        assert_eq!(resolve(20, 20, 1), Failure);
+        assert_eq!(resolve(10, 30, 1), CriticalFailure);
+        assert_eq!(resolve(30, 10, 1), Success);
+    }
+
+    #[test]
+    fn overrides() {
+        let path_to_perfection = vec![(Success, CriticalSuccess)];
+        assert_eq!(
+            Success.with_overrides(path_to_perfection.clone()),
+            CriticalSuccess
+        );
+        assert_eq!(Failure.with_overrides(path_to_perfection), Failure);
+        let both = vec![(Failure, Success), (Failure, CriticalFailure)];
+        assert_eq!(Failure.with_overrides(both), Success);
+    }
+
+    #[test]
+    fn deserialize_override_effect() {
+        let raw = r#"{"degree of success": {"on": "FORT", "when you roll": "success", "you get": "critical success"}}"#;
+        let parsed: Effect = serde_json::from_str(raw).unwrap();
+        let expected = Effect::DegreeOfSuccess(DegreeOfSuccessEffect {
+            common: EffectCommon::default(),
+            check: "FORT".into(),
+            from: Success,
+            to: CriticalSuccess,
+        });
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn character_overrides() {
+        let mut common = ResourceCommon::new("Path to Perfection");
+        common.add_effect(DegreeOfSuccessEffect {
+            common: EffectCommon::default(),
+            check: "FORT".into(),
+            from: Success,
+            to: CriticalSuccess,
+        });
+        let feat = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut character = Character::new("Test Monk");
+        character.resources.insert(feat.make_rref_no_mod());
+        let storage = TestStorage::new(vec![feat]);
+
+        let degree = character.degree_of_success("FORT", None, 15, 16, &storage);
+        assert_eq!(degree, CriticalSuccess);
+        let degree = character.degree_of_success("fort", None, 15, 14, &storage);
+        assert_eq!(degree, Failure);
+        let degree = character.degree_of_success("REF", None, 15, 16, &storage);
+        assert_eq!(degree, Success);
+    }
+
+    #[test]
+    fn chosen_check_overrides() {
+        let mut common = ResourceCommon::new("Path to Perfection");
+        common.add_effect(DegreeOfSuccessEffect {
+            common: EffectCommon::default(),
+            check: "$save".into(),
+            from: Success,
+            to: CriticalSuccess,
+        });
+        let feat = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let rref = feat.make_rref_no_mod();
+        let mut character = Character::new("Test Monk");
+        character.resources.insert(rref.clone());
+        let storage = TestStorage::new(vec![feat]);
+
+        let degree = character.degree_of_success("REF", None, 15, 16, &storage);
+        assert_eq!(degree, Success);
+        character.set_choice(&rref, "save".into(), &"REF").unwrap();
+        let degree = character.degree_of_success("REF", None, 15, 16, &storage);
+        assert_eq!(degree, CriticalSuccess);
+        let degree = character.degree_of_success("WILL", None, 15, 16, &storage);
+        assert_eq!(degree, Success);
+    }
+}
@@ -4,6 +4,7 @@ use proptest::prelude::*;
 use proptest_derive::Arbitrary;
 use ref_cast::RefCast;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use smartstring::alias::String;
 use std::{
     borrow::Borrow,
@@ -14,9 +15,12 @@ use std::{
     str::FromStr,
 };
 use thiserror::Error;
+use uuid::Uuid;
 
 use crate::{
     common::{ResourceRef, ResourceType},
+    spells::Tradition,
+    stats::{Ability, Level, Skill},
     try_from_str,
 };
 
@@ -26,6 +30,7 @@ pub enum ChoiceKind {
     Ability,
     Distance,
     Level,
+    /// An entry in the character's inventory, recorded by its ID.
     OwnedItem,
     Resource {
         #[serde(rename = "type")]
@@ -67,6 +72,25 @@ impl fmt::Display for ChoiceKind {
     }
 }
 
+impl ChoiceKind {
+    /// Check that `value` can be read as this kind of choice.
+    pub fn check_value(&self, value: &Value) -> Result<(), serde_json::Error> {
+        fn read<T: serde::de::DeserializeOwned>(value: &Value) -> Result<(), serde_json::Error> {
+            serde_json::from_value::<T>(value.clone()).map(|_| ())
+        }
+
+        match self {
+            Self::Ability => read::<Ability>(value),
+            Self::Level => read::<Level>(value),
+            Self::OwnedItem => read::<Uuid>(value),
+            Self::Resource { .. } => read::<ResourceRef>(value),
+            Self::Skill => read::<Skill>(value),
+            Self::SpellTradition => read::<Tradition>(value),
+            Self::Distance | Self::SavingThrow => Ok(()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct ChoiceMeta {
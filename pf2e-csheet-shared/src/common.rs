@@ -13,10 +13,15 @@ use thiserror::Error;
 use crate::{
     bonuses::{Bonus, Modifier},
     calc::{CalcContext, CalculatedString, Calculation},
+    checks::DegreeOfSuccess,
     choices::{Choice, ChoiceMeta, ResourceChoices},
-    cond::{Condition, Conditions},
-    effects::{Effect, Effects},
-    stats::{Ability, Level, Proficiency, Skill},
+    cond::{self, Conditions},
+    defenses::{DamageKind, DefenseType},
+    effects::{Effect, Effects, FocusSpellEffect, SpellcastingEffect},
+    feats::FeatCategory,
+    items::{Armor, Container, ItemType, Rarity, Shield, Weapon, WeaponTrait},
+    spells::{Heightening, SpellSave, Tradition},
+    stats::{Ability, AbilityBoost, Bulk, Gold, Level, Proficiency, Size, Skill},
 };
 
 mod rref;
@@ -32,6 +37,7 @@ pub enum ResourceType {
     Background,
     Class,
     ClassFeature,
+    Condition,
     Feat,
     Heritage,
     Item,
@@ -69,6 +75,7 @@ impl<'v> FromFormValue<'v> for ResourceType {
             Ok("background") => Ok(Self::Background),
             Ok("class") => Ok(Self::Class),
             Ok("class-feature") => Ok(Self::ClassFeature),
+            Ok("condition") => Ok(Self::Condition),
             Ok("feat") => Ok(Self::Feat),
             Ok("heritage") => Ok(Self::Heritage),
             Ok("item") => Ok(Self::Item),
@@ -86,6 +93,7 @@ impl fmt::Display for ResourceType {
             Self::Background => "background",
             Self::Class => "class",
             Self::ClassFeature => "class feature",
+            Self::Condition => "condition",
             Self::Feat => "feat",
             Self::Heritage => "heritage",
             Self::Item => "item",
@@ -170,23 +178,60 @@ impl ResourceCommon {
         }
     }
 
-    fn get_modifier(&self, label: &str, ctx: CalcContext<'_>) -> Modifier {
-        let mut modifier = Modifier::new();
+    /// The modifier each effect gives to `label`, leaving out the effects
+    /// that don't apply.
+    fn effect_modifiers(&self, label: &str, ctx: CalcContext<'_>) -> Vec<(&Effect, Modifier)> {
+        let mut modifiers = vec![];
         if self.requirements.reject(ctx) {
-            return modifier;
+            return modifiers;
         }
 
         for effect in self.effects.iter() {
+            // Conditions can ask for modifiers themselves, so only check them
+            // when the effect matters.
+            if !effect.affects_modifier(label) {
+                continue;
+            }
             let common_e = effect.common();
             if common_e.conditions.reject(ctx) {
                 continue;
             }
             match effect.get_modifier(label, ctx) {
-                Ok(e_mod) => modifier += e_mod,
+                Ok(e_mod) => modifiers.push((effect, e_mod)),
                 Err(err) => error!("Failed to get modifier from effect {:?}: {}", effect, err),
             }
         }
-        modifier
+        modifiers
+    }
+
+    /// What `value` gives for each effect that currently applies, given this
+    /// resource's requirements and each effect's conditions. Conditions are
+    /// only checked for effects with a value, since checking them can need
+    /// proficiencies or modifiers in turn.
+    fn active_values<T>(
+        &self,
+        ctx: CalcContext<'_>,
+        value: impl Fn(&Effect) -> Option<T>,
+    ) -> SmallVec<[T; 1]> {
+        let mut values = SmallVec::new();
+        let mut requirements_met = None;
+        for effect in self.effects.iter() {
+            let v = match value(effect) {
+                Some(v) => v,
+                None => continue,
+            };
+            let met = *requirements_met.get_or_insert_with(|| !self.requirements.reject(ctx));
+            if met && !effect.common().conditions.reject(ctx) {
+                values.push(v);
+            }
+        }
+        values
+    }
+
+    fn get_proficiency(&self, target: &str, ctx: CalcContext<'_>) -> Option<Proficiency> {
+        self.active_values(ctx, |effect| effect.get_proficiency(target, ctx))
+            .into_iter()
+            .max()
     }
 
     pub fn get_choice<T: serde::de::DeserializeOwned>(
@@ -235,11 +280,11 @@ impl ResourceCommon {
         self.effects.add_effect(effect.into());
     }
 
-    pub fn add_prerequisite(&mut self, prereq: Condition) {
+    pub fn add_prerequisite(&mut self, prereq: cond::Condition) {
         self.prerequisites &= prereq;
     }
 
-    pub fn add_requirement(&mut self, req: Condition) {
+    pub fn add_requirement(&mut self, req: cond::Condition) {
         self.requirements &= req;
     }
 
@@ -265,6 +310,8 @@ pub enum Resource {
     Class(Class),
     #[serde(rename = "class feature")]
     ClassFeature(ClassFeature),
+    #[serde(rename = "condition")]
+    Condition(Condition),
     #[serde(rename = "feat")]
     Feat(Feat),
     #[serde(rename = "heritage")]
@@ -287,6 +334,7 @@ impl Arbitrary for Resource {
             any::<Background>().prop_map(Resource::Background),
             any::<Class>().prop_map(Resource::Class),
             any::<ClassFeature>().prop_map(Resource::ClassFeature),
+            any::<Condition>().prop_map(Resource::Condition),
             any::<Feat>().prop_map(Resource::Feat),
             any::<Heritage>().prop_map(Resource::Heritage),
             any::<Item>().prop_map(Resource::Item),
@@ -310,6 +358,7 @@ impl Resource {
             Self::Background(r) => &r.common,
             Self::Class(r) => &r.common,
             Self::ClassFeature(r) => &r.common,
+            Self::Condition(r) => &r.common,
             Self::Feat(r) => &r.common,
             Self::Heritage(r) => &r.common,
             Self::Item(r) => &r.common,
@@ -330,6 +379,7 @@ impl Resource {
             Self::Background(_) => ResourceType::Background,
             Self::Class(_) => ResourceType::Class,
             Self::ClassFeature(_) => ResourceType::ClassFeature,
+            Self::Condition(_) => ResourceType::Condition,
             Self::Feat(_) => ResourceType::Feat,
             Self::Heritage(_) => ResourceType::Heritage,
             Self::Item(_) => ResourceType::Item,
@@ -337,13 +387,97 @@ impl Resource {
         }
     }
 
-    pub(crate) fn get_modifier(&self, name: &str, ctx: CalcContext<'_>) -> Modifier {
-        let mut m = self.common().get_modifier(name, ctx);
+    /// The level of feats, items and spells.
+    pub fn level(&self) -> Option<Level> {
+        match self {
+            Self::Feat(f) => Some(f.level),
+            Self::Item(i) => Some(i.level),
+            Self::Spell(s) => Some(s.level),
+            _ => None,
+        }
+    }
+
+    /// What each of this resource's effects adds to the modifier `name`. The
+    /// part without an effect comes from the resource itself, like a class's
+    /// hit points.
+    pub(crate) fn modifier_parts(
+        &self,
+        name: &str,
+        ctx: CalcContext<'_>,
+    ) -> Vec<(Option<&Effect>, Modifier)> {
+        let mut parts: Vec<(Option<&Effect>, Modifier)> = self
+            .common()
+            .effect_modifiers(name, ctx)
+            .into_iter()
+            .map(|(effect, m)| (Some(effect), m))
+            .collect();
         match self {
-            Self::Class(cls) => m += cls.get_modifier(name, ctx),
+            Self::Ancestry(ancestry) => parts.push((None, ancestry.get_modifier(name))),
+            Self::Class(cls) => parts.push((None, cls.get_modifier(name, ctx))),
             _ => (),
         }
-        m
+        parts
+    }
+
+    pub(crate) fn get_proficiency(
+        &self,
+        target: &str,
+        ctx: CalcContext<'_>,
+    ) -> Option<Proficiency> {
+        self.common().get_proficiency(target, ctx)
+    }
+
+    pub(crate) fn get_proficiency_grants(
+        &self,
+        ctx: CalcContext<'_>,
+    ) -> SmallVec<[(String, Proficiency); 1]> {
+        self.common()
+            .active_values(ctx, |effect| effect.get_proficiency_grant(ctx))
+    }
+
+    pub(crate) fn get_skill_increases(&self, ctx: CalcContext<'_>) -> SmallVec<[Skill; 1]> {
+        self.common()
+            .active_values(ctx, |effect| effect.get_skill_increase(ctx))
+    }
+
+    pub(crate) fn get_degree_overrides(
+        &self,
+        check: &str,
+        ctx: CalcContext<'_>,
+    ) -> SmallVec<[(DegreeOfSuccess, DegreeOfSuccess); 1]> {
+        self.common()
+            .active_values(ctx, |effect| effect.get_degree_override(check, ctx))
+    }
+
+    pub(crate) fn get_spellcasting(
+        &self,
+        ctx: CalcContext<'_>,
+    ) -> SmallVec<[SpellcastingEffect; 1]> {
+        self.common()
+            .active_values(ctx, |effect| effect.get_spellcasting().cloned())
+    }
+
+    pub(crate) fn get_focus_spells(&self, ctx: CalcContext<'_>) -> SmallVec<[FocusSpellEffect; 1]> {
+        self.common()
+            .active_values(ctx, |effect| effect.get_focus_spell().cloned())
+    }
+
+    pub(crate) fn get_defenses(
+        &self,
+        ctx: CalcContext<'_>,
+    ) -> SmallVec<[(DefenseType, DamageKind, u16); 1]> {
+        self.common()
+            .active_values(ctx, |effect| effect.get_defense(ctx))
+    }
+
+    pub(crate) fn get_weapon_traits(
+        &self,
+        name: &str,
+        weapon: &Weapon,
+        ctx: CalcContext<'_>,
+    ) -> SmallVec<[WeaponTrait; 1]> {
+        self.common()
+            .active_values(ctx, |effect| effect.get_weapon_trait(name, weapon))
     }
 
     pub fn all_choices(&self) -> impl Iterator<Item = (&Choice, &ChoiceMeta)> + '_ {
@@ -354,6 +488,9 @@ impl Resource {
         let mut rrefs: Vec<ResourceRef> = self.common().granted_resources(ctx).collect();
         match self {
             Self::Class(class) => rrefs.extend(class.granted_resources(ctx)),
+            Self::Background(background) => {
+                rrefs.extend(background.skill_feat.clone().map(TypedRef::as_runtime))
+            }
             _ => (),
         }
         rrefs
@@ -466,16 +603,61 @@ impl_has_resource_type!(Action);
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct Ancestry {
     #[serde(flatten)]
-    common: ResourceCommon,
+    pub common: ResourceCommon,
+    #[serde(default, rename = "ability boosts", alias = "ability_boosts")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::vec(any::<AbilityBoost>(), 0..=3).prop_map_into()"
+        )
+    )]
+    pub ability_boosts: SmallVec<[AbilityBoost; 3]>,
+    #[serde(default, rename = "ability flaws", alias = "ability_flaws")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::collection::vec(any::<Ability>(), 0..=1).prop_map_into()")
+    )]
+    pub ability_flaws: SmallVec<[Ability; 1]>,
+    /// The Hit Points the ancestry gives at 1st level.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub hp: u16,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub size: Size,
 }
 
 impl_has_resource_type!(Ancestry);
 
+impl Ancestry {
+    fn get_modifier(&self, label: &str) -> Modifier {
+        match label {
+            "Max HP" => Bonus::untyped(self.hp as i16).into(),
+            _ => Modifier::new(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct Background {
     #[serde(flatten)]
     pub common: ResourceCommon,
+    #[serde(default, rename = "ability boosts", alias = "ability_boosts")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::vec(any::<AbilityBoost>(), 0..=2).prop_map_into()"
+        )
+    )]
+    pub ability_boosts: SmallVec<[AbilityBoost; 2]>,
+    /// The skill feat the background gives.
+    #[serde(default, rename = "skill feat", alias = "skill_feat")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skill_feat: Option<TypedRef<Feat>>,
 }
 
 impl_has_resource_type!(Background);
@@ -538,6 +720,48 @@ pub struct Class {
 impl_has_resource_type!(Class);
 
 impl Class {
+    /// Features granted at more than one level (like skill increases) get the
+    /// level as their modifier, so that each one keeps its own choices.
+    fn feature_ref(&self, level: Level, feature: &TypedRef<ClassFeature>) -> ResourceRef {
+        let mut rref = feature.clone().as_runtime();
+        let repeats = self
+            .advancement
+            .values()
+            .filter(|features| features.contains(feature))
+            .count();
+        if rref.modifier.is_none() && repeats > 1 {
+            rref.modifier = Some(format!("level {}", level));
+        }
+        rref
+    }
+
+    /// Whether an advancement entry is a class feature to grant, rather than
+    /// something the character sheet tracks itself: feat slots, ability
+    /// boosts, and the ancestry and background picked at 1st level.
+    pub fn grants_feature(&self, feature: &TypedRef<ClassFeature>) -> bool {
+        let name = feature.name.trim();
+        if FeatCategory::from_feature_name(name, &self.common.name).is_some() {
+            return false;
+        }
+        !["ancestry", "background", "ability boosts"]
+            .iter()
+            .any(|builtin| name.eq_ignore_ascii_case(builtin))
+    }
+
+    /// The class features gained at exactly `level`.
+    pub fn features_at(&self, level: Level) -> Vec<ResourceRef> {
+        self.advancement
+            .get(&level)
+            .map(|features| {
+                features
+                    .iter()
+                    .filter(|f| self.grants_feature(f))
+                    .map(|f| self.feature_ref(level, f))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn granted_resources(&self, ctx: CalcContext<'_>) -> impl Iterator<Item = ResourceRef> + '_ {
         let mut rrefs = vec![];
         let cls_level_opt = ctx.character.get_class_and_level();
@@ -549,7 +773,12 @@ impl Class {
                         continue;
                     }
                     debug!("Granting level {} class features: {:?}", level, cf_refs);
-                    rrefs.extend(cf_refs.iter().map(|tr| tr.clone().as_runtime()));
+                    rrefs.extend(
+                        cf_refs
+                            .iter()
+                            .filter(|tr| self.grants_feature(tr))
+                            .map(|tr| self.feature_ref(*level, tr)),
+                    );
                 }
             }
             Some((other_class, _current_level)) => {
@@ -575,10 +804,10 @@ impl Class {
         let level: Level = ctx
             .character
             .get_choice(&cls_rref, "Level")
-            .unwrap_or_default();
+            .unwrap_or(1.into());
         match label {
             "Max HP" => {
-                let per_level_val = self.hp_per_level.evaluate(ctx).max(1);
+                let per_level_val = self.hp_per_level.evaluate_number(ctx).max(1);
                 let per_level_bonus = Bonus::untyped(per_level_val);
                 (per_level_bonus * level).into()
             }
@@ -648,6 +877,43 @@ pub enum ClassFeatureDetails {
 
 impl_has_resource_type!(ClassFeature);
 
+/// A condition a character can be under during play, like frightened or
+/// off-guard. Its effects can use `condition value` for the condition's
+/// value.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct Condition {
+    #[serde(flatten)]
+    pub common: ResourceCommon,
+    /// Whether the condition has a value, like frightened 2.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub valued: bool,
+    /// Whether the value goes down by 1 at the end of each of the
+    /// character's turns.
+    #[serde(default, rename = "decreases each turn")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub decreases: bool,
+    /// Other conditions that come with this one, like grabbed making you
+    /// off-guard.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub implies: Vec<ResourceRef>,
+}
+
+impl_has_resource_type!(Condition);
+
+impl Condition {
+    pub fn new(common: ResourceCommon) -> Self {
+        Self {
+            common,
+            valued: false,
+            decreases: false,
+            implies: vec![],
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct Feat {
@@ -673,24 +939,184 @@ impl_has_resource_type!(Heritage);
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct Item {
     #[serde(flatten)]
-    common: ResourceCommon,
+    pub common: ResourceCommon,
+    #[serde(default)]
+    pub level: Level,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub price: Gold,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub bulk: Bulk,
+    /// How many hands it takes to wield or use the item.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub hands: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub rarity: Rarity,
+    /// How the item is used, like "held in 1 hand" or "worn armor".
     #[serde(default)]
-    level: Level,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::option::of(any::<std::string::String>().prop_map_into())")
+    )]
+    pub usage: Option<String>,
+    #[serde(default, rename = "item type")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub item_type: ItemType,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub armor: Option<Armor>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shield: Option<Shield>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weapon: Option<Weapon>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
 }
 
 impl_has_resource_type!(Item);
 
+impl Item {
+    pub fn new(common: ResourceCommon, item_type: ItemType) -> Self {
+        Self {
+            common,
+            level: Level::default(),
+            price: Gold::zero(),
+            bulk: Bulk::default(),
+            hands: 0,
+            rarity: Rarity::default(),
+            usage: None,
+            item_type,
+            armor: None,
+            shield: None,
+            weapon: None,
+            container: None,
+        }
+    }
+
+    pub fn has_trait(&self, wanted: &str) -> bool {
+        self.common
+            .traits
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(wanted))
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct Spell {
     #[serde(flatten)]
-    common: ResourceCommon,
+    pub common: ResourceCommon,
+    #[serde(default)]
+    pub level: Level,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "SmallVec::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::vec(any::<Tradition>(), 0..=4).prop_map_into()"
+        )
+    )]
+    pub traditions: SmallVec<[Tradition; 4]>,
+    /// How long it takes to cast, like "2" or "1 to 3".
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::option::of(any::<std::string::String>().prop_map_into())")
+    )]
+    pub actions: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::option::of(any::<std::string::String>().prop_map_into())")
+    )]
+    pub range: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::option::of(any::<std::string::String>().prop_map_into())")
+    )]
+    pub targets: Option<String>,
+    #[serde(default, rename = "saving throw")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub save: Option<SpellSave>,
+    /// What changes when the spell is cast at a higher level.
     #[serde(default)]
-    level: Level,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::btree_map(any::<Heightening>(), any::<std::string::String>().prop_map_into(), 0..=3)"
+        )
+    )]
+    pub heightened: BTreeMap<Heightening, String>,
 }
 
 impl_has_resource_type!(Spell);
 
+impl Spell {
+    pub fn new(common: ResourceCommon) -> Self {
+        Self {
+            common,
+            level: Level::from(1),
+            traditions: SmallVec::new(),
+            actions: None,
+            range: None,
+            targets: None,
+            save: None,
+            heightened: BTreeMap::new(),
+        }
+    }
+
+    fn has_trait(&self, wanted: &str) -> bool {
+        self.common
+            .traits
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(wanted))
+    }
+
+    /// Cantrips are always heightened to half the caster's level.
+    pub fn is_cantrip(&self) -> bool {
+        self.has_trait("cantrip")
+    }
+
+    /// Focus spells are heightened like cantrips.
+    pub fn is_focus(&self) -> bool {
+        self.has_trait("focus")
+    }
+
+    /// The heightened entries that apply when the spell is cast at `level`,
+    /// with how many times each applies. Only the highest of the entries for
+    /// specific levels applies.
+    pub fn heightened_at(&self, level: u8) -> Vec<(Heightening, &str, u8)> {
+        let above = level.saturating_sub(self.level.get());
+        let mut entries = vec![];
+        let mut highest = None;
+        for (heightening, text) in self.heightened.iter() {
+            match heightening {
+                Heightening::Every(n) if *n > 0 && above >= *n => {
+                    entries.push((*heightening, text.as_str(), above / n));
+                }
+                Heightening::At(n) if *n <= level => {
+                    highest = Some((*heightening, text.as_str(), 1))
+                }
+                _ => (),
+            }
+        }
+        entries.extend(highest);
+        entries
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use proptest::prelude::*;
@@ -723,6 +1149,83 @@ mod tests {
         assert_eq!(left_value, right_value);
     }
 
+    #[test]
+    fn deserialize_item() {
+        let raw = r###"
+{ "name": "Longbow"
+, "price": "6 gp"
+, "bulk": 2
+, "hands": 1
+, "usage": "held in 1+ hands"
+, "item type": "weapon"
+, "weapon":
+  { "category": "martial"
+  , "group": "bow"
+  , "damage die": "d8"
+  , "damage type": "P"
+  , "range": 100
+  , "traits": ["deadly d10", "volley 30 ft."]
+  }
+}"###;
+        let item: Item = serde_json::from_str(raw).unwrap();
+        assert_eq!(item.price, Gold::gp(6));
+        assert_eq!(item.bulk, Bulk::Heavy(2));
+        assert_eq!(item.rarity, Rarity::Common);
+        let weapon = item.weapon.unwrap();
+        assert_eq!(weapon.range, Some(100));
+        assert_eq!(
+            weapon.traits.as_slice(),
+            &[
+                crate::items::WeaponTrait::Deadly(crate::stats::Die::D10),
+                crate::items::WeaponTrait::Volley(30),
+            ]
+        );
+    }
+
+    #[test]
+    fn crb_items() {
+        let items = crate::test_helpers::crb_items()
+            .into_iter()
+            .map(|r| match r {
+                Resource::Item(item) => item,
+                other => panic!("Expected an item, got {:?}", other.resource_type()),
+            })
+            .collect::<Vec<_>>();
+        let find = |name: &str| {
+            items
+                .iter()
+                .find(|i| i.common.name == name)
+                .unwrap_or_else(|| panic!("{} is missing", name))
+        };
+
+        let armor = find("Studded Leather");
+        assert_eq!(armor.price, Gold::gp(3));
+        assert_eq!(armor.bulk, Bulk::Heavy(1));
+        assert_eq!(armor.item_type, ItemType::Armor);
+        let stats = armor.armor.as_ref().unwrap();
+        assert_eq!(stats.ac_bonus, 2);
+        assert_eq!(stats.dex_cap, Some(3));
+        assert_eq!(stats.check_penalty, 1);
+        assert_eq!(stats.strength, Some(12));
+
+        let longbow = find("Longbow");
+        assert_eq!(longbow.price, Gold::gp(6));
+        assert_eq!(longbow.bulk, Bulk::Heavy(2));
+        assert_eq!(longbow.hands, 1);
+        let weapon = longbow.weapon.as_ref().unwrap();
+        assert_eq!(weapon.damage_die, crate::stats::Die::D8);
+        assert_eq!(weapon.damage_type, crate::stats::DamageType::P);
+        assert_eq!(weapon.range, Some(100));
+        assert_eq!(weapon.traits.len(), 2);
+
+        let backpack = find("Backpack");
+        assert_eq!(backpack.price, Gold::sp(1));
+        assert_eq!(backpack.bulk, Bulk::Light);
+        let container = backpack.container.as_ref().unwrap();
+        assert_eq!(container.capacity, 4);
+        assert_eq!(container.ignored_bulk, 2);
+    }
+
     macro_rules! make_roundtrip_proptest {
         ($test_name:ident : $t:ty) => {
             proptest! {
@@ -760,16 +1263,22 @@ mod tests {
     make_roundtrip_proptest!(roundtrip_conditions: Conditions);
     make_roundtrip_proptest!(roundtrip_effect: Effect);
     make_roundtrip_proptest!(roundtrip_skill: Skill);
-
-    // make_roundtrip_proptest!(roundtrip_ancestry: Ancestry);
-    // make_roundtrip_proptest!(roundtrip_action: Action);
-    // make_roundtrip_proptest!(roundtrip_background: Background);
-    // // make_roundtrip_proptest!(roundtrip_class: Class);
-    // // make_roundtrip_proptest!(roundtrip_classfeature: ClassFeature);
-    // make_roundtrip_proptest!(roundtrip_feat: Feat);
-    // make_roundtrip_proptest!(roundtrip_heritage: Heritage);
-    // make_roundtrip_proptest!(roundtrip_item: Item);
-    // make_roundtrip_proptest!(roundtrip_spell: Spell);
+    make_roundtrip_proptest!(roundtrip_weapon: crate::items::Weapon);
+    make_roundtrip_proptest!(roundtrip_inventory_item: crate::inventory::InventoryItem);
+    make_roundtrip_proptest!(roundtrip_ledger_entry: crate::wealth::LedgerEntry);
+    make_roundtrip_proptest!(roundtrip_play_state: crate::play::PlayState);
+    make_roundtrip_proptest!(roundtrip_level_record: crate::levels::LevelRecord);
+
+    make_roundtrip_proptest!(roundtrip_ancestry: Ancestry);
+    make_roundtrip_proptest!(roundtrip_action: Action);
+    make_roundtrip_proptest!(roundtrip_background: Background);
+    // make_roundtrip_proptest!(roundtrip_class: Class);
+    // make_roundtrip_proptest!(roundtrip_classfeature: ClassFeature);
+    make_roundtrip_proptest!(roundtrip_feat: Feat);
+    make_roundtrip_proptest!(roundtrip_heritage: Heritage);
+    make_roundtrip_proptest!(roundtrip_condition: Condition);
+    make_roundtrip_proptest!(roundtrip_item: Item);
+    make_roundtrip_proptest!(roundtrip_spell: Spell);
 
     // make_roundtrip_proptest!(roundtrip_resource: Resource);
 }
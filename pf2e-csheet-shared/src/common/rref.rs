@@ -1,3 +1,7 @@
+#[cfg(test)]
+use proptest::prelude::*;
+#[cfg(test)]
+use proptest_derive::Arbitrary;
 use serde::Deserialize;
 use smartstring::alias::String;
 use std::{fmt, marker::PhantomData, str::FromStr};
@@ -38,6 +42,22 @@ impl ResourceRef {
         self
     }
 
+    /// Returns true if this reference names the same resource as `wanted`.
+    /// Names are compared case-insensitively, and the modifier and resource
+    /// type are only compared when `wanted` specifies them.
+    pub fn matches(&self, wanted: &ResourceRef) -> bool {
+        if !self.name.eq_ignore_ascii_case(&wanted.name) {
+            return false;
+        }
+        if wanted.modifier.is_some() && self.modifier != wanted.modifier {
+            return false;
+        }
+        match (self.resource_type, wanted.resource_type) {
+            (Some(have), Some(want)) => have == want,
+            _ => true,
+        }
+    }
+
     pub fn as_typed<R: HasResourceType>(self) -> Result<TypedRef<R>, Self> {
         match self.resource_type {
             None => Ok(TypedRef {
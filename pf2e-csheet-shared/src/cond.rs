@@ -10,7 +10,7 @@ use std::collections::HashMap;
 
 use crate::{
     calc::CalcContext,
-    common::ResourceRef,
+    common::{Item, Resource, ResourceRef, ResourceType},
     items::{ArmorCategory, ItemType},
     stats::Proficiency,
 };
@@ -34,6 +34,9 @@ struct AllSingleConditions {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     proficiency: Option<ProficiencyCondition>,
+    #[serde(default, rename = "weapon proficiency")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weapon_proficiency: Option<WeaponProficiencyCondition>,
 
     #[serde(default, rename = "unenforced (unknown)")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -90,6 +93,7 @@ pub enum SingleCondition {
     ArmorCategory(ArmorCategory),
     ItemHasTrait(ItemHasTraitCondition),
     Proficiency(ProficiencyCondition),
+    WeaponProficiency(WeaponProficiencyCondition),
     HaveResource(ResourceRef),
     #[from(ignore)]
     Unenforced(UnenforcedCondition),
@@ -109,6 +113,7 @@ impl From<SingleCondition> for AllSingleConditions {
             SingleCondition::HaveResource(r) => all.have_resource = Some(r),
             SingleCondition::ItemHasTrait(s) => all.item_trait = Some(s),
             SingleCondition::Proficiency(pc) => all.proficiency = Some(pc),
+            SingleCondition::WeaponProficiency(wc) => all.weapon_proficiency = Some(wc),
             SingleCondition::Unenforced(u) => {
                 if u.known {
                     all.unenforced_known = Some(u.text);
@@ -135,6 +140,7 @@ impl From<AllSingleConditions> for Condition {
         field!(have_resource, SingleCondition::HaveResource);
         field!(item_trait, SingleCondition::ItemHasTrait);
         field!(proficiency, SingleCondition::Proficiency);
+        field!(weapon_proficiency, SingleCondition::WeaponProficiency);
         field!(unenforced_unknown, UnenforcedCondition::unknown);
         field!(unenforced_known, UnenforcedCondition::known);
 
@@ -143,12 +149,31 @@ impl From<AllSingleConditions> for Condition {
 }
 
 impl SingleCondition {
-    fn reject(&self, _ctx: CalcContext<'_>) -> bool {
+    /// What the condition asks for, like "expert in Athletics".
+    fn describe(&self) -> String {
+        match self {
+            Self::ArmorCategory(ArmorCategory::Unarmored) => "being unarmored".into(),
+            Self::ArmorCategory(ArmorCategory::LightArmor) => "wearing light armor".into(),
+            Self::ArmorCategory(ArmorCategory::MediumArmor) => "wearing medium armor".into(),
+            Self::ArmorCategory(ArmorCategory::HeavyArmor) => "wearing heavy armor".into(),
+            Self::HaveResource(r) => match r.modifier.as_ref() {
+                Some(m) => format!("{} ({})", r.name, m),
+                None => r.name.clone(),
+            },
+            Self::ItemHasTrait(t) => t.describe(),
+            Self::Proficiency(c) => c.describe(),
+            Self::WeaponProficiency(c) => c.describe(),
+            Self::Unenforced(u) => u.text.clone(),
+        }
+    }
+
+    fn reject(&self, ctx: CalcContext<'_>) -> bool {
         match self {
-            Self::ArmorCategory(ac) => todo!("ArmorCategory({:?}).reject", ac),
-            Self::HaveResource(r) => todo!("HaveResource({}).reject", r),
-            Self::ItemHasTrait(t) => todo!("ItemHasTrait({:?}).reject", t),
-            Self::Proficiency(c) => todo!("Proficiency({:?}).reject", c),
+            Self::ArmorCategory(ac) => ctx.character.armor_category(ctx.resources) != *ac,
+            Self::HaveResource(r) => !ctx.character.has_resource(r),
+            Self::ItemHasTrait(t) => !t.matches(ctx),
+            Self::Proficiency(c) => !c.matches(ctx),
+            Self::WeaponProficiency(c) => !c.matches(ctx),
             Self::Unenforced(_) => false,
         }
     }
@@ -248,10 +273,10 @@ impl Arbitrary for Condition {
     fn arbitrary_with(_args: ()) -> Self::Strategy {
         let leaf = prop_oneof![
             Just(Condition::None),
-            any::<std::string::String>()
-                .prop_map_into()
-                .prop_map(Condition::ItemHasTrait),
-            arb_proficiency_condition().prop_map(Condition::Proficiency),
+            any::<ItemHasTraitCondition>().prop_map(|c| SingleCondition::ItemHasTrait(c).into()),
+            arb_proficiency_condition().prop_map(|c| SingleCondition::Proficiency(c).into()),
+            any::<WeaponProficiencyCondition>()
+                .prop_map(|c| SingleCondition::WeaponProficiency(c).into()),
         ];
         leaf.prop_recursive(
             3,  // levels deep
@@ -328,6 +353,7 @@ impl From<AllConditions> for Condition {
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub struct ItemHasTraitCondition {
+    #[serde(default)]
     #[serde(skip_serializing_if = "crate::is_default")]
     item_slots: ItemType,
     #[cfg_attr(
@@ -339,6 +365,60 @@ pub struct ItemHasTraitCondition {
     item_traits: SmallVec<[String; 1]>,
 }
 
+impl ItemHasTraitCondition {
+    pub fn new<T: Into<String>>(
+        item_slots: ItemType,
+        item_traits: impl IntoIterator<Item = T>,
+    ) -> Self {
+        Self {
+            item_slots,
+            item_traits: item_traits.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let item = match self.item_slots {
+            ItemType::Armor => "armor",
+            ItemType::Shield => "a shield",
+            ItemType::Weapon => "a weapon",
+            ItemType::Any | ItemType::Other => "an item",
+        };
+        let traits: Vec<&str> = self.item_traits.iter().map(|t| t.as_str()).collect();
+        match traits.as_slice() {
+            [] => item.into(),
+            [t] => format!("{} with the {} trait", item, t),
+            _ => format!("{} with the {} traits", item, traits.join(" and ")),
+        }
+    }
+
+    fn matches_item(&self, item: &Item) -> bool {
+        let slot_ok = match self.item_slots {
+            ItemType::Any => true,
+            slot => slot == item.item_type,
+        };
+        slot_ok && self.item_traits.iter().all(|t| item.has_trait(t))
+    }
+
+    /// When the context targets an item only that item is checked, otherwise
+    /// any item the character is using will do.
+    fn matches(&self, ctx: CalcContext<'_>) -> bool {
+        let resources = ctx.resources;
+        let is_match = |rref: &ResourceRef| match resources.lookup_immediate(rref).as_deref() {
+            Some(Resource::Item(item)) => self.matches_item(item),
+            _ => false,
+        };
+        if let Some(target) = ctx.target {
+            if target.resource_type == Some(ResourceType::Item) {
+                return is_match(target);
+            }
+        }
+        ctx.character
+            .loadout(resources)
+            .iter()
+            .any(|equipped| self.matches_item(&equipped.item))
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 #[serde(untagged)]
@@ -364,6 +444,27 @@ pub enum ProficiencyCondition {
     },
 }
 
+impl ProficiencyCondition {
+    fn describe(&self) -> String {
+        match self {
+            Self::AtLeast { target, at_least } => format!("{} in {}", at_least, target),
+            Self::Exactly { target, exactly } => format!("exactly {} in {}", exactly, target),
+        }
+    }
+
+    fn matches(&self, ctx: CalcContext<'_>) -> bool {
+        let c = ctx.character;
+        match self {
+            Self::AtLeast { target, at_least } => {
+                c.get_proficiency(target, ctx.target, ctx.resources).0 >= *at_least
+            }
+            Self::Exactly { target, exactly } => {
+                c.get_proficiency(target, ctx.target, ctx.resources).0 == *exactly
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) fn arb_proficiency_condition() -> impl Strategy<Value = ProficiencyCondition> {
     prop_oneof![
@@ -380,16 +481,77 @@ pub(crate) fn arb_proficiency_condition() -> impl Strategy<Value = ProficiencyCo
     ]
 }
 
+/// Checks the rank in whichever proficiency the targeted weapon uses, so
+/// it never matches without a weapon to check.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+#[serde(untagged)]
+pub enum WeaponProficiencyCondition {
+    AtLeast {
+        #[serde(rename = "at least")]
+        at_least: Proficiency,
+    },
+    Exactly {
+        #[serde(alias = "exact")]
+        exactly: Proficiency,
+    },
+}
+
+impl WeaponProficiencyCondition {
+    fn describe(&self) -> String {
+        match self {
+            Self::AtLeast { at_least } => format!("{} with the weapon", at_least),
+            Self::Exactly { exactly } => format!("exactly {} with the weapon", exactly),
+        }
+    }
+
+    fn matches(&self, ctx: CalcContext<'_>) -> bool {
+        let weapon = match ctx.target {
+            Some(t) => t,
+            None => return false,
+        };
+        let rank = ctx.character.weapon_proficiency(weapon, ctx.resources);
+        match self {
+            Self::AtLeast { at_least } => rank >= *at_least,
+            Self::Exactly { exactly } => rank == *exactly,
+        }
+    }
+}
+
 impl Condition {
     fn reject(&self, ctx: CalcContext<'_>) -> bool {
         match self {
             Self::None => false,
-            Self::Negate(c) => c.reject(ctx),
-            Self::Or(conds) => conds.iter().any(|c| c.reject(ctx)),
-            Self::And(conds) => conds.iter().all(|c| c.reject(ctx)),
+            Self::Negate(c) => !c.reject(ctx),
+            Self::Or(conds) => conds.iter().all(|c| c.reject(ctx)),
+            Self::And(conds) => conds.iter().any(|c| c.reject(ctx)),
             Self::Single(sc) => sc.reject(ctx),
         }
     }
+
+    fn describe(&self) -> String {
+        let join = |conds: &[Condition], sep: &str| -> String {
+            let parts: Vec<String> = conds.iter().map(Condition::describe).collect();
+            parts.join(sep).into()
+        };
+        match self {
+            Self::None => "nothing".into(),
+            Self::Negate(c) => format!("not {}", c.describe()),
+            Self::Or(conds) => join(conds, " or "),
+            Self::And(conds) => join(conds, " and "),
+            Self::Single(sc) => sc.describe(),
+        }
+    }
+
+    /// Explain each part of the condition that `ctx` doesn't meet. Each part
+    /// of an AND is explained on its own, other conditions as a whole.
+    fn unmet(&self, ctx: CalcContext<'_>) -> Vec<String> {
+        match self {
+            Self::And(conds) => conds.iter().flat_map(|c| c.unmet(ctx)).collect(),
+            _ if self.reject(ctx) => vec![format!("requires {}", self.describe())],
+            _ => vec![],
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
@@ -404,6 +566,12 @@ impl Conditions {
         self.inner.reject(ctx)
     }
 
+    /// Readable explanations of what isn't met, like "requires expert in
+    /// Athletics". This is empty when the conditions are met.
+    pub fn unmet(&self, ctx: CalcContext<'_>) -> Vec<String> {
+        self.inner.unmet(ctx)
+    }
+
     pub fn is_none(&self) -> bool {
         match self.inner {
             Condition::None => true,
@@ -426,28 +594,269 @@ impl std::ops::BitAndAssign<Condition> for Conditions {
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-
-//     #[inline]
-//     fn wrap_single(c: Condition) -> Conditions {
-//         Conditions { inner: c }
-//     }
-
-//     #[test]
-//     fn deserialize_proficiency_at_least() {
-//         let raw = r###"\
-// { "proficiency":
-//   { "in": "martial weapons"
-//   , "at least": "master"
-//   }
-// }"###;
-//         let parsed: Conditions = serde_json::from_str(raw).unwrap();
-//         let expected = Condition::Proficiency(ProficiencyCondition::AtLeast {
-//             target: "martial weapons".into(),
-//             at_least: Proficiency::Master,
-//         });
-//         assert_eq!(parsed, wrap_single(expected));
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::ResourceCommon,
+        items::Armor,
+        test_helpers::{proficiency_feat, setup, TestStorage},
+        Character,
+    };
+
+    #[inline]
+    fn wrap_single(c: Condition) -> Conditions {
+        Conditions { inner: c }
+    }
+
+    fn item(name: &str, item_type: ItemType, traits: &[&str]) -> Resource {
+        let mut common = ResourceCommon::new(name);
+        common.add_traits(traits);
+        Resource::Item(Item::new(common, item_type))
+    }
+
+    fn armor(name: &str, category: ArmorCategory) -> Resource {
+        match item(name, ItemType::Armor, &[]) {
+            Resource::Item(mut i) => {
+                i.armor = Some(Armor::new(category));
+                Resource::Item(i)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn check(cond: &Condition, character: &Character, storage: &TestStorage) -> bool {
+        let rref = ResourceRef::new("Test Resource", None::<&str>);
+        !cond.reject(CalcContext::new(character, &rref, storage))
+    }
+
+    fn have(name: &str) -> Condition {
+        SingleCondition::HaveResource(ResourceRef::new(name, None::<&str>)).into()
+    }
+
+    #[test]
+    fn deserialize_proficiency_at_least() {
+        let raw = r###"
+{ "proficiency":
+  { "in": "martial weapons"
+  , "at least": "master"
+  }
+}"###;
+        let parsed: Conditions = serde_json::from_str(raw).unwrap();
+        let expected = SingleCondition::Proficiency(ProficiencyCondition::AtLeast {
+            target: "martial weapons".into(),
+            at_least: Proficiency::Master,
+        })
+        .into();
+        assert_eq!(parsed, wrap_single(expected));
+    }
+
+    #[test]
+    fn armor_category() {
+        let (unarmored, storage) = setup(vec![]);
+        let cond: Condition = ArmorCategory::Unarmored.into();
+        assert!(check(&cond, &unarmored, &storage));
+        let cond: Condition = ArmorCategory::MediumArmor.into();
+        assert!(!check(&cond, &unarmored, &storage));
+
+        let (armored, storage) = setup(vec![
+            armor("Leather", ArmorCategory::LightArmor),
+            armor("Hide", ArmorCategory::MediumArmor),
+        ]);
+        assert!(check(&cond, &armored, &storage));
+        let cond: Condition = ArmorCategory::Unarmored.into();
+        assert!(!check(&cond, &armored, &storage));
+    }
+
+    #[test]
+    fn have_resource() {
+        let (character, storage) = setup(vec![proficiency_feat(
+            "Toughness",
+            &[("FORT", Proficiency::Trained)],
+        )]);
+        assert!(check(&have("Toughness"), &character, &storage));
+        assert!(check(&have("toughness"), &character, &storage));
+        assert!(!check(&have("Fleet"), &character, &storage));
+
+        let typed = ResourceRef::new("Toughness", None::<&str>).with_type(Some(ResourceType::Item));
+        let cond: Condition = SingleCondition::HaveResource(typed).into();
+        assert!(!check(&cond, &character, &storage));
+    }
+
+    #[test]
+    fn item_has_trait() {
+        let (character, storage) = setup(vec![
+            item(
+                "Rapier",
+                ItemType::Weapon,
+                &["Deadly d8", "Disarm", "Finesse"],
+            ),
+            item("Backpack", ItemType::Other, &[]),
+        ]);
+        let finesse = |slot| -> Condition {
+            SingleCondition::ItemHasTrait(ItemHasTraitCondition::new(slot, vec!["finesse"])).into()
+        };
+        assert!(check(&finesse(ItemType::Any), &character, &storage));
+        assert!(check(&finesse(ItemType::Weapon), &character, &storage));
+        assert!(!check(&finesse(ItemType::Shield), &character, &storage));
+
+        let both: Condition = SingleCondition::ItemHasTrait(ItemHasTraitCondition::new(
+            ItemType::Any,
+            vec!["Finesse", "Agile"],
+        ))
+        .into();
+        assert!(!check(&both, &character, &storage));
+
+        // A targeted item is checked on its own.
+        let backpack =
+            ResourceRef::new("Backpack", None::<&str>).with_type(Some(ResourceType::Item));
+        let rref = ResourceRef::new("Test Resource", None::<&str>);
+        let ctx = CalcContext::new(&character, &rref, &storage).with_target(&backpack);
+        assert!(finesse(ItemType::Any).reject(ctx));
+    }
+
+    #[test]
+    fn proficiency() {
+        let (character, storage) = setup(vec![
+            proficiency_feat(
+                "Weapon Training",
+                &[("martial weapons", Proficiency::Trained)],
+            ),
+            proficiency_feat(
+                "Weapon Expertise",
+                &[("Martial Weapons", Proficiency::Expert)],
+            ),
+        ]);
+        let at_least = |at_least| -> Condition {
+            SingleCondition::Proficiency(ProficiencyCondition::AtLeast {
+                target: "martial weapons".into(),
+                at_least,
+            })
+            .into()
+        };
+        let exactly = |exactly| -> Condition {
+            SingleCondition::Proficiency(ProficiencyCondition::Exactly {
+                target: "martial weapons".into(),
+                exactly,
+            })
+            .into()
+        };
+        assert!(check(&at_least(Proficiency::Trained), &character, &storage));
+        assert!(check(&at_least(Proficiency::Expert), &character, &storage));
+        assert!(!check(&at_least(Proficiency::Master), &character, &storage));
+        assert!(check(&exactly(Proficiency::Expert), &character, &storage));
+        assert!(!check(&exactly(Proficiency::Trained), &character, &storage));
+
+        let untrained: Condition = SingleCondition::Proficiency(ProficiencyCondition::Exactly {
+            target: "simple weapons".into(),
+            exactly: Proficiency::Untrained,
+        })
+        .into();
+        assert!(check(&untrained, &character, &storage));
+    }
+
+    #[test]
+    fn unenforced_always_passes() {
+        let (character, storage) = setup(vec![]);
+        let cond: Condition = UnenforcedCondition::unknown("wielding a bow").into();
+        assert!(check(&cond, &character, &storage));
+    }
+
+    #[test]
+    fn negate_or_and() {
+        let (character, storage) = setup(vec![proficiency_feat(
+            "Toughness",
+            &[("FORT", Proficiency::Trained)],
+        )]);
+        let yes = || have("Toughness");
+        let no = || have("Fleet");
+
+        assert!(check(&Condition::None, &character, &storage));
+        assert!(!check(
+            &Condition::Negate(Box::new(yes())),
+            &character,
+            &storage
+        ));
+        assert!(check(
+            &Condition::Negate(Box::new(no())),
+            &character,
+            &storage
+        ));
+
+        assert!(check(
+            &Condition::Or(vec![no(), yes()]),
+            &character,
+            &storage
+        ));
+        assert!(check(
+            &Condition::Or(vec![yes(), yes()]),
+            &character,
+            &storage
+        ));
+        assert!(!check(
+            &Condition::Or(vec![no(), no()]),
+            &character,
+            &storage
+        ));
+
+        assert!(check(
+            &Condition::And(vec![yes(), yes()]),
+            &character,
+            &storage
+        ));
+        assert!(!check(
+            &Condition::And(vec![yes(), no()]),
+            &character,
+            &storage
+        ));
+        assert!(!check(
+            &Condition::And(vec![no(), no()]),
+            &character,
+            &storage
+        ));
+    }
+
+    #[test]
+    fn explain_unmet() {
+        let (character, storage) = setup(vec![proficiency_feat(
+            "Athlete",
+            &[("Athletics", Proficiency::Trained)],
+        )]);
+        let rref = ResourceRef::new("Test Resource", None::<&str>);
+        let ctx = CalcContext::new(&character, &rref, &storage);
+        let expert = |target: &str| -> Condition {
+            SingleCondition::Proficiency(ProficiencyCondition::AtLeast {
+                target: target.into(),
+                at_least: Proficiency::Expert,
+            })
+            .into()
+        };
+        let trained: Condition = SingleCondition::Proficiency(ProficiencyCondition::AtLeast {
+            target: "Athletics".into(),
+            at_least: Proficiency::Trained,
+        })
+        .into();
+
+        let mut prereqs = Conditions::default();
+        assert!(prereqs.unmet(ctx).is_empty());
+        prereqs &= trained;
+        prereqs &= expert("Athletics");
+        prereqs &= have("Power Attack");
+        prereqs &= Condition::Or(vec![ArmorCategory::HeavyArmor.into(), expert("Acrobatics")]);
+        assert_eq!(
+            prereqs.unmet(ctx),
+            vec![
+                String::from("requires expert in Athletics"),
+                "requires Power Attack".into(),
+                "requires wearing heavy armor or expert in Acrobatics".into(),
+            ]
+        );
+
+        let mut prereqs = Conditions::default();
+        prereqs &= Condition::Negate(Box::new(have("Athlete")));
+        assert_eq!(
+            prereqs.unmet(ctx),
+            vec![String::from("requires not Athlete")]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+#[cfg(test)]
+use proptest::prelude::*;
+#[cfg(test)]
+use proptest_derive::Arbitrary;
+use serde::Deserialize;
+use smallvec::SmallVec;
+use smartstring::alias::String;
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use crate::stats::DamageType;
+
+/// What a resistance, weakness or immunity applies to.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+#[serde(try_from = "smartstring::alias::String")]
+pub enum DamageKind {
+    All,
+    /// Bludgeoning, piercing and slashing damage.
+    Physical,
+    Type(DamageType),
+    /// Any other damage type or trait, like "fire", "poison" or "silver".
+    Other(
+        #[cfg_attr(
+            test,
+            proptest(
+                strategy = "\"acid|cold|electricity|fire|sonic|poison|mental|silver\".prop_map_into()"
+            )
+        )]
+        String,
+    ),
+}
+
+impl DamageKind {
+    pub fn applies_to(&self, damage: &Damage) -> bool {
+        match self {
+            Self::All => true,
+            Self::Physical => matches!(damage.damage_type, Self::Type(_) | Self::Physical),
+            Self::Type(_) => &damage.damage_type == self,
+            Self::Other(name) => {
+                matches!(&damage.damage_type, Self::Other(t) if t.eq_ignore_ascii_case(name))
+                    || damage.traits.iter().any(|t| t.eq_ignore_ascii_case(name))
+            }
+        }
+    }
+}
+
+impl fmt::Display for DamageKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Physical => write!(f, "physical"),
+            Self::Type(t) => write!(f, "{}", t),
+            Self::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+serialize_display!(DamageKind);
+try_from_str!(DamageKind);
+
+impl FromStr for DamageKind {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Ok(match s.to_lowercase().as_str() {
+            "all" | "all damage" => Self::All,
+            "physical" => Self::Physical,
+            other => match s.parse::<DamageType>().or_else(|_| other.parse()) {
+                Ok(t) => Self::Type(t),
+                Err(_) => Self::Other(other.into()),
+            },
+        })
+    }
+}
+
+/// Damage dealt to a character, before their defenses.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Damage {
+    pub amount: u16,
+    pub damage_type: DamageKind,
+    /// Traits of whatever dealt the damage, like "magical" or "silver".
+    pub traits: SmallVec<[String; 1]>,
+}
+
+impl Damage {
+    pub fn new(amount: u16, damage_type: impl Into<DamageKind>) -> Self {
+        Self {
+            amount,
+            damage_type: damage_type.into(),
+            traits: SmallVec::new(),
+        }
+    }
+
+    pub fn with_trait(mut self, t: impl Into<String>) -> Self {
+        self.traits.push(t.into());
+        self
+    }
+}
+
+impl From<DamageType> for DamageKind {
+    fn from(t: DamageType) -> Self {
+        Self::Type(t)
+    }
+}
+
+impl From<&str> for DamageKind {
+    fn from(s: &str) -> Self {
+        match s.parse() {
+            Ok(kind) => kind,
+            Err(e) => match e {},
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DefenseType {
+    Resistance,
+    Weakness,
+    Immunity,
+}
+
+/// A character's resistances, weaknesses and immunities. Only the highest
+/// resistance and weakness to each kind of damage is kept, since they don't
+/// stack.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Defenses {
+    pub immunities: Vec<DamageKind>,
+    pub resistances: Vec<(DamageKind, u16)>,
+    pub weaknesses: Vec<(DamageKind, u16)>,
+}
+
+impl Defenses {
+    pub fn is_empty(&self) -> bool {
+        self.immunities.is_empty() && self.resistances.is_empty() && self.weaknesses.is_empty()
+    }
+
+    pub fn add(&mut self, defense_type: DefenseType, kind: DamageKind, value: u16) {
+        let list = match defense_type {
+            DefenseType::Immunity => {
+                if !self.immunities.contains(&kind) {
+                    self.immunities.push(kind);
+                }
+                return;
+            }
+            DefenseType::Resistance => &mut self.resistances,
+            DefenseType::Weakness => &mut self.weaknesses,
+        };
+        if value == 0 {
+            return;
+        }
+        match list.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, existing)) => *existing = (*existing).max(value),
+            None => list.push((kind, value)),
+        }
+    }
+
+    pub fn is_immune(&self, damage: &Damage) -> bool {
+        self.immunities.iter().any(|k| k.applies_to(damage))
+    }
+
+    fn highest(list: &[(DamageKind, u16)], damage: &Damage) -> u16 {
+        list.iter()
+            .filter(|(k, _)| k.applies_to(damage))
+            .map(|(_, v)| *v)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// How much of `damage` gets through. Immunities apply first, then the
+    /// highest weakness, then the highest resistance.
+    pub fn apply(&self, damage: &Damage) -> u16 {
+        if damage.amount == 0 || self.is_immune(damage) {
+            return 0;
+        }
+        let weakened = damage
+            .amount
+            .saturating_add(Self::highest(&self.weaknesses, damage));
+        weakened.saturating_sub(Self::highest(&self.resistances, damage))
+    }
+
+    /// Values for the defense fields on the PDF character sheet, by field ID.
+    pub fn pdf_fields(&self) -> Vec<(&'static str, String)> {
+        vec![("ResistancesAndImmunities", format!("{}", self))]
+    }
+}
+
+impl fmt::Display for Defenses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = vec![];
+        if !self.immunities.is_empty() {
+            let kinds: Vec<String> = self.immunities.iter().map(|k| format!("{}", k)).collect();
+            parts.push(format!("immune {}", kinds.join(", ")));
+        }
+        for (kind, value) in self.resistances.iter() {
+            parts.push(format!("resist {} {}", kind, value));
+        }
+        for (kind, value) in self.weaknesses.iter() {
+            parts.push(format!("weak {} {}", kind, value));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_damage_kinds() {
+        assert_eq!("all".parse::<DamageKind>(), Ok(DamageKind::All));
+        assert_eq!("Physical".parse::<DamageKind>(), Ok(DamageKind::Physical));
+        assert_eq!(
+            "slashing".parse::<DamageKind>(),
+            Ok(DamageKind::Type(DamageType::S))
+        );
+        assert_eq!(
+            "Fire".parse::<DamageKind>(),
+            Ok(DamageKind::Other("fire".into()))
+        );
+    }
+
+    #[test]
+    fn apply_defenses() {
+        let mut defenses = Defenses::default();
+        defenses.add(DefenseType::Resistance, DamageKind::Physical, 3);
+        defenses.add(DefenseType::Resistance, DamageKind::Physical, 5);
+        defenses.add(DefenseType::Resistance, DamageKind::Physical, 2);
+        defenses.add(DefenseType::Resistance, "fire".into(), 2);
+        defenses.add(DefenseType::Weakness, "silver".into(), 4);
+        defenses.add(DefenseType::Immunity, "poison".into(), 0);
+        assert_eq!(defenses.resistances.len(), 2);
+
+        assert_eq!(defenses.apply(&Damage::new(8, DamageType::S)), 3);
+        assert_eq!(defenses.apply(&Damage::new(4, DamageType::P)), 0);
+        assert_eq!(defenses.apply(&Damage::new(6, "fire")), 4);
+        assert_eq!(defenses.apply(&Damage::new(6, "cold")), 6);
+        assert_eq!(defenses.apply(&Damage::new(10, "poison")), 0);
+        // Weakness applies before resistance.
+        let silver = Damage::new(3, DamageType::P).with_trait("silver");
+        assert_eq!(defenses.apply(&silver), 2);
+        let huge = Damage::new(u16::MAX, DamageType::P).with_trait("silver");
+        assert_eq!(defenses.apply(&huge), u16::MAX - 5);
+
+        assert_eq!(
+            format!("{}", defenses),
+            "immune poison; resist physical 5; resist fire 2; weak silver 4"
+        );
+    }
+}
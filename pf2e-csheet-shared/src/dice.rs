@@ -0,0 +1,273 @@
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::fmt;
+
+use crate::{
+    bonuses::Modifier,
+    calc::{CalcContext, Calculation, Op},
+    stats::{Die, DieRoll},
+};
+
+/// A single die that was rolled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct DieResult {
+    pub die: Die,
+    pub value: u16,
+}
+
+impl fmt::Display for DieResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.die, self.value)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct RollResult {
+    pub dice: SmallVec<[DieResult; 2]>,
+    /// Everything in the total that didn't come from a die.
+    pub modifier: i16,
+    /// The typed bonuses and penalties that make up `modifier`, when they're
+    /// known.
+    pub breakdown: Modifier,
+}
+
+impl RollResult {
+    pub fn dice_total(&self) -> i16 {
+        self.dice.iter().map(|d| d.value as i16).sum()
+    }
+
+    pub fn total(&self) -> i16 {
+        self.dice_total() + self.modifier
+    }
+
+    /// The value showing on the die if this was a single d20 roll.
+    pub fn natural(&self) -> Option<u16> {
+        match self.dice.as_slice() {
+            [DieResult {
+                die: Die::D20,
+                value,
+            }] => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RollResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (", self.total())?;
+        for (i, d) in self.dice.iter().enumerate() {
+            if i > 0 {
+                write!(f, " + ")?;
+            }
+            write!(f, "{}", d.value)?;
+        }
+        match (self.dice.is_empty(), self.modifier) {
+            (true, m) => write!(f, "{})", m),
+            (false, 0) => write!(f, ")"),
+            (false, m) if m < 0 => write!(f, " - {})", -m),
+            (false, m) => write!(f, " + {})", m),
+        }
+    }
+}
+
+/// Rolls dice with an injectable random number generator, so that tests (or
+/// anything else that needs to) can get repeatable results.
+#[derive(Clone, Debug)]
+pub struct Roller<R: RngCore = StdRng> {
+    rng: R,
+}
+
+impl Roller<StdRng> {
+    pub fn from_entropy() -> Self {
+        Self::new(StdRng::from_entropy())
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self::new(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl<R: RngCore> Roller<R> {
+    pub fn new(rng: R) -> Self {
+        Self { rng }
+    }
+
+    pub fn roll_die(&mut self, die: Die) -> DieResult {
+        let value = self.rng.gen_range(1, die.sides() + 1);
+        DieResult { die, value }
+    }
+
+    pub fn roll(&mut self, roll: DieRoll) -> RollResult {
+        let dice = (0..roll.count).map(|_| self.roll_die(roll.size)).collect();
+        RollResult {
+            dice,
+            modifier: 0,
+            breakdown: Modifier::new(),
+        }
+    }
+
+    /// Roll a d20 check with the given modifier.
+    pub fn roll_check(&mut self, modifier: &Modifier) -> RollResult {
+        let mut dice = SmallVec::new();
+        dice.push(self.roll_die(Die::D20));
+        RollResult {
+            dice,
+            modifier: modifier.total(),
+            breakdown: modifier.clone(),
+        }
+    }
+
+    pub fn roll_calculation(&mut self, calc: &Calculation, ctx: CalcContext<'_>) -> RollResult {
+        let mut dice: SmallVec<[DieResult; 2]> = SmallVec::new();
+        let total = calc.evaluate_with_dice(ctx, &mut |roll| {
+            let start = dice.len();
+            dice.extend((0..roll.count).map(|_| self.roll_die(roll.size)));
+            dice[start..].iter().map(|d| d.value as i16).sum()
+        });
+        let dice_total: i16 = dice.iter().map(|d| d.value as i16).sum();
+        let modifier = total - dice_total;
+        let breakdown = match typed_modifier(calc, ctx) {
+            Some(typed) if typed.total() == modifier => typed,
+            _ => Modifier::untyped(modifier),
+        };
+        RollResult {
+            dice,
+            modifier,
+            breakdown,
+        }
+    }
+}
+
+/// The typed bonuses and penalties in a calculation that adds up dice, named
+/// modifiers and numbers, like "1d8 + STR bonus + 2". Anything else only has
+/// an untyped total.
+fn typed_modifier(calc: &Calculation, ctx: CalcContext<'_>) -> Option<Modifier> {
+    match calc {
+        Calculation::Dice(_) => Some(Modifier::new()),
+        Calculation::Modifier(m) => Some(m.clone()),
+        Calculation::Named(name) => {
+            Some(ctx.character.get_modifier(name, ctx.target, ctx.resources))
+        }
+        Calculation::Op(Op::Add, terms) => terms.iter().try_fold(Modifier::new(), |sum, term| {
+            Some(sum + typed_modifier(term, ctx)?)
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bonuses::{Bonus, BonusType, Penalty},
+        common::{Feat, Resource, ResourceCommon, ResourceRef},
+        effects::{BonusEffect, EffectCommon},
+        test_helpers::TestStorage,
+        Character,
+    };
+
+    fn roll_calc(roller: &mut Roller, s: &str) -> RollResult {
+        let calc: Calculation = s.parse().unwrap();
+        let character = Character::new("Test Character");
+        let rref = ResourceRef::new("Test Resource", None::<&str>);
+        let storage = TestStorage::new(vec![]);
+        roller.roll_calculation(&calc, CalcContext::new(&character, &rref, &storage))
+    }
+
+    #[test]
+    fn seeded_rolls_repeat() {
+        let roll = DieRoll {
+            count: 10,
+            size: Die::D6,
+        };
+        let first = Roller::seeded(1234).roll(roll);
+        let second = Roller::seeded(1234).roll(roll);
+        assert_eq!(first, second);
+        assert_eq!(first.dice.len(), 10);
+        assert!(first.dice.iter().all(|d| d.die == Die::D6));
+        assert!(first.dice.iter().all(|d| (1..=6).contains(&d.value)));
+    }
+
+    #[test]
+    fn every_face_comes_up() {
+        let mut roller = Roller::seeded(20);
+        let mut seen = [false; 20];
+        for _ in 0..1000 {
+            let d = roller.roll_die(Die::D20);
+            seen[(d.value - 1) as usize] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+    }
+
+    #[test]
+    fn check_keeps_breakdown() {
+        let modifier = Modifier::from((Bonus::item(1) + Bonus::untyped(5), Penalty::status(-1)));
+        let result = Roller::seeded(5).roll_check(&modifier);
+        let natural = result.natural().unwrap();
+        assert!((1..=20).contains(&natural));
+        assert_eq!(result.modifier, 5);
+        assert_eq!(result.total(), natural as i16 + 5);
+        assert_eq!(result.breakdown, modifier);
+    }
+
+    #[test]
+    fn calculation_with_dice() {
+        let mut roller = Roller::seeded(99);
+        let result = roll_calc(&mut roller, "2d8 + 4");
+        assert_eq!(result.dice.len(), 2);
+        assert!(result.dice.iter().all(|d| d.die == Die::D8));
+        assert_eq!(result.modifier, 4);
+        assert_eq!(result.total(), result.dice_total() + 4);
+        assert_eq!(result.natural(), None);
+
+        let result = roll_calc(&mut roller, "(1d6 + 1) * 2");
+        assert_eq!(result.dice.len(), 1);
+        assert_eq!(result.total(), (result.dice_total() + 1) * 2);
+
+        let result = roll_calc(&mut roller, "3 + 4");
+        assert!(result.dice.is_empty());
+        assert_eq!(result.total(), 7);
+
+        let result = roll_calc(&mut roller, "1d4 - 5");
+        assert_eq!(result.modifier, -5);
+        assert_eq!(result.breakdown, Modifier::untyped(-5));
+    }
+
+    #[test]
+    fn calculation_keeps_typed_modifiers() {
+        let mut common = ResourceCommon::new("Inspire Courage");
+        common.add_effect(BonusEffect {
+            common: EffectCommon::default(),
+            bonus_type: BonusType::Status,
+            target: "weapon damage".into(),
+            value: Calculation::from_number(1),
+        });
+        let feat = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut character = Character::new("Test Character");
+        character.resources.insert(feat.make_rref_no_mod());
+        let storage = TestStorage::new(vec![feat]);
+        let rref = ResourceRef::new("Longsword", None::<&str>);
+        let ctx = CalcContext::new(&character, &rref, &storage);
+        let mut roller = Roller::seeded(7);
+
+        let calc: Calculation = "1d8 + weapon damage + 2".parse().unwrap();
+        let result = roller.roll_calculation(&calc, ctx);
+        assert_eq!(result.modifier, 3);
+        assert_eq!(
+            result.breakdown.parts(),
+            vec![(BonusType::Status, 1), (BonusType::Untyped, 2)]
+        );
+
+        // Only sums keep their types.
+        let calc: Calculation = "(1d8 + weapon damage) * 2".parse().unwrap();
+        let result = roller.roll_calculation(&calc, ctx);
+        assert_eq!(
+            result.breakdown,
+            Modifier::untyped(result.total() - result.dice_total())
+        );
+    }
+}
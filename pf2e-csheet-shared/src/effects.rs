@@ -12,10 +12,14 @@ use thiserror::Error;
 use crate::{
     bonuses::{Bonus, BonusType, Modifier, Penalty},
     calc::{CalcContext, Calculation},
+    checks::DegreeOfSuccess,
     choices::Choice,
     common::{ResourceRef, ResourceType},
     cond::Conditions,
-    stats::Proficiency,
+    defenses::{DamageKind, DefenseType},
+    items::{Weapon, WeaponTrait},
+    spells::CastingType,
+    stats::{Ability, Proficiency, Skill},
 };
 
 #[derive(Clone, Debug, Eq, PartialEq, From, Deserialize, Serialize)]
@@ -41,6 +45,27 @@ pub enum Effect {
     IncreaseProficiency(IncreaseProficiencyEffect),
     #[serde(rename = "skill increase")]
     SkillIncrease(SkillIncreaseEffect),
+    #[serde(rename = "degree of success")]
+    DegreeOfSuccess(DegreeOfSuccessEffect),
+    #[serde(rename = "spellcasting")]
+    Spellcasting(SpellcastingEffect),
+    #[serde(rename = "focus spell")]
+    #[serde(alias = "gain spell")]
+    #[serde(deserialize_with = "effect_from_name_or_struct::<ResourceRef, _, _>")]
+    FocusSpell(FocusSpellEffect),
+    #[serde(rename = "resistance")]
+    #[serde(alias = "resist")]
+    #[from(ignore)]
+    Resistance(DefenseEffect),
+    #[serde(rename = "weakness")]
+    #[from(ignore)]
+    Weakness(DefenseEffect),
+    #[serde(rename = "immunity")]
+    #[serde(deserialize_with = "effect_from_name_or_struct::<DamageKind, _, _>")]
+    #[from(ignore)]
+    Immunity(DefenseEffect),
+    #[serde(rename = "weapon trait")]
+    AddWeaponTrait(WeaponTraitEffect),
 }
 
 impl Effect {
@@ -54,6 +79,11 @@ impl Effect {
             Self::GrantResourceChoice(rc) => &rc.common,
             Self::IncreaseProficiency(ip) => &ip.common,
             Self::SkillIncrease(s) => &s.common,
+            Self::DegreeOfSuccess(d) => &d.common,
+            Self::Spellcasting(s) => &s.common,
+            Self::FocusSpell(f) => &f.common,
+            Self::Resistance(d) | Self::Weakness(d) | Self::Immunity(d) => &d.common,
+            Self::AddWeaponTrait(w) => &w.common,
         }
     }
 
@@ -71,6 +101,24 @@ impl Effect {
             Self::GrantResourceChoice(_) => smallvec![],
             Self::IncreaseProficiency(_) => smallvec![],
             Self::SkillIncrease(_) => smallvec![],
+            Self::DegreeOfSuccess(_) => smallvec![],
+            Self::Spellcasting(_) => smallvec![],
+            Self::FocusSpell(_) => smallvec![],
+            Self::Resistance(_) | Self::Weakness(_) | Self::Immunity(_) => smallvec![],
+            Self::AddWeaponTrait(_) => smallvec![],
+        }
+    }
+
+    /// Whether this effect changes the modifier `label`. This doesn't check
+    /// conditions.
+    pub fn affects_modifier(&self, label: &str) -> bool {
+        match self {
+            Self::AddBonus(effect) => label == effect.target.as_str(),
+            Self::AddPenalty(effect) => label == effect.target.as_str(),
+            Self::AddFocusPoolPoint(_) | Self::AddSingleFocusPoolPoint => {
+                label == "Focus Pool Size"
+            }
+            _ => false,
         }
     }
 
@@ -81,13 +129,13 @@ impl Effect {
     ) -> Result<Modifier, GetModifierError> {
         match self {
             Self::AddBonus(effect) if label == effect.target.as_str() => {
-                let value = effect.value.evaluate(ctx);
+                let value = effect.value.evaluate_number(ctx);
                 let bonus = Bonus::from_value_type(value, &effect.bonus_type)?;
                 Ok(bonus.into())
             }
             Self::AddBonus(_) => Ok(Modifier::new()),
             Self::AddFocusPoolPoint(effect) if label == "Focus Pool Size" => {
-                let value = effect.points.evaluate(ctx);
+                let value = effect.points.evaluate_number(ctx);
                 let bonus = Bonus::untyped(value);
                 Ok(bonus.into())
             }
@@ -98,7 +146,9 @@ impl Effect {
             Self::AddSingleFocusPoolPoint => Ok(Modifier::new()),
             Self::AddPenalty(effect) => {
                 if label == effect.target.as_str() {
-                    let value = effect.value.evaluate(ctx);
+                    // Penalties are always negated, whatever sign they're
+                    // written with.
+                    let value = -effect.value.evaluate_number(ctx).abs();
                     let penalty = Penalty::from_value_type(value, &effect.penalty_type)?;
                     Ok(penalty.into())
                 } else {
@@ -109,6 +159,91 @@ impl Effect {
             Self::GrantResourceChoice(_) => Ok(Modifier::new()),
             Self::IncreaseProficiency(_) => Ok(Modifier::new()),
             Self::SkillIncrease(_) => Ok(Modifier::new()),
+            Self::DegreeOfSuccess(_) => Ok(Modifier::new()),
+            Self::Spellcasting(_) => Ok(Modifier::new()),
+            Self::FocusSpell(_) => Ok(Modifier::new()),
+            Self::Resistance(_) | Self::Weakness(_) | Self::Immunity(_) => Ok(Modifier::new()),
+            Self::AddWeaponTrait(_) => Ok(Modifier::new()),
+        }
+    }
+
+    /// What this effect grants proficiency in, and at what rank.
+    pub fn get_proficiency_grant(&self, ctx: CalcContext<'_>) -> Option<(String, Proficiency)> {
+        match self {
+            Self::IncreaseProficiency(effect) => {
+                Some((resolve_choice_label(&effect.target, ctx)?, effect.level))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_proficiency(&self, target: &str, ctx: CalcContext<'_>) -> Option<Proficiency> {
+        match self.get_proficiency_grant(ctx) {
+            Some((t, level)) if t.eq_ignore_ascii_case(target) => Some(level),
+            _ => None,
+        }
+    }
+
+    /// The skill chosen for a skill increase, if one has been chosen.
+    pub fn get_skill_increase(&self, ctx: CalcContext<'_>) -> Option<Skill> {
+        match self {
+            Self::SkillIncrease(effect) => ctx.character.get_choice(ctx.rref, &effect.choice),
+            _ => None,
+        }
+    }
+
+    pub fn get_degree_override(
+        &self,
+        check: &str,
+        ctx: CalcContext<'_>,
+    ) -> Option<(DegreeOfSuccess, DegreeOfSuccess)> {
+        match self {
+            Self::DegreeOfSuccess(effect) => {
+                let on = resolve_choice_label(&effect.check, ctx)?;
+                if on.eq_ignore_ascii_case(check) {
+                    Some((effect.from, effect.to))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_spellcasting(&self) -> Option<&SpellcastingEffect> {
+        match self {
+            Self::Spellcasting(effect) => Some(effect),
+            _ => None,
+        }
+    }
+
+    pub fn get_focus_spell(&self) -> Option<&FocusSpellEffect> {
+        match self {
+            Self::FocusSpell(effect) => Some(effect),
+            _ => None,
+        }
+    }
+
+    /// The resistance, weakness or immunity this effect grants, with its
+    /// value.
+    pub fn get_defense(&self, ctx: CalcContext<'_>) -> Option<(DefenseType, DamageKind, u16)> {
+        let (defense_type, effect) = match self {
+            Self::Resistance(effect) => (DefenseType::Resistance, effect),
+            Self::Weakness(effect) => (DefenseType::Weakness, effect),
+            Self::Immunity(effect) => (DefenseType::Immunity, effect),
+            _ => return None,
+        };
+        let value = effect.value.evaluate_number(ctx).max(0) as u16;
+        Some((defense_type, effect.against.clone(), value))
+    }
+
+    /// The trait this effect adds to `weapon`, which is called `name`.
+    pub fn get_weapon_trait(&self, name: &str, weapon: &Weapon) -> Option<WeaponTrait> {
+        match self {
+            Self::AddWeaponTrait(effect) if effect.applies_to(name, weapon) => {
+                Some(effect.weapon_trait.clone())
+            }
+            _ => None,
         }
     }
 }
@@ -135,6 +270,21 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Replace a leading choice like "$save" in a proficiency or check label
+/// with the value chosen for it, keeping any words after the choice (as in
+/// "$tradition spellcasting").
+fn resolve_choice_label(label: &str, ctx: CalcContext<'_>) -> Option<String> {
+    let rest = match label.strip_prefix('$') {
+        Some(rest) => rest,
+        None => return Some(label.into()),
+    };
+    let (choice, suffix) = rest.split_at(rest.find(' ').unwrap_or_else(|| rest.len()));
+    let value = ctx
+        .character
+        .get_effect_choice::<String, _>(ctx.rref, choice)?;
+    Some(format!("{}{}", value, suffix).into())
+}
+
 fn effect_from_name_or_struct<'de, R, E, D>(deserializer: D) -> Result<E, D::Error>
 where
     D: de::Deserializer<'de>,
@@ -230,6 +380,8 @@ pub struct PenaltyEffect {
         proptest(strategy = "any::<std::string::String>().prop_map_into()")
     )]
     pub target: String,
+    /// The size of the penalty. Its sign is ignored, so `value` and `-1`
+    /// both work.
     pub value: Calculation,
 }
 
@@ -273,6 +425,152 @@ pub struct IncreaseProficiencyEffect {
 pub struct SkillIncreaseEffect {
     #[serde(flatten)]
     pub common: EffectCommon,
+    /// The choice on the granting resource that records which skill is
+    /// increased.
+    #[serde(default = "default_skill_choice")]
+    pub choice: Choice,
+}
+
+fn default_skill_choice() -> Choice {
+    "skill".into()
+}
+
+/// "When you roll a `from` on a `check`, you get a `to` instead."
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct DegreeOfSuccessEffect {
+    #[serde(flatten)]
+    pub common: EffectCommon,
+    #[serde(rename = "on")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "any::<std::string::String>().prop_map_into()")
+    )]
+    pub check: String,
+    #[serde(rename = "when you roll")]
+    pub from: DegreeOfSuccess,
+    #[serde(rename = "you get")]
+    pub to: DegreeOfSuccess,
+}
+
+/// A spellcasting entry, like a wizard's arcane spellcasting.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct SpellcastingEffect {
+    #[serde(flatten)]
+    pub common: EffectCommon,
+    /// The entry's name, which is also how the character's spell list for it
+    /// is found.
+    #[cfg_attr(
+        test,
+        proptest(strategy = "any::<std::string::String>().prop_map_into()")
+    )]
+    pub name: String,
+    /// A tradition, or a choice on the granting resource (like
+    /// "$tradition") holding one.
+    #[cfg_attr(
+        test,
+        proptest(strategy = "any::<std::string::String>().prop_map_into()")
+    )]
+    pub tradition: String,
+    pub ability: Ability,
+    #[serde(rename = "type")]
+    pub casting_type: CastingType,
+    /// The spell slots at each character level starting at 1st, each
+    /// listing the slots for each spell level starting with cantrips. Levels
+    /// past the end of the table use its last entry.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::vec(proptest::collection::vec(0u8..=5, 0..=11).prop_map_into(), 0..=3)"
+        )
+    )]
+    pub slots: Vec<SmallVec<[u8; 11]>>,
+}
+
+/// A focus spell, like a monk's ki spells. These are cast with Focus Points
+/// instead of spell slots.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct FocusSpellEffect {
+    #[serde(flatten)]
+    pub common: EffectCommon,
+    pub spell: ResourceRef,
+    /// A tradition, or a choice on the granting resource holding one. Without
+    /// one, the spell has no spell attack or DC.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::option::of(any::<std::string::String>().prop_map_into())")
+    )]
+    pub tradition: Option<String>,
+    /// Defaults to the character's key ability.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ability: Option<Ability>,
+}
+
+impl From<ResourceRef> for FocusSpellEffect {
+    fn from(spell: ResourceRef) -> Self {
+        Self {
+            common: EffectCommon::default(),
+            spell,
+            tradition: None,
+            ability: None,
+        }
+    }
+}
+
+/// A resistance, weakness or immunity to a damage type or trait. Immunities
+/// don't need a value.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct DefenseEffect {
+    #[serde(flatten)]
+    pub common: EffectCommon,
+    #[serde(rename = "to")]
+    pub against: DamageKind,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub value: Calculation,
+}
+
+impl From<DamageKind> for DefenseEffect {
+    fn from(against: DamageKind) -> Self {
+        Self {
+            common: EffectCommon::default(),
+            against,
+            value: Calculation::default(),
+        }
+    }
+}
+
+/// A trait or material some of the character's weapons gain, like the
+/// monk's unarmed attacks counting as magical.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct WeaponTraitEffect {
+    #[serde(flatten)]
+    pub common: EffectCommon,
+    /// The weapon's name, or the proficiency its attacks use, like "unarmed
+    /// attacks".
+    #[cfg_attr(
+        test,
+        proptest(strategy = "any::<std::string::String>().prop_map_into()")
+    )]
+    pub to: String,
+    #[serde(rename = "trait")]
+    pub weapon_trait: WeaponTrait,
+}
+
+impl WeaponTraitEffect {
+    pub fn applies_to(&self, name: &str, weapon: &Weapon) -> bool {
+        self.to.eq_ignore_ascii_case(name)
+            || self.to.eq_ignore_ascii_case(weapon.category.proficiency())
+    }
 }
 
 #[repr(transparent)]
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+
+use crate::{
+    choices::ChoiceKind,
+    common::{Resource, ResourceRef, ResourceType},
+    stats::Level,
+};
+
+/// Which resources to offer the player, see
+/// [`Character::eligible_resources`].
+///
+/// [`Character::eligible_resources`]: crate::Character::eligible_resources
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ResourceQuery {
+    pub resource_type: ResourceType,
+    #[serde(default, rename = "trait")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trait_filter: Option<String>,
+    /// Resources with a level above this are left out. Resources without a
+    /// level are always kept.
+    pub max_level: Level,
+}
+
+impl ResourceQuery {
+    pub fn new(resource_type: ResourceType, max_level: Level) -> Self {
+        Self {
+            resource_type,
+            trait_filter: None,
+            max_level,
+        }
+    }
+
+    pub fn with_trait(mut self, trait_filter: impl Into<String>) -> Self {
+        self.trait_filter = Some(trait_filter.into());
+        self
+    }
+
+    /// The query for picking a resource for a choice, or `None` if the choice
+    /// isn't for a resource.
+    pub fn for_choice(kind: &ChoiceKind, max_level: Level) -> Option<Self> {
+        match kind {
+            ChoiceKind::Resource {
+                resource_type,
+                trait_filter,
+            } => Some(Self {
+                resource_type: *resource_type,
+                trait_filter: trait_filter.clone(),
+                max_level,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether `resource` has the type, trait and level asked for. This
+    /// doesn't check prerequisites.
+    pub fn matches(&self, resource: &Resource) -> bool {
+        if resource.resource_type() != self.resource_type {
+            return false;
+        }
+        if let Some(wanted) = self.trait_filter.as_ref() {
+            let has_trait = resource
+                .common()
+                .traits
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(wanted));
+            if !has_trait {
+                return false;
+            }
+        }
+        match resource.level() {
+            Some(level) => level <= self.max_level,
+            None => true,
+        }
+    }
+}
+
+/// Whether the character can pick a resource, and if not, why.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Eligibility {
+    pub rref: ResourceRef,
+    /// The prerequisites the character doesn't meet, like "requires expert in
+    /// Athletics".
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unmet: Vec<String>,
+}
+
+impl Eligibility {
+    pub fn is_eligible(&self) -> bool {
+        self.unmet.is_empty()
+    }
+}
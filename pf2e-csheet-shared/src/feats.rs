@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use std::fmt;
+use thiserror::Error;
+
+use crate::{
+    choices::Choice,
+    common::{Feat, ResourceRef},
+    stats::Level,
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
+pub enum FeatCategory {
+    Ancestry,
+    Class,
+    Skill,
+    General,
+}
+
+impl FeatCategory {
+    /// Work out which kind of feat a class advancement entry like "monk feat"
+    /// or "skill feat" grants, or `None` if it isn't a feat.
+    pub fn from_feature_name(name: &str, class_name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        let prefix = name.strip_suffix(" feat")?.trim();
+        match prefix {
+            "ancestry" => Some(Self::Ancestry),
+            "skill" => Some(Self::Skill),
+            "general" => Some(Self::General),
+            "class" => Some(Self::Class),
+            _ if prefix.eq_ignore_ascii_case(class_name) => Some(Self::Class),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FeatCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ancestry => write!(f, "Ancestry"),
+            Self::Class => write!(f, "Class"),
+            Self::Skill => write!(f, "Skill"),
+            Self::General => write!(f, "General"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Error, Deserialize, Serialize)]
+pub enum FeatSlotError {
+    #[error("{0} isn't a feat")]
+    NotAFeat(ResourceRef),
+    #[error("The {slot} needs a feat with the {feat_trait:?} trait, but {feat} doesn't have it")]
+    MissingTrait {
+        slot: String,
+        feat: ResourceRef,
+        feat_trait: String,
+    },
+    #[error("The {slot} can't hold {feat}, which is a level {feat_level} feat")]
+    TooHighLevel {
+        slot: String,
+        feat: ResourceRef,
+        feat_level: Level,
+    },
+    #[error("The feat for {0} is set by the resource that grants it")]
+    Fixed(String),
+}
+
+/// A place for a feat, granted by a character's class, ancestry or
+/// background. The feat picked for it is recorded as a choice on the resource
+/// that grants it, see [`FeatSlot::choice`].
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct FeatSlot {
+    pub source: ResourceRef,
+    pub category: FeatCategory,
+    pub level: Level,
+    /// The trait a feat needs to go in this slot, like "monk" or "dwarf".
+    pub feat_trait: Option<String>,
+    pub chosen: Option<ResourceRef>,
+    /// Whether the feat is set by the source, like the skill feat a
+    /// background gives, rather than chosen.
+    pub fixed: bool,
+    /// Which of the source's slots of this category and level this is, when
+    /// it grants more than one.
+    #[serde(default)]
+    pub index: u8,
+}
+
+impl FeatSlot {
+    pub fn new(
+        source: ResourceRef,
+        category: FeatCategory,
+        level: Level,
+        feat_trait: Option<String>,
+    ) -> Self {
+        Self {
+            source,
+            category,
+            level,
+            feat_trait,
+            chosen: None,
+            fixed: false,
+            index: 0,
+        }
+    }
+
+    /// The choice the picked feat is stored under. The first slot keeps the
+    /// plain name so existing characters still find their feats.
+    pub fn choice(&self) -> Choice {
+        if self.index == 0 {
+            format!("Level {} {} Feat", self.level, self.category)
+        } else {
+            format!(
+                "Level {} {} Feat {}",
+                self.level,
+                self.category,
+                self.index + 1
+            )
+        }
+        .as_str()
+        .into()
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.chosen.is_some()
+    }
+
+    /// Check whether `feat` can go in this slot: it needs the slot's trait, and
+    /// can't be a higher level than the slot.
+    pub fn check(&self, rref: &ResourceRef, feat: &Feat) -> Result<(), FeatSlotError> {
+        if let Some(feat_trait) = self.feat_trait.as_ref() {
+            let has_trait = feat
+                .common
+                .traits
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(feat_trait));
+            if !has_trait {
+                return Err(FeatSlotError::MissingTrait {
+                    slot: format!("{}", self),
+                    feat: rref.clone(),
+                    feat_trait: feat_trait.clone(),
+                });
+            }
+        }
+        if feat.level > self.level {
+            return Err(FeatSlotError::TooHighLevel {
+                slot: format!("{}", self),
+                feat: rref.clone(),
+                feat_level: feat.level,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FeatSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let category = format!("{}", self.category).to_lowercase();
+        write!(
+            f,
+            "level {} {} feat from {}",
+            self.level, category, self.source
+        )
+    }
+}
@@ -0,0 +1,573 @@
+#[cfg(test)]
+use proptest::prelude::*;
+#[cfg(test)]
+use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use uuid::Uuid;
+
+use crate::{
+    common::{Item, Resource, ResourceRef},
+    stats::{BulkTotal, Gold},
+    storage::ResourceStorage,
+};
+
+/// Where an owned item is kept.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum ItemState {
+    Held,
+    Worn,
+    Stowed,
+}
+
+impl Default for ItemState {
+    fn default() -> Self {
+        Self::Stowed
+    }
+}
+
+/// Fundamental and property runes etched onto an owned item. Fundamental
+/// runes here replace the ones on the item itself.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct Runes {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub potency: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub striking: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub resilient: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "SmallVec::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::vec(any::<ResourceRef>(), 0..=2).prop_map_into()"
+        )
+    )]
+    pub property: SmallVec<[ResourceRef; 2]>,
+}
+
+// The prices of the +1, +2 and +3 (or standard, greater and major)
+// fundamental runes, in gp.
+const WEAPON_POTENCY_PRICES: [u32; 3] = [35, 935, 8_935];
+const STRIKING_PRICES: [u32; 3] = [65, 1_065, 31_065];
+const ARMOR_POTENCY_PRICES: [u32; 3] = [160, 1_060, 20_560];
+const RESILIENT_PRICES: [u32; 3] = [340, 3_440, 49_440];
+
+fn rune_price(prices: &[u32; 3], rank: u8) -> Gold {
+    match rank {
+        0 => Gold::zero(),
+        n => Gold::gp(prices[(n.min(3) - 1) as usize]),
+    }
+}
+
+impl Runes {
+    /// What the fundamental runes on `item` are worth. Potency runes cost
+    /// more on armor than on weapons.
+    pub fn fundamental_price(&self, item: &Item) -> Gold {
+        let mut price = Gold::zero();
+        if item.weapon.is_some() {
+            price += rune_price(&WEAPON_POTENCY_PRICES, self.potency);
+            price += rune_price(&STRIKING_PRICES, self.striking);
+        }
+        if item.armor.is_some() {
+            price += rune_price(&ARMOR_POTENCY_PRICES, self.potency);
+            price += rune_price(&RESILIENT_PRICES, self.resilient);
+        }
+        price
+    }
+
+    /// Put the fundamental runes onto `item`'s weapon or armor.
+    pub fn apply(&self, item: &mut Item) {
+        if let Some(weapon) = item.weapon.as_mut() {
+            if self.potency > 0 {
+                weapon.potency = self.potency;
+            }
+            if self.striking > 0 {
+                weapon.striking = self.striking;
+            }
+        }
+        if let Some(armor) = item.armor.as_mut() {
+            if self.potency > 0 {
+                armor.potency = self.potency;
+            }
+            if self.resilient > 0 {
+                armor.resilient = self.resilient;
+            }
+        }
+    }
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+fn is_one(n: &u32) -> bool {
+    *n == 1
+}
+
+/// One stack of an item the character owns.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct InventoryItem {
+    #[cfg_attr(test, proptest(strategy = "any::<u128>().prop_map(Uuid::from_u128)"))]
+    pub id: Uuid,
+    pub item: ResourceRef,
+    #[serde(default = "default_quantity")]
+    #[serde(skip_serializing_if = "is_one")]
+    pub quantity: u32,
+    #[serde(default)]
+    pub state: ItemState,
+    /// The entry this one is stored in, like a backpack.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::option::of(any::<u128>().prop_map(Uuid::from_u128))")
+    )]
+    pub container: Option<Uuid>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub invested: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub runes: Runes,
+}
+
+impl InventoryItem {
+    pub fn new(item: ResourceRef) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            item,
+            quantity: 1,
+            state: ItemState::default(),
+            container: None,
+            invested: false,
+            runes: Runes::default(),
+        }
+    }
+
+    pub fn is_equipped(&self) -> bool {
+        self.state != ItemState::Stowed
+    }
+
+    /// Look up the item, with this entry's runes applied.
+    pub fn resolve(&self, resources: &dyn ResourceStorage) -> Option<Item> {
+        match resources.lookup_immediate(&self.item).as_deref() {
+            Some(Resource::Item(item)) => {
+                let mut item = item.clone();
+                self.runes.apply(&mut item);
+                Some(item)
+            }
+            _ => {
+                debug!("Inventory entry {} isn't a known item", self.item);
+                None
+            }
+        }
+    }
+}
+
+/// An item the character is using right now, with its runes applied.
+/// Items granted as resources (like unarmed attacks) don't have an entry or
+/// a state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EquippedItem {
+    pub rref: ResourceRef,
+    pub entry: Option<Uuid>,
+    pub state: Option<ItemState>,
+    pub item: Item,
+}
+
+impl EquippedItem {
+    /// Armor only protects you while worn.
+    pub fn worn(&self) -> bool {
+        self.state.or_else(|| self.natural_state()) == Some(ItemState::Worn)
+    }
+
+    /// Shields only protect you while held.
+    pub fn held(&self) -> bool {
+        self.state.or_else(|| self.natural_state()) == Some(ItemState::Held)
+    }
+
+    /// Whether the item's effects (and its runes') only apply when it's the
+    /// item being used, like a weapon's when Striking with it. Everything
+    /// else helps whoever is wearing it.
+    pub fn is_scoped(&self) -> bool {
+        self.item.weapon.is_some() || self.held()
+    }
+
+    /// How an item without a state is used, going by its usage or else by
+    /// what kind of item it is.
+    fn natural_state(&self) -> Option<ItemState> {
+        match self.item.usage.as_deref() {
+            Some(usage) if usage.starts_with("worn") => return Some(ItemState::Worn),
+            Some(usage) if usage.starts_with("held") => return Some(ItemState::Held),
+            _ => (),
+        }
+        if self.item.armor.is_some() {
+            Some(ItemState::Worn)
+        } else if self.item.shield.is_some() || self.item.weapon.is_some() {
+            Some(ItemState::Held)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Inventory(Vec<InventoryItem>);
+
+impl Inventory {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &InventoryItem> + '_ {
+        self.0.iter()
+    }
+
+    /// Add `entry` to the inventory, returning its ID.
+    pub fn add(&mut self, entry: InventoryItem) -> Uuid {
+        let id = entry.id;
+        self.0.push(entry);
+        id
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&InventoryItem> {
+        self.0.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: Uuid) -> Option<&mut InventoryItem> {
+        self.0.iter_mut().find(|entry| entry.id == id)
+    }
+
+    /// Remove an entry. Anything stored in it ends up loose in the inventory.
+    pub fn remove(&mut self, id: Uuid) -> Option<InventoryItem> {
+        let index = self.0.iter().position(|entry| entry.id == id)?;
+        for entry in self.0.iter_mut() {
+            if entry.container == Some(id) {
+                entry.container = None;
+            }
+        }
+        Some(self.0.remove(index))
+    }
+
+    /// The entries stored directly in `container`.
+    pub fn contents(&self, container: Uuid) -> impl Iterator<Item = &InventoryItem> + '_ {
+        self.0
+            .iter()
+            .filter(move |entry| entry.container == Some(container))
+    }
+
+    pub fn equipped(&self) -> impl Iterator<Item = &InventoryItem> + '_ {
+        self.0.iter().filter(|entry| entry.is_equipped())
+    }
+
+    /// The Bulk of everything in the inventory.
+    pub fn total_bulk(&self, resources: &dyn ResourceStorage) -> BulkTotal {
+        self.0
+            .iter()
+            .filter(|entry| entry.container.map_or(true, |id| self.get(id).is_none()))
+            .map(|entry| self.entry_bulk(entry, resources))
+            .sum()
+    }
+
+    /// The Bulk of an entry, counting what's stored in it past the Bulk the
+    /// container ignores.
+    pub fn entry_bulk(&self, entry: &InventoryItem, resources: &dyn ResourceStorage) -> BulkTotal {
+        let item = entry.resolve(resources);
+        let contents: BulkTotal = self
+            .contents(entry.id)
+            .map(|inner| self.entry_bulk(inner, resources))
+            .sum();
+        let ignored = item
+            .as_ref()
+            .and_then(|item| item.container.as_ref())
+            .map_or(0, |container| container.ignored_bulk);
+        let own = item.map_or(BulkTotal::default(), |item| item.bulk * entry.quantity);
+        own + contents.saturating_sub(ignored as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bonuses::BonusType,
+        calc::{CalcContext, Calculation},
+        common::ResourceCommon,
+        cond::{Condition, ItemHasTraitCondition, SingleCondition},
+        effects::{BonusEffect, EffectCommon},
+        items::{Armor, ArmorCategory, Container, ItemType, Shield, Weapon, WeaponCategory},
+        stats::{Bulk, BulkTotal, DamageType, Die},
+        test_helpers::{crb_conditions, TestStorage},
+        Character,
+    };
+
+    fn item(name: &str, item_type: ItemType, f: impl FnOnce(&mut Item)) -> Resource {
+        let mut item = Item::new(ResourceCommon::new(name), item_type);
+        f(&mut item);
+        Resource::Item(item)
+    }
+
+    fn bonus(to: &str, value: i16) -> BonusEffect {
+        BonusEffect {
+            common: EffectCommon::default(),
+            bonus_type: BonusType::Item,
+            target: to.into(),
+            value: Calculation::from_number(value),
+        }
+    }
+
+    fn own(character: &mut Character, resource: &Resource, state: ItemState) -> Uuid {
+        let mut entry = InventoryItem::new(resource.make_rref_no_mod());
+        entry.state = state;
+        character.inventory.add(entry)
+    }
+
+    #[test]
+    fn loadout_follows_state() {
+        let breastplate = item("Breastplate", ItemType::Armor, |i| {
+            i.armor = Some(Armor {
+                ac_bonus: 4,
+                ..Armor::new(ArmorCategory::MediumArmor)
+            });
+        });
+        let shield = item("Steel Shield", ItemType::Shield, |i| {
+            i.shield = Some(Shield {
+                ac_bonus: 2,
+                ..Default::default()
+            });
+        });
+        let storage = TestStorage::new(vec![breastplate.clone(), shield.clone()]);
+        let mut character = Character::new("Test Character");
+        character.shield_raised = true;
+        let armor_id = own(&mut character, &breastplate, ItemState::Stowed);
+        let shield_id = own(&mut character, &shield, ItemState::Worn);
+        assert_eq!(character.armor_category(&storage), ArmorCategory::Unarmored);
+        assert_eq!(character.armor_class(&storage).total(), 10);
+
+        character.inventory.get_mut(armor_id).unwrap().state = ItemState::Worn;
+        assert_eq!(
+            character.armor_category(&storage),
+            ArmorCategory::MediumArmor
+        );
+        assert_eq!(character.armor_class(&storage).total(), 14);
+
+        character.inventory.get_mut(shield_id).unwrap().state = ItemState::Held;
+        assert_eq!(character.armor_class(&storage).total(), 16);
+
+        let cond: Condition = SingleCondition::ItemHasTrait(ItemHasTraitCondition::new(
+            ItemType::Shield,
+            Vec::<&str>::new(),
+        ))
+        .into();
+        let mut common = ResourceCommon::new("Shield Block");
+        common.add_requirement(cond);
+        let rref = ResourceRef::new("Shield Block", None::<&str>);
+        let ctx = CalcContext::new(&character, &rref, &storage);
+        assert!(!common.requirements.reject(ctx));
+        character.inventory.remove(shield_id);
+        let ctx = CalcContext::new(&character, &rref, &storage);
+        assert!(common.requirements.reject(ctx));
+    }
+
+    #[test]
+    fn runes_and_investment() {
+        let sword = item("Longsword", ItemType::Weapon, |i| {
+            i.weapon = Some(Weapon::new(WeaponCategory::Martial, Die::D8, DamageType::S));
+        });
+        let armor = item("Leather Armor", ItemType::Armor, |i| {
+            i.armor = Some(Armor::new(ArmorCategory::LightArmor));
+        });
+        let cloak = item("Cloak of Elvenkind", ItemType::Other, |i| {
+            i.common.add_traits(&["Invested"]);
+            i.common.add_effect(bonus("Stealth", 1));
+        });
+        let mut flaming = ResourceCommon::new("Flaming");
+        flaming.add_effect(bonus("weapon damage", 1));
+        let flaming = item("Flaming", ItemType::Other, |i| i.common = flaming);
+        let storage = TestStorage::new(vec![
+            sword.clone(),
+            armor.clone(),
+            cloak.clone(),
+            flaming.clone(),
+        ]);
+
+        let mut character = Character::new("Test Character");
+        let sword_id = own(&mut character, &sword, ItemState::Held);
+        let armor_id = own(&mut character, &armor, ItemState::Worn);
+        let cloak_id = own(&mut character, &cloak, ItemState::Worn);
+        {
+            let runes = &mut character.inventory.get_mut(sword_id).unwrap().runes;
+            runes.potency = 1;
+            runes.striking = 1;
+            runes.property.push(flaming.make_rref_no_mod());
+        }
+        character
+            .inventory
+            .get_mut(armor_id)
+            .unwrap()
+            .runes
+            .resilient = 1;
+
+        let strike = &character.strikes(&storage)[0];
+        assert_eq!(strike.damage_dice.count, 2);
+        assert_eq!(strike.attack.item_part().total(), 1);
+        assert_eq!(strike.damage_bonus(), 1);
+        assert_eq!(character.get_modifier("REF", None, &storage).total(), 1);
+
+        assert_eq!(character.get_modifier("Stealth", None, &storage).total(), 0);
+        character.inventory.get_mut(cloak_id).unwrap().invested = true;
+        assert_eq!(character.get_modifier("Stealth", None, &storage).total(), 1);
+        character.inventory.get_mut(cloak_id).unwrap().state = ItemState::Stowed;
+        assert_eq!(character.get_modifier("Stealth", None, &storage).total(), 0);
+    }
+
+    #[test]
+    fn runes_stay_on_their_weapon() {
+        let sword = item("Longsword", ItemType::Weapon, |i| {
+            i.weapon = Some(Weapon::new(WeaponCategory::Martial, Die::D8, DamageType::S));
+        });
+        let dagger = item("Dagger", ItemType::Weapon, |i| {
+            i.weapon = Some(Weapon::new(WeaponCategory::Simple, Die::D4, DamageType::P));
+        });
+        let fist = item("Fist", ItemType::Weapon, |i| {
+            i.weapon = Some(Weapon::new(WeaponCategory::Unarmed, Die::D4, DamageType::B));
+        });
+        let mut flaming = ResourceCommon::new("Flaming");
+        flaming.add_effect(bonus("weapon damage", 1));
+        let flaming = item("Flaming", ItemType::Other, |i| i.common = flaming);
+        let storage = TestStorage::new(vec![
+            sword.clone(),
+            dagger.clone(),
+            fist.clone(),
+            flaming.clone(),
+        ]);
+
+        let mut character = Character::new("Test Character");
+        character.resources.insert(fist.make_rref_no_mod());
+        let sword_id = own(&mut character, &sword, ItemState::Held);
+        own(&mut character, &dagger, ItemState::Held);
+        let runes = &mut character.inventory.get_mut(sword_id).unwrap().runes;
+        runes.property.push(flaming.make_rref_no_mod());
+
+        let strikes = character.strikes(&storage);
+        let damage: Vec<(&str, i16)> = strikes
+            .iter()
+            .map(|strike| (strike.name.as_str(), strike.damage_bonus()))
+            .collect();
+        assert_eq!(damage, vec![("Dagger", 0), ("Fist", 0), ("Longsword", 1)]);
+        assert_eq!(
+            character
+                .get_modifier("weapon damage", None, &storage)
+                .total(),
+            0
+        );
+
+        // Granted items don't have a state, so they're used the usual way.
+        let loadout = character.loadout(&storage);
+        let fist = loadout
+            .iter()
+            .find(|e| e.item.common.name == "Fist")
+            .unwrap();
+        assert!(fist.held() && !fist.worn());
+        let mut rope = fist.clone();
+        rope.item.weapon = None;
+        assert!(!rope.held() && !rope.worn());
+        rope.item.usage = Some("worn".into());
+        assert!(rope.worn() && !rope.held());
+    }
+
+    #[test]
+    fn bulk_and_encumbrance() {
+        let backpack = item("Backpack", ItemType::Other, |i| {
+            i.bulk = Bulk::Light;
+            i.container = Some(Container {
+                capacity: 4,
+                ignored_bulk: 2,
+            });
+        });
+        let rope = item("Rope", ItemType::Other, |i| i.bulk = Bulk::Light);
+        let hammer = item("Warhammer", ItemType::Weapon, |i| i.bulk = Bulk::Heavy(1));
+        let anvil = item("Anvil", ItemType::Other, |i| i.bulk = Bulk::Heavy(5));
+        let storage = TestStorage::new(crb_conditions().into_iter().chain(vec![
+            backpack.clone(),
+            rope.clone(),
+            hammer.clone(),
+            anvil.clone(),
+        ]));
+
+        let mut character = Character::new("Test Character");
+        let pack_id = own(&mut character, &backpack, ItemState::Worn);
+        let rope_id = own(&mut character, &rope, ItemState::Stowed);
+        character.inventory.get_mut(rope_id).unwrap().quantity = 13;
+        let hammer_id = own(&mut character, &hammer, ItemState::Stowed);
+        let inventory = &character.inventory;
+        assert_eq!(inventory.total_bulk(&storage), BulkTotal::new(2, 4));
+
+        for id in [rope_id, hammer_id].iter() {
+            character.inventory.get_mut(*id).unwrap().container = Some(pack_id);
+        }
+        let inventory = &character.inventory;
+        let pack = inventory.get(pack_id).unwrap();
+        assert_eq!(inventory.entry_bulk(pack, &storage), BulkTotal::new(0, 4));
+        assert_eq!(inventory.total_bulk(&storage), BulkTotal::new(0, 4));
+
+        let limits = character.bulk_limits(&storage);
+        assert_eq!(limits.encumbered, BulkTotal::new(5, 0));
+        assert!(!limits.is_encumbered());
+        assert_eq!(character.get_modifier("Speed", None, &storage).total(), 0);
+
+        own(&mut character, &anvil, ItemState::Held);
+        assert_eq!(
+            character.bulk_limits(&storage).carried,
+            BulkTotal::new(5, 4)
+        );
+        assert!(!character.bulk_limits(&storage).is_encumbered());
+        let anvil_id = own(&mut character, &anvil, ItemState::Held);
+        assert!(character.bulk_limits(&storage).is_encumbered());
+        assert_eq!(character.get_modifier("Speed", None, &storage).total(), -10);
+        assert_eq!(character.get_modifier("REF", None, &storage).total(), -1);
+        assert_eq!(
+            character.get_modifier("Stealth", None, &storage).total(),
+            -1
+        );
+        assert_eq!(character.get_modifier("FORT", None, &storage).total(), 0);
+        assert_eq!(character.armor_class(&storage).total(), 9);
+        assert!(character
+            .active_conditions(&storage)
+            .iter()
+            .any(|c| c.name == "Clumsy"));
+
+        // Being encumbered doesn't add to a worse clumsy condition.
+        let clumsy = ResourceRef::new("Clumsy", None::<&str>);
+        character.apply_condition(&clumsy, 2, &storage).unwrap();
+        assert_eq!(character.get_modifier("REF", None, &storage).total(), -2);
+        character.play.remove_condition("Clumsy");
+
+        character.inventory.remove(anvil_id);
+        assert_eq!(character.get_modifier("REF", None, &storage).total(), 0);
+    }
+
+    #[test]
+    fn owned_item_choice() {
+        let sword = item("Longsword", ItemType::Weapon, |_| ());
+        let mut character = Character::new("Test Character");
+        let id = own(&mut character, &sword, ItemState::Held);
+        let feat = ResourceRef::new("Weapon Bond", None::<&str>);
+        character.set_choice(&feat, "Item".into(), &id).unwrap();
+        let entry = character.get_owned_item(&feat, "Item").unwrap();
+        assert_eq!(entry.item, sword.make_rref_no_mod());
+
+        character.inventory.remove(id);
+        assert!(character.get_owned_item(&feat, "Item").is_none());
+    }
+}
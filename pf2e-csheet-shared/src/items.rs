@@ -1,6 +1,14 @@
 #[cfg(test)]
+use proptest::prelude::*;
+#[cfg(test)]
 use proptest_derive::Arbitrary;
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use smartstring::alias::String;
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+use crate::stats::{DamageType, Die, DieRoll, Range};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -19,6 +27,22 @@ impl Default for ItemType {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Unique,
+}
+
+impl Default for Rarity {
+    fn default() -> Self {
+        Self::Common
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 pub enum ArmorCategory {
@@ -31,3 +55,284 @@ pub enum ArmorCategory {
     #[serde(rename = "heavy armor")]
     HeavyArmor,
 }
+
+/// The armor-specific parts of an item.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct Armor {
+    pub category: ArmorCategory,
+    #[serde(default, rename = "ac bonus")]
+    pub ac_bonus: u16,
+    /// The most of your DEX modifier that still applies to AC.
+    #[serde(default, rename = "dex cap")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dex_cap: Option<u16>,
+    #[serde(default, rename = "check penalty")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub check_penalty: u16,
+    #[serde(default, rename = "speed penalty")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub speed_penalty: u16,
+    /// The STR score that removes the check penalty and lessens the speed
+    /// penalty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strength: Option<u16>,
+    /// The armor potency rune, which adds to the item bonus.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub potency: u8,
+    /// The resilient rune, which adds an item bonus to saving throws.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub resilient: u8,
+}
+
+impl Armor {
+    pub fn new(category: ArmorCategory) -> Self {
+        Self {
+            category,
+            ac_bonus: 0,
+            dex_cap: None,
+            check_penalty: 0,
+            speed_penalty: 0,
+            strength: None,
+            potency: 0,
+            resilient: 0,
+        }
+    }
+
+    pub fn item_bonus(&self) -> u16 {
+        self.ac_bonus + self.potency as u16
+    }
+
+    /// Whether `str_score` is high enough to ignore the check penalty.
+    pub fn strong_enough(&self, str_score: i16) -> bool {
+        match self.strength {
+            Some(s) => str_score >= s as i16,
+            None => false,
+        }
+    }
+}
+
+/// The shield-specific parts of an item.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct Shield {
+    /// The circumstance bonus to AC while the shield is raised.
+    #[serde(default, rename = "ac bonus")]
+    pub ac_bonus: u16,
+    #[serde(default)]
+    pub hardness: u16,
+    #[serde(default)]
+    pub hp: u16,
+    #[serde(default, rename = "broken threshold")]
+    pub broken_threshold: u16,
+    #[serde(default, rename = "speed penalty")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub speed_penalty: u16,
+}
+
+/// The container-specific parts of an item, like a backpack.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct Container {
+    /// How much Bulk fits inside.
+    #[serde(default)]
+    pub capacity: u16,
+    /// How much of the Bulk inside doesn't count against the carrier's
+    /// limits. Backpacks ignore the first 2 Bulk.
+    #[serde(default, rename = "ignored bulk")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub ignored_bulk: u16,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum WeaponCategory {
+    Unarmed,
+    Simple,
+    Martial,
+    Advanced,
+}
+
+impl WeaponCategory {
+    /// The proficiency used to attack with weapons in this category.
+    pub fn proficiency(self) -> &'static str {
+        match self {
+            Self::Unarmed => "unarmed attacks",
+            Self::Simple => "simple weapons",
+            Self::Martial => "martial weapons",
+            Self::Advanced => "advanced weapons",
+        }
+    }
+}
+
+/// Weapon traits that change how a Strike is calculated. Any other trait is
+/// kept as-is.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+#[serde(try_from = "smartstring::alias::String")]
+pub enum WeaponTrait {
+    Agile,
+    Finesse,
+    Propulsive,
+    Forceful,
+    Deadly(Die),
+    Fatal(Die),
+    /// Melee weapons list the range they can be thrown, ranged weapons use
+    /// their own range increment.
+    Thrown(Option<u16>),
+    Volley(u16),
+    Other(
+        #[cfg_attr(
+            test,
+            proptest(strategy = "\"(disarm|nonlethal|parry|reach|trip)\".prop_map_into()")
+        )]
+        String,
+    ),
+}
+
+try_from_str!(WeaponTrait);
+serialize_display!(WeaponTrait);
+
+impl fmt::Display for WeaponTrait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Agile => write!(f, "agile"),
+            Self::Finesse => write!(f, "finesse"),
+            Self::Propulsive => write!(f, "propulsive"),
+            Self::Forceful => write!(f, "forceful"),
+            Self::Deadly(die) => write!(f, "deadly {}", die),
+            Self::Fatal(die) => write!(f, "fatal {}", die),
+            Self::Thrown(None) => write!(f, "thrown"),
+            Self::Thrown(Some(range)) => write!(f, "thrown {}", Range(*range)),
+            Self::Volley(range) => write!(f, "volley {}", Range(*range)),
+            Self::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Clone, Error, Debug, Deserialize, Serialize)]
+pub enum WeaponTraitFromStrError {
+    #[error("Failed to parse weapon trait")]
+    Invalid,
+}
+
+impl FromStr for WeaponTrait {
+    type Err = WeaponTraitFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::parsers::weapon_trait(s).map_err(|_| WeaponTraitFromStrError::Invalid)
+    }
+}
+
+#[test]
+fn test_parse_weapon_trait() {
+    assert_eq!("agile".parse::<WeaponTrait>().unwrap(), WeaponTrait::Agile);
+    assert_eq!(
+        "Deadly d10".parse::<WeaponTrait>().unwrap(),
+        WeaponTrait::Deadly(Die::D10)
+    );
+    assert_eq!(
+        "thrown 20 ft.".parse::<WeaponTrait>().unwrap(),
+        WeaponTrait::Thrown(Some(20))
+    );
+    assert_eq!(
+        "thrown".parse::<WeaponTrait>().unwrap(),
+        WeaponTrait::Thrown(None)
+    );
+    assert_eq!(
+        "volley 30".parse::<WeaponTrait>().unwrap(),
+        WeaponTrait::Volley(30)
+    );
+    assert_eq!(
+        "versatile S".parse::<WeaponTrait>().unwrap(),
+        WeaponTrait::Other("versatile S".into())
+    );
+    assert_eq!(
+        "agile-ish".parse::<WeaponTrait>().unwrap(),
+        WeaponTrait::Other("agile-ish".into())
+    );
+}
+
+/// The weapon-specific parts of an item.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct Weapon {
+    pub category: WeaponCategory,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::option::of(\"[a-z]{1,10}\".prop_map_into())")
+    )]
+    pub group: Option<String>,
+    #[serde(rename = "damage die")]
+    pub damage_die: Die,
+    #[serde(rename = "damage type")]
+    pub damage_type: DamageType,
+    /// The range increment of a ranged weapon. Melee weapons that can be
+    /// thrown give their range with the thrown trait instead.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<u16>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "SmallVec::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::collection::vec(any::<WeaponTrait>(), 0..=4).prop_map_into()"
+        )
+    )]
+    pub traits: SmallVec<[WeaponTrait; 4]>,
+    /// The weapon potency rune, which adds an item bonus to attack rolls.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub potency: u8,
+    /// The striking rune: 1 for striking, 2 for greater striking and 3 for
+    /// major striking.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub striking: u8,
+}
+
+impl Weapon {
+    pub fn new(category: WeaponCategory, damage_die: Die, damage_type: DamageType) -> Self {
+        Self {
+            category,
+            group: None,
+            damage_die,
+            damage_type,
+            range: None,
+            traits: SmallVec::new(),
+            potency: 0,
+            striking: 0,
+        }
+    }
+
+    pub fn has_trait(&self, wanted: &WeaponTrait) -> bool {
+        self.traits.contains(wanted)
+    }
+
+    pub fn is_ranged(&self) -> bool {
+        self.range.is_some()
+    }
+
+    /// The range this weapon can be thrown, if it has the thrown trait.
+    pub fn thrown_range(&self) -> Option<u16> {
+        self.traits.iter().find_map(|t| match t {
+            WeaponTrait::Thrown(range) => Some(range.or(self.range).unwrap_or(0)),
+            _ => None,
+        })
+    }
+
+    /// One damage die, plus one more for each step of striking rune.
+    pub fn damage_dice(&self) -> DieRoll {
+        DieRoll {
+            count: 1 + self.striking as u16,
+            size: self.damage_die,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(test)]
+use proptest::prelude::*;
+#[cfg(test)]
+use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    abilities::BoostSource,
+    choices::{Choice, ChoiceKind, ChoiceMeta},
+    common::ResourceRef,
+    feats::FeatCategory,
+    stats::Level,
+};
+
+/// How much XP it takes to gain a level.
+pub const XP_PER_LEVEL: u32 = 1000;
+
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum LevelError {
+    #[error("The character doesn't have a class")]
+    NoClass,
+    #[error("Characters can't go above 20th level")]
+    MaxLevel,
+    #[error("Characters can't go below 1st level")]
+    MinLevel,
+    #[error(
+        "Leveling up takes {} XP, but the character only has {0}",
+        XP_PER_LEVEL
+    )]
+    NotEnoughXp(u32),
+}
+
+/// A choice recorded on a resource.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct ChoiceSlot {
+    pub resource: ResourceRef,
+    pub choice: Choice,
+}
+
+impl ChoiceSlot {
+    pub fn new(resource: ResourceRef, choice: Choice) -> Self {
+        Self { resource, choice }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LevelChoiceKind {
+    /// A choice a resource declares, like the feat for a class feat.
+    Declared(ChoiceMeta),
+    SkillIncrease,
+    AbilityBoosts(BoostSource),
+    Feat(FeatCategory),
+}
+
+impl LevelChoiceKind {
+    /// Whether the choice picks a resource the character gains, like a feat.
+    pub fn picks_resource(&self) -> bool {
+        match self {
+            Self::Declared(meta) => matches!(meta.kind, ChoiceKind::Resource { .. }),
+            Self::Feat(_) => true,
+            Self::SkillIncrease | Self::AbilityBoosts(_) => false,
+        }
+    }
+}
+
+/// A choice that a new level asks the player to make.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LevelChoice {
+    pub slot: ChoiceSlot,
+    pub kind: LevelChoiceKind,
+}
+
+/// What a character gained from leveling up.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LevelUp {
+    pub level: Level,
+    pub granted: Vec<ResourceRef>,
+    pub choices: Vec<LevelChoice>,
+}
+
+/// What the character gained at a level, so that leveling down can take it
+/// away again.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct LevelRecord {
+    pub level: Level,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::collection::vec(any::<ResourceRef>(), 0..=5)")
+    )]
+    pub granted: Vec<ResourceRef>,
+    /// The choices introduced at this level.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::collection::vec(any::<ChoiceSlot>(), 0..=5)")
+    )]
+    pub choices: Vec<ChoiceSlot>,
+    /// The choices that picked a resource, which goes away with the level.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "proptest::collection::vec(any::<ChoiceSlot>(), 0..=5)")
+    )]
+    pub resource_choices: Vec<ChoiceSlot>,
+}
+
+impl LevelRecord {
+    pub fn new(up: &LevelUp) -> Self {
+        Self {
+            level: up.level,
+            granted: up.granted.clone(),
+            choices: up.choices.iter().map(|c| c.slot.clone()).collect(),
+            resource_choices: up
+                .choices
+                .iter()
+                .filter(|c| c.kind.picks_resource())
+                .map(|c| c.slot.clone())
+                .collect(),
+        }
+    }
+}
@@ -8,18 +8,34 @@ extern crate pretty_assertions;
 #[macro_use]
 mod macros;
 
+pub mod abilities;
 pub mod bonuses;
+pub mod breakdown;
 pub mod calc;
 mod character;
+pub mod checks;
 pub mod choices;
 mod common;
 pub mod cond;
+pub mod defenses;
+pub mod dice;
 pub mod effects;
+pub mod eligibility;
+pub mod feats;
+pub mod inventory;
 pub mod items;
+pub mod levels;
 pub mod messages;
 pub mod parsers;
+pub mod play;
+pub mod spells;
 pub mod stats;
 pub mod storage;
+pub mod strikes;
+pub mod validation;
+pub mod wealth;
+#[cfg(test)]
+mod test_helpers;
 
 pub use character::*;
 pub use common::*;
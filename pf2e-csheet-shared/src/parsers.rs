@@ -1,7 +1,7 @@
 use smallvec::{smallvec, SmallVec};
 use std::convert::{TryFrom as _, TryInto as _};
 
-use crate::{bonuses::*, calc::*, common::*, stats::*};
+use crate::{bonuses::*, calc::*, common::*, items::WeaponTrait, stats::*};
 
 peg::parser! {
     grammar parsers() for str {
@@ -71,18 +71,77 @@ peg::parser! {
             = p:penalty() { p.into() }
             / b:bonus() { b.into() }
 
+        rule calculation_name_char() = ['a'..='z' | 'A'..='Z' | '_']
+
+        rule calculation_function() -> Function
+            = "min" { Function::Min }
+            / "max" { Function::Max }
+            / "floor" { Function::Floor }
+            / "ceil" { Function::Ceil }
+
+        rule calculation_comparison() -> Comparison
+            = "<=" { Comparison::LessOrEqual }
+            / ">=" { Comparison::GreaterOrEqual }
+            / "==" { Comparison::Equal }
+            / "!=" { Comparison::NotEqual }
+            / "<" { Comparison::Less }
+            / ">" { Comparison::Greater }
+            / "=" { Comparison::Equal }
+
+        rule calculation_step() -> (Level, Calculation)
+            = ws()* level:unsigned() ws()* ":" step:calculation()
+              {? u8::try_from(level).map(|l| (Level::from(l), step)).map_err(|_| "Level is too large") }
+
         rule calculation_terminal() -> Calculation
-            = "$" var:$(['a'..='z' | 'A'..='Z'] ['a'..='z' | 'A'..='Z' | '_']+) { Calculation::Choice(var.into()).normalized() }
-            / name:$(['a'..='z' | 'A'..='Z'] ['a'..='z' | 'A'..='Z' | '_']+) { Calculation::Named(name.into()).normalized() }
+            = "half" ws() "level" !calculation_name_char() { Calculation::Builtin(BuiltinValue::HalfLevel) }
+            / "level" !calculation_name_char() { Calculation::Builtin(BuiltinValue::Level) }
+            / "condition" ws() "value" !calculation_name_char() { Calculation::Builtin(BuiltinValue::ConditionValue) }
+            / f:calculation_function() ws()* "(" args:(calculation() ** ",") ")"
+              {? match (f, args.len()) {
+                     (_, 0) => Err("Functions need at least one argument"),
+                     (Function::Floor, 1) | (Function::Ceil, 1) => Ok(Calculation::Function(f, args).normalized()),
+                     (Function::Floor, _) | (Function::Ceil, _) => Err("floor and ceil take exactly one argument"),
+                     _ => Ok(Calculation::Function(f, args).normalized()),
+                 }
+              }
+            / "if" ws()* "(" left:calculation() cmp:calculation_comparison() right:calculation()
+              "," then:calculation() "," otherwise:calculation() ")"
+              { Calculation::If(Box::new(Conditional { left, cmp, right, then, otherwise })).normalized() }
+            / "table" ws()* "(" steps:(calculation_step() ++ ",") ")"
+              {? calculation_table(steps) }
+            / "$" var:$(['a'..='z' | 'A'..='Z'] ['a'..='z' | 'A'..='Z' | '_']+) { Calculation::Choice(var.into()).normalized() }
+            / name:$(['a'..='z' | 'A'..='Z'] calculation_name_char()+ (" " calculation_name_char()+)*)
+              { Calculation::Named(name.into()).normalized() }
+            / d:die_roll() { Calculation::Dice(d) }
             / m:modifier() { Calculation::Modifier(m).normalized() }
 
-        pub rule calculation() -> Calculation = precedence! {
-            x:(@) "+" y:@ { Calculation::Op(Op::Add, vec![x, y]).normalized() }
-            --
-            ws()* term:calculation_terminal() ws()* { term.normalized() }
-            ws()* "(" ws()* inner:calculation() ws()* ")" ws()* { inner.normalized() }
-        }
-
+        rule calculation_operand() -> Calculation
+            = ws()* term:calculation_terminal() ws()* { term }
+            / ws()* "(" inner:calculation() ")" ws()* { inner }
+
+        rule calculation_op() -> Op
+            = "+" { Op::Add }
+            / "-" { Op::Subtract }
+            / "*" { Op::Multiply }
+            / "/" { Op::Divide }
+
+        // Different operators can't be mixed without parentheses, so there is
+        // no precedence to worry about.
+        pub rule calculation() -> Calculation
+            = first:calculation_operand() rest:(op:calculation_op() t:calculation_operand() { (op, t) })*
+              {? calculation_chain(first, rest) }
+
+        pub rule die_roll() -> DieRoll
+            = count:unsigned() size:die()
+              {? count.try_into().map(|count| DieRoll { count, size }).map_err(|_| "Too many dice") }
+
+        rule die() -> Die
+            = "d4" { Die::D4 }
+            / "d6" { Die::D6 }
+            / "d8" { Die::D8 }
+            / "d10" { Die::D10 }
+            / "d12" { Die::D12 }
+            / "d20" { Die::D20 }
 
         rule currency_cp() -> Gold
             = c:unsigned() ws()? "cp" {? c.try_into().map(Gold::cp).map_err(|_| "Copper value is out of range") }
@@ -96,26 +155,17 @@ peg::parser! {
         rule currency_pp() -> Gold
             = p:unsigned() ws()? "pp" {? p.try_into().map(Gold::pp).map_err(|_| "Platinum value is out of range") }
 
+        // Each denomination is optional, but they have to come in order.
         pub rule currency() -> Gold
-            = ( p:currency_pp()
-                gsc:(gsc_opt:( ws() g:currency_gp()
-                      sc:(sc_opt:( ws() s:currency_sp()
-                           c:(c_opt:( ws() c:currency_cp() { c })? { c_opt.unwrap_or(Gold::zero()) })
-                           { s + c }
-                      )? { sc_opt.unwrap_or(Gold::zero()) })
-                      { g + sc }
-                )? { gsc_opt.unwrap_or(Gold::zero()) })
-                { p + gsc })
-            / ( g:currency_gp()
-                sc:(sc_opt:( ws() s:currency_sp()
-                             c:(c_opt:( ws() c:currency_cp() { c })? { c_opt.unwrap_or(Gold::zero()) })
-                             { s + c }
-                )? { sc_opt.unwrap_or(Gold::zero()) })
-                { g + sc })
-            / ( s:currency_sp()
-                c:(c_opt:( ws() c:currency_cp() { c })? { c_opt.unwrap_or(Gold::zero()) })
-                { s + c })
-            / c:currency_cp() { c }
+            = p:currency_pp()? ws()? g:currency_gp()? ws()? s:currency_sp()? ws()? c:currency_cp()?
+              {?
+               let parts = [p, g, s, c];
+               if parts.iter().all(Option::is_none) {
+                   Err("Expected a currency value")
+               } else {
+                   Ok(parts.iter().flatten().fold(Gold::zero(), |total, part| total + *part))
+               }
+              }
 
         pub rule damage_type() -> DamageType
             = ( "B" / "bludgeoning" ) { DamageType::B }
@@ -173,11 +223,28 @@ peg::parser! {
             / "background" { ResourceType::Background }
             / ("class feature" / "class-feature") { ResourceType::ClassFeature }
             / "class" { ResourceType::Class }
+            / "condition" { ResourceType::Condition }
             / "feat" { ResourceType::Feat }
             / "heritage" { ResourceType::Heritage }
             / "item" { ResourceType::Item }
             / "spell" { ResourceType::Spell }
 
+        rule feet() -> u16
+            = n:unsigned() ( ws()? "ft" "."? )? {? n.try_into().map_err(|_| "Too large of a range") }
+
+        rule end() = ![_]
+
+        pub rule weapon_trait() -> WeaponTrait
+            = ( "agile" / "Agile" ) end() { WeaponTrait::Agile }
+            / ( "finesse" / "Finesse" ) end() { WeaponTrait::Finesse }
+            / ( "propulsive" / "Propulsive" ) end() { WeaponTrait::Propulsive }
+            / ( "forceful" / "Forceful" ) end() { WeaponTrait::Forceful }
+            / ( "deadly" / "Deadly" ) ws() d:die() end() { WeaponTrait::Deadly(d) }
+            / ( "fatal" / "Fatal" ) ws() d:die() end() { WeaponTrait::Fatal(d) }
+            / ( "thrown" / "Thrown" ) r:( ws() r:feet() { r } )? end() { WeaponTrait::Thrown(r) }
+            / ( "volley" / "Volley" ) ws() r:feet() end() { WeaponTrait::Volley(r) }
+            / name:$( [_]+ ) { WeaponTrait::Other(name.trim().into()) }
+
         pub rule skill() -> Skill
             = ( "acrobatics" / "Acrobatics" ) { Skill::Acrobatics }
             / ( "arcana" / "Arcana" ) { Skill::Arcana }
@@ -200,3 +267,32 @@ peg::parser! {
 }
 
 pub use parsers::*;
+
+fn calculation_chain(
+    first: Calculation,
+    rest: Vec<(Op, Calculation)>,
+) -> Result<Calculation, &'static str> {
+    let op = match rest.first() {
+        None => return Ok(first),
+        Some((op, _)) => *op,
+    };
+    let mut terms = Vec::with_capacity(rest.len() + 1);
+    terms.push(first);
+    for (next_op, term) in rest {
+        if next_op != op {
+            return Err("Mixing operators requires explicit parentheses");
+        }
+        terms.push(term);
+    }
+    Ok(Calculation::Op(op, terms).normalized())
+}
+
+fn calculation_table(steps: Vec<(Level, Calculation)>) -> Result<Calculation, &'static str> {
+    let mut table = std::collections::BTreeMap::new();
+    for (level, step) in steps {
+        if table.insert(level, step).is_some() {
+            return Err("Levels in a table must be unique");
+        }
+    }
+    Ok(Calculation::Table(table).normalized())
+}
@@ -0,0 +1,312 @@
+#[cfg(test)]
+use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{checks::DegreeOfSuccess, common::ResourceRef};
+
+/// What happened to a character after they took damage.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DamageOutcome {
+    /// Temporary HP soaked all of it.
+    Absorbed,
+    Damaged,
+    /// They dropped to 0 HP, or took damage while already there, and are now
+    /// dying with this value.
+    Dying(u8),
+    Dead,
+}
+
+#[derive(Clone, Error, Debug, Eq, PartialEq)]
+pub enum PlayError {
+    #[error("There are no Hero Points left to spend")]
+    NoHeroPoints,
+    #[error("The character isn't dying")]
+    NotDying,
+    #[error("{0} isn't a condition")]
+    NotACondition(ResourceRef),
+}
+
+/// A condition applied to a character, along with its value if it has one.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct ActiveCondition {
+    pub condition: ResourceRef,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub value: u8,
+}
+
+/// The parts of a character that change as they're played, rather than
+/// built. This is saved along with the character.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct PlayState {
+    /// Damage taken, so that current HP follows changes to max HP.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub damage: u16,
+    #[serde(default, rename = "temporary hp")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub temp_hp: u16,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub dying: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub wounded: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub doomed: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub dead: bool,
+    #[serde(default, rename = "hero points")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub hero_points: u8,
+    /// How many Focus Points have been spent since the pool was last full.
+    #[serde(default, rename = "focus spent")]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub focus_spent: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<ActiveCondition>,
+}
+
+impl PlayState {
+    /// Nobody can have more than this many Hero Points.
+    pub const MAX_HERO_POINTS: u8 = 3;
+
+    pub fn current_hp(&self, max_hp: u16) -> u16 {
+        max_hp.saturating_sub(self.damage)
+    }
+
+    /// The dying value that kills the character.
+    pub fn death_threshold(&self) -> u8 {
+        4u8.saturating_sub(self.doomed)
+    }
+
+    /// Take damage, using up temporary HP first. Dropping to 0 HP makes the
+    /// character dying 1 (2 from a critical hit) plus their wounded value, and
+    /// more damage while at 0 HP increases it. Damage of at least twice the
+    /// character's max HP in one go kills them outright.
+    pub fn take_damage(&mut self, amount: u16, critical: bool, max_hp: u16) -> DamageOutcome {
+        if self.dead {
+            return DamageOutcome::Dead;
+        }
+        let from_temp = amount.min(self.temp_hp);
+        self.temp_hp -= from_temp;
+        let amount = amount - from_temp;
+        if amount == 0 {
+            return DamageOutcome::Absorbed;
+        }
+
+        if amount >= max_hp.saturating_mul(2) {
+            self.damage = max_hp;
+            self.dead = true;
+            return DamageOutcome::Dead;
+        }
+        self.damage = self.damage.saturating_add(amount).min(max_hp);
+        if self.current_hp(max_hp) > 0 {
+            return DamageOutcome::Damaged;
+        }
+        let increase = if critical { 2 } else { 1 };
+        self.dying = if self.dying == 0 {
+            increase + self.wounded
+        } else {
+            self.dying + increase
+        };
+        self.check_death()
+    }
+
+    /// Regain HP, up to the maximum. Getting back above 0 HP ends dying, and
+    /// increases wounded.
+    pub fn heal(&mut self, amount: u16, max_hp: u16) {
+        if self.dead || amount == 0 {
+            return;
+        }
+        self.damage = self.damage.min(max_hp).saturating_sub(amount);
+        self.stop_dying();
+    }
+
+    /// Temporary HP don't stack: keep whichever is higher.
+    pub fn gain_temp_hp(&mut self, amount: u16) {
+        self.temp_hp = self.temp_hp.max(amount);
+    }
+
+    /// The DC of a recovery check.
+    pub fn recovery_dc(&self) -> i16 {
+        10 + self.dying as i16
+    }
+
+    /// Apply the result of a recovery check while dying.
+    pub fn recovery_check(&mut self, result: DegreeOfSuccess) -> Result<DamageOutcome, PlayError> {
+        if self.dying == 0 {
+            return Err(PlayError::NotDying);
+        }
+        match result {
+            DegreeOfSuccess::CriticalSuccess => self.dying = self.dying.saturating_sub(2),
+            DegreeOfSuccess::Success => self.dying -= 1,
+            DegreeOfSuccess::Failure => self.dying += 1,
+            DegreeOfSuccess::CriticalFailure => self.dying += 2,
+        }
+        if self.dying == 0 {
+            self.wounded += 1;
+            return Ok(DamageOutcome::Damaged);
+        }
+        Ok(self.check_death())
+    }
+
+    fn check_death(&mut self) -> DamageOutcome {
+        if self.dying >= self.death_threshold() {
+            self.dead = true;
+            DamageOutcome::Dead
+        } else {
+            DamageOutcome::Dying(self.dying)
+        }
+    }
+
+    fn stop_dying(&mut self) {
+        if self.dying > 0 {
+            self.dying = 0;
+            self.wounded += 1;
+        }
+    }
+
+    pub fn gain_hero_point(&mut self) {
+        self.hero_points = (self.hero_points + 1).min(Self::MAX_HERO_POINTS);
+    }
+
+    /// Spend a Hero Point to reroll a check, returning how many are left.
+    pub fn spend_hero_point(&mut self) -> Result<u8, PlayError> {
+        if self.hero_points == 0 {
+            return Err(PlayError::NoHeroPoints);
+        }
+        self.hero_points -= 1;
+        Ok(self.hero_points)
+    }
+
+    /// Spend all Hero Points to avoid death: the character stops dying at 0
+    /// HP without their wounded value increasing.
+    pub fn heroic_recovery(&mut self) -> Result<(), PlayError> {
+        if self.dying == 0 {
+            return Err(PlayError::NotDying);
+        }
+        if self.hero_points == 0 {
+            return Err(PlayError::NoHeroPoints);
+        }
+        self.hero_points = 0;
+        self.dying = 0;
+        Ok(())
+    }
+
+    pub fn condition(&self, name: &str) -> Option<&ActiveCondition> {
+        self.conditions
+            .iter()
+            .find(|c| c.condition.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Apply a condition. If the character already has it, the higher value
+    /// wins.
+    pub fn apply_condition(&mut self, condition: ResourceRef, value: u8) {
+        let name = condition.name.clone();
+        match self
+            .conditions
+            .iter_mut()
+            .find(|c| c.condition.name.eq_ignore_ascii_case(&name))
+        {
+            Some(existing) => existing.value = existing.value.max(value),
+            None => self.conditions.push(ActiveCondition { condition, value }),
+        }
+    }
+
+    pub fn remove_condition(&mut self, name: &str) -> Option<ActiveCondition> {
+        let index = self
+            .conditions
+            .iter()
+            .position(|c| c.condition.name.eq_ignore_ascii_case(name))?;
+        Some(self.conditions.remove(index))
+    }
+
+    /// Values for the play fields on the PDF character sheet, by field ID.
+    pub fn pdf_fields(&self, max_hp: u16) -> Vec<(&'static str, i16)> {
+        vec![
+            ("MaxHP", max_hp as i16),
+            ("HeroPoints", self.hero_points as i16),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damage_and_temp_hp() {
+        let mut state = PlayState::default();
+        state.gain_temp_hp(5);
+        state.gain_temp_hp(3);
+        assert_eq!(state.take_damage(4, false, 20), DamageOutcome::Absorbed);
+        assert_eq!(state.take_damage(6, false, 20), DamageOutcome::Damaged);
+        assert_eq!(state.current_hp(20), 15);
+        state.heal(100, 20);
+        assert_eq!(state.current_hp(20), 20);
+        assert_eq!(state.wounded, 0);
+    }
+
+    #[test]
+    fn dying_and_wounded() {
+        let mut state = PlayState::default();
+        assert_eq!(state.take_damage(25, true, 20), DamageOutcome::Dying(2));
+        assert_eq!(
+            state.recovery_check(DegreeOfSuccess::Success),
+            Ok(DamageOutcome::Dying(1))
+        );
+        state.heal(5, 20);
+        assert_eq!(
+            (state.dying, state.wounded, state.current_hp(20)),
+            (0, 1, 5)
+        );
+
+        // Wounded adds to dying the next time.
+        assert_eq!(state.take_damage(5, false, 20), DamageOutcome::Dying(2));
+        assert_eq!(state.take_damage(1, false, 20), DamageOutcome::Dying(3));
+        assert_eq!(
+            state.recovery_check(DegreeOfSuccess::CriticalSuccess),
+            Ok(DamageOutcome::Dying(1))
+        );
+        assert_eq!(
+            state.recovery_check(DegreeOfSuccess::Success),
+            Ok(DamageOutcome::Damaged)
+        );
+        assert_eq!(state.wounded, 2);
+        assert_eq!(state.current_hp(20), 0);
+
+        state.doomed = 1;
+        assert_eq!(state.take_damage(1, false, 20), DamageOutcome::Dead);
+        assert!(state.dead);
+    }
+
+    #[test]
+    fn massive_damage() {
+        let mut state = PlayState::default();
+        assert_eq!(state.take_damage(40, false, 20), DamageOutcome::Dead);
+    }
+
+    #[test]
+    fn hero_points() {
+        let mut state = PlayState::default();
+        for _ in 0..5 {
+            state.gain_hero_point();
+        }
+        assert_eq!(state.hero_points, 3);
+        assert_eq!(state.pdf_fields(12), vec![("MaxHP", 12), ("HeroPoints", 3)]);
+        assert_eq!(state.spend_hero_point(), Ok(2));
+        assert_eq!(state.heroic_recovery(), Err(PlayError::NotDying));
+
+        state.take_damage(10, false, 10);
+        assert_eq!(state.heroic_recovery(), Ok(()));
+        assert_eq!((state.dying, state.wounded, state.hero_points), (0, 0, 0));
+        assert_eq!(state.spend_hero_point(), Err(PlayError::NoHeroPoints));
+    }
+}
@@ -0,0 +1,695 @@
+#[cfg(test)]
+use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use smartstring::alias::String;
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+use crate::{
+    bonuses::Modifier,
+    common::{Resource, ResourceRef, Spell},
+    effects::{FocusSpellEffect, SpellcastingEffect},
+    stats::Ability,
+    storage::ResourceStorage,
+    Character,
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum Tradition {
+    Arcane,
+    Divine,
+    Occult,
+    Primal,
+}
+
+impl fmt::Display for Tradition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Arcane => write!(f, "arcane"),
+            Self::Divine => write!(f, "divine"),
+            Self::Occult => write!(f, "occult"),
+            Self::Primal => write!(f, "primal"),
+        }
+    }
+}
+
+#[derive(Clone, Error, Debug, Deserialize, Serialize)]
+pub enum TraditionFromStrError {
+    #[error("Failed to parse spell tradition")]
+    Invalid,
+}
+
+impl FromStr for Tradition {
+    type Err = TraditionFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "arcane" => Ok(Self::Arcane),
+            "divine" => Ok(Self::Divine),
+            "occult" => Ok(Self::Occult),
+            "primal" => Ok(Self::Primal),
+            _ => Err(TraditionFromStrError::Invalid),
+        }
+    }
+}
+
+/// How a spellcasting entry picks the spells it casts each day.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum CastingType {
+    /// Spells are prepared into slots from a spellbook (or similar) each day.
+    Prepared,
+    /// Any spell in the repertoire can be cast with an open slot.
+    Spontaneous,
+    /// Spells granted by ancestry or items, cast a set number of times.
+    Innate,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum Save {
+    Fortitude,
+    Reflex,
+    Will,
+}
+
+impl Save {
+    /// The modifier label for the save.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Fortitude => "FORT",
+            Self::Reflex => "REF",
+            Self::Will => "WILL",
+        }
+    }
+}
+
+/// The saving throw a spell calls for, like "basic Reflex".
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+#[serde(try_from = "smartstring::alias::String")]
+pub struct SpellSave {
+    pub save: Save,
+    pub basic: bool,
+}
+
+impl fmt::Display for SpellSave {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.basic {
+            write!(f, "basic ")?;
+        }
+        write!(f, "{:?}", self.save)
+    }
+}
+
+serialize_display!(SpellSave);
+try_from_str!(SpellSave);
+
+#[derive(Clone, Error, Debug, Deserialize, Serialize)]
+pub enum SpellSaveFromStrError {
+    #[error("Failed to parse saving throw, expected something like \"basic Reflex\"")]
+    Invalid,
+}
+
+impl FromStr for SpellSave {
+    type Err = SpellSaveFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (basic, save) = match s.trim().strip_prefix("basic ") {
+            Some(rest) => (true, rest.trim()),
+            None => (false, s.trim()),
+        };
+        let save = match save.to_lowercase().as_str() {
+            "fortitude" | "fort" => Save::Fortitude,
+            "reflex" | "ref" => Save::Reflex,
+            "will" => Save::Will,
+            _ => return Err(SpellSaveFromStrError::Invalid),
+        };
+        Ok(Self { save, basic })
+    }
+}
+
+/// When a heightened entry of a spell applies: every `+n` levels above the
+/// spell's level, or at a specific spell level and up.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+#[serde(try_from = "smartstring::alias::String")]
+pub enum Heightening {
+    Every(#[cfg_attr(test, proptest(strategy = "1u8..=9"))] u8),
+    At(#[cfg_attr(test, proptest(strategy = "1u8..=10"))] u8),
+}
+
+fn ordinal(n: u8) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+impl fmt::Display for Heightening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Every(n) => write!(f, "+{}", n),
+            Self::At(n) => write!(f, "{}", ordinal(*n)),
+        }
+    }
+}
+
+serialize_display!(Heightening);
+try_from_str!(Heightening);
+
+#[derive(Clone, Error, Debug, Deserialize, Serialize)]
+pub enum HeighteningFromStrError {
+    #[error("Failed to parse heightening, expected something like \"+1\" or \"3rd\"")]
+    Invalid,
+}
+
+impl FromStr for Heightening {
+    type Err = HeighteningFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(n) = s.strip_prefix('+') {
+            return n
+                .parse()
+                .map(Self::Every)
+                .map_err(|_| HeighteningFromStrError::Invalid);
+        }
+        let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        digits
+            .parse()
+            .map(Self::At)
+            .map_err(|_| HeighteningFromStrError::Invalid)
+    }
+}
+
+/// A spell in a repertoire or spellbook. Spontaneous casters learn spells
+/// at a specific level, and signature spells can be heightened freely.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct KnownSpell {
+    pub spell: ResourceRef,
+    pub level: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub signature: bool,
+}
+
+/// A spell prepared into a slot for the day.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct PreparedSpell {
+    pub spell: ResourceRef,
+    pub level: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "crate::is_default")]
+    pub expended: bool,
+}
+
+/// The spells a character has picked for one of their spellcasting entries.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct SpellList {
+    /// The repertoire of a spontaneous caster, or the spellbook of a prepared
+    /// one.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub known: Vec<KnownSpell>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prepared: Vec<PreparedSpell>,
+}
+
+/// A spell ready to cast from a spellcasting entry, at the level it's cast
+/// at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CastableSpell {
+    pub spell: ResourceRef,
+    pub name: String,
+    pub level: u8,
+    pub cantrip: bool,
+    pub focus: bool,
+    pub expended: bool,
+}
+
+/// Read a tradition from an effect, which can name a choice like
+/// "$tradition" instead.
+fn resolve_tradition(
+    character: &Character,
+    source: &ResourceRef,
+    value: &str,
+) -> Option<Tradition> {
+    match value.strip_prefix('$') {
+        Some(choice) => character.get_effect_choice::<Tradition, _>(source, choice),
+        None => value.parse().ok(),
+    }
+}
+
+/// The spell attack modifier and the modifier to the spell DC (without the
+/// base 10) for spells of `tradition` cast with `ability`.
+fn spell_modifiers(
+    character: &Character,
+    tradition: Tradition,
+    ability: Ability,
+    resources: &dyn ResourceStorage,
+) -> (Modifier, Modifier) {
+    let ability_bonus = character.ability_scores(resources).modifier(ability);
+    let ability_mod = character.ability_check_modifier(ability, None, resources);
+    let mut attack = Modifier::untyped(ability_bonus) + ability_mod.clone();
+    attack += character.get_modifier(&format!("{} spell attack", tradition), None, resources);
+    let mut dc = Modifier::untyped(ability_bonus) + ability_mod;
+    dc += character.get_modifier(&format!("{} spell DC", tradition), None, resources);
+    (attack, dc)
+}
+
+/// One of the character's spellcasting entries, with everything the sheet
+/// needs to show for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spellcasting {
+    pub name: String,
+    pub source: ResourceRef,
+    pub tradition: Tradition,
+    pub ability: Ability,
+    pub casting_type: CastingType,
+    /// The number of slots for each spell level, starting with cantrips.
+    pub slots: SmallVec<[u8; 11]>,
+    pub attack: Modifier,
+    /// Everything added to 10 for the spell DC.
+    pub dc: Modifier,
+    /// The repertoire or spellbook, for spontaneous casters and spellbook
+    /// browsing.
+    pub known: Vec<CastableSpell>,
+    pub prepared: Vec<CastableSpell>,
+}
+
+impl Spellcasting {
+    pub fn new(
+        character: &Character,
+        source: &ResourceRef,
+        effect: &SpellcastingEffect,
+        resources: &dyn ResourceStorage,
+    ) -> Option<Self> {
+        let tradition = resolve_tradition(character, source, &effect.tradition)?;
+        let level = character.level();
+        let index = (level.get() as usize).saturating_sub(1);
+        let slots = effect
+            .slots
+            .get(index)
+            .or_else(|| effect.slots.last())
+            .cloned()
+            .unwrap_or_default();
+
+        let (attack, dc) = spell_modifiers(character, tradition, effect.ability, resources);
+
+        let heightened = character.cantrip_level();
+        let castable = |spell: &ResourceRef, level: u8, expended: bool| {
+            let resource = resources.lookup_immediate(spell)?;
+            let spell_resource: &Spell = match &*resource {
+                Resource::Spell(s) => s,
+                _ => return None,
+            };
+            let cantrip = spell_resource.is_cantrip();
+            let focus = spell_resource.is_focus();
+            let level = if cantrip || focus {
+                heightened
+            } else {
+                level.max(spell_resource.level.get())
+            };
+            Some(CastableSpell {
+                spell: spell.clone(),
+                name: spell_resource.common.name.clone(),
+                level,
+                cantrip,
+                focus,
+                expended,
+            })
+        };
+        let list = character.spell_lists.get(&effect.name);
+        let known = list
+            .iter()
+            .flat_map(|list| list.known.iter())
+            .filter_map(|known| castable(&known.spell, known.level, false))
+            .collect();
+        let prepared = list
+            .iter()
+            .flat_map(|list| list.prepared.iter())
+            .filter_map(|prepared| castable(&prepared.spell, prepared.level, prepared.expended))
+            .collect();
+
+        Some(Self {
+            name: effect.name.clone(),
+            source: source.clone(),
+            tradition,
+            ability: effect.ability,
+            casting_type: effect.casting_type,
+            slots,
+            attack,
+            dc,
+            known,
+            prepared,
+        })
+    }
+
+    pub fn attack_bonus(&self) -> i16 {
+        self.attack.total()
+    }
+
+    pub fn dc(&self) -> i16 {
+        10 + self.dc.total()
+    }
+
+    /// The highest level of spell this entry has slots for.
+    pub fn max_spell_level(&self) -> u8 {
+        self.slots.len().saturating_sub(1) as u8
+    }
+
+    /// The slots for a spell level, with level 0 being cantrips.
+    pub fn slots_at(&self, level: u8) -> u8 {
+        self.slots.get(level as usize).copied().unwrap_or(0)
+    }
+}
+
+/// Focus Points, which are spent to cast focus spells and regained by
+/// Refocusing.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FocusPool {
+    pub max: u8,
+    pub current: u8,
+}
+
+impl FocusPool {
+    /// A focus pool never holds more than this, however many resources add
+    /// to it.
+    pub const MAX: u8 = 3;
+}
+
+#[derive(Clone, Error, Debug, Eq, PartialEq)]
+pub enum FocusError {
+    #[error("There are no Focus Points left to spend")]
+    Empty,
+}
+
+/// A focus spell, along with the resource that grants it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FocusSpell {
+    pub spell: ResourceRef,
+    pub name: String,
+    /// The class feature or feat that grants the spell.
+    pub source: ResourceRef,
+    /// Focus spells are heightened like cantrips.
+    pub level: u8,
+    pub tradition: Option<Tradition>,
+    pub ability: Option<Ability>,
+    /// The spell attack modifier and DC modifier, when the tradition is
+    /// known.
+    pub modifiers: Option<(Modifier, Modifier)>,
+}
+
+impl FocusSpell {
+    pub fn new(
+        character: &Character,
+        source: &ResourceRef,
+        effect: &FocusSpellEffect,
+        resources: &dyn ResourceStorage,
+    ) -> Option<Self> {
+        let name = match resources.lookup_immediate(&effect.spell).as_deref() {
+            Some(Resource::Spell(spell)) => spell.common.name.clone(),
+            _ => {
+                debug!("Focus spell {} from {} isn't a spell", effect.spell, source);
+                return None;
+            }
+        };
+        let tradition = effect
+            .tradition
+            .as_ref()
+            .and_then(|t| resolve_tradition(character, source, t));
+        let ability = effect.ability.or_else(|| character.key_ability(resources));
+        let modifiers = match (tradition, ability) {
+            (Some(tradition), Some(ability)) => {
+                Some(spell_modifiers(character, tradition, ability, resources))
+            }
+            _ => None,
+        };
+        Some(Self {
+            spell: effect.spell.clone(),
+            name,
+            source: source.clone(),
+            level: character.cantrip_level(),
+            tradition,
+            ability,
+            modifiers,
+        })
+    }
+
+    pub fn attack_bonus(&self) -> Option<i16> {
+        self.modifiers.as_ref().map(|(attack, _)| attack.total())
+    }
+
+    pub fn dc(&self) -> Option<i16> {
+        self.modifiers.as_ref().map(|(_, dc)| 10 + dc.total())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::{Feat, ResourceCommon, ResourceType},
+        effects::{Effect, EffectCommon, IncreaseProficiencyEffect},
+        stats::{Level, Proficiency},
+        test_helpers::{crb_monk, TestStorage},
+    };
+
+    fn spell(name: &str, level: u8, traits: &[&str]) -> Resource {
+        let mut common = ResourceCommon::new(name);
+        common.add_traits(traits);
+        Resource::Spell(Spell {
+            level: Level::from(level),
+            ..Spell::new(common)
+        })
+    }
+
+    fn spellcasting_feature(tradition: &str, casting_type: CastingType) -> Resource {
+        let slots = vec![
+            SmallVec::from_slice(&[5, 2]),
+            SmallVec::from_slice(&[5, 3]),
+            SmallVec::from_slice(&[5, 3, 2]),
+        ];
+        let mut common = ResourceCommon::new("Spellcasting");
+        common.add_effect(SpellcastingEffect {
+            common: EffectCommon::default(),
+            name: "Arcane Spellcasting".into(),
+            tradition: tradition.into(),
+            ability: Ability::INT,
+            casting_type,
+            slots,
+        });
+        common.add_effect(IncreaseProficiencyEffect {
+            common: EffectCommon::default(),
+            target: "arcane spellcasting".into(),
+            level: Proficiency::Trained,
+        });
+        Resource::Feat(Feat {
+            common,
+            level: Level::from(1),
+        })
+    }
+
+    fn character_at(level: u8, feature: &Resource) -> Character {
+        let mut character = Character::new("Test Character");
+        let class = ResourceRef::new("Wizard", None::<&str>).with_type(Some(ResourceType::Class));
+        character
+            .set_choice(&class, "Level".into(), &Level::from(level))
+            .unwrap();
+        character.resources.insert(class);
+        character.resources.insert(feature.make_rref_no_mod());
+        character
+    }
+
+    #[test]
+    fn parse_spell_parts() {
+        let save: SpellSave = "basic Reflex".parse().unwrap();
+        assert_eq!(
+            save,
+            SpellSave {
+                save: Save::Reflex,
+                basic: true
+            }
+        );
+        assert_eq!(&format!("{}", save), "basic Reflex");
+        assert_eq!("+2".parse::<Heightening>().unwrap(), Heightening::Every(2));
+        assert_eq!("3rd".parse::<Heightening>().unwrap(), Heightening::At(3));
+        assert_eq!(&format!("{}", Heightening::At(2)), "2nd");
+        assert_eq!(&format!("{}", Heightening::At(11)), "11th");
+        assert!("Arcane".parse::<Tradition>().is_ok());
+    }
+
+    #[test]
+    fn prepared_caster() {
+        let feature = spellcasting_feature("arcane", CastingType::Prepared);
+        let shield = spell("Shield", 1, &["Cantrip", "Abjuration"]);
+        let missile = spell("Magic Missile", 1, &["Evocation"]);
+        let storage = TestStorage::new(vec![feature.clone(), shield.clone(), missile.clone()]);
+
+        let mut character = character_at(3, &feature);
+        let list = character
+            .spell_lists
+            .entry("Arcane Spellcasting".into())
+            .or_default();
+        for (spell, level) in [(&shield, 1), (&missile, 2), (&missile, 1)].iter() {
+            list.prepared.push(PreparedSpell {
+                spell: spell.make_rref_no_mod(),
+                level: *level,
+                expended: false,
+            });
+        }
+
+        let entries = character.spellcasting(&storage);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.tradition, Tradition::Arcane);
+        assert_eq!(entry.max_spell_level(), 2);
+        assert_eq!(entry.slots_at(1), 3);
+        // Trained (+2) at level 3, with no INT bonus.
+        assert_eq!(entry.attack_bonus(), 5);
+        assert_eq!(entry.dc(), 15);
+        let levels: Vec<_> = entry
+            .prepared
+            .iter()
+            .map(|s| (s.cantrip, s.level))
+            .collect();
+        assert_eq!(levels, vec![(true, 2), (false, 2), (false, 1)]);
+
+        let character = character_at(1, &feature);
+        let entry = &character.spellcasting(&storage)[0];
+        assert_eq!(entry.max_spell_level(), 1);
+        assert_eq!(entry.dc(), 13);
+    }
+
+    #[test]
+    fn tradition_choice() {
+        let feature = spellcasting_feature("$tradition", CastingType::Spontaneous);
+        let storage = TestStorage::new(vec![feature.clone()]);
+        let mut character = character_at(1, &feature);
+        assert!(character.spellcasting(&storage).is_empty());
+        character
+            .set_choice(
+                &feature.make_rref_no_mod(),
+                "tradition".into(),
+                &Tradition::Occult,
+            )
+            .unwrap();
+        let entry = &character.spellcasting(&storage)[0];
+        assert_eq!(entry.tradition, Tradition::Occult);
+        // Only arcane proficiency was granted.
+        assert_eq!(entry.attack_bonus(), 0);
+    }
+
+    #[test]
+    fn focus_pool_and_spells() {
+        let ki_strike = spell("Ki Strike", 1, &["Focus", "Monk"]);
+        let mut feats = vec![];
+        for name in ["Ki Strike", "Ki Rush", "Wholeness of Body", "Abundant Step"].iter() {
+            let mut common = ResourceCommon::new(*name);
+            common.add_effect(Effect::AddSingleFocusPoolPoint);
+            feats.push(Resource::Feat(Feat {
+                common,
+                level: Level::from(1),
+            }));
+        }
+        if let Resource::Feat(feat) = &mut feats[0] {
+            feat.common.add_effect(FocusSpellEffect {
+                tradition: Some("divine".into()),
+                ability: Some(Ability::WIS),
+                ..FocusSpellEffect::from(ki_strike.make_rref_no_mod())
+            });
+        }
+        let mut all = feats.clone();
+        all.push(ki_strike.clone());
+        let storage = TestStorage::new(all);
+
+        let mut character = character_at(5, &feats[0]);
+        assert_eq!(character.focus_pool(&storage).max, 1);
+        for feat in feats.iter().skip(1) {
+            character.resources.insert(feat.make_rref_no_mod());
+        }
+        let pool = character.focus_pool(&storage);
+        assert_eq!((pool.max, pool.current), (3, 3));
+
+        assert_eq!(character.spend_focus_point(&storage), Ok(2));
+        assert_eq!(character.spend_focus_point(&storage), Ok(1));
+        assert_eq!(character.spend_focus_point(&storage), Ok(0));
+        assert_eq!(
+            character.spend_focus_point(&storage),
+            Err(FocusError::Empty)
+        );
+        assert_eq!(character.refocus(&storage), 1);
+        assert_eq!(character.focus_pool(&storage).current, 1);
+
+        let spells = character.focus_spells(&storage);
+        assert_eq!(spells.len(), 1);
+        let ki = &spells[0];
+        assert_eq!(ki.source, feats[0].make_rref_no_mod());
+        assert_eq!(ki.level, 3);
+        assert_eq!(ki.tradition, Some(Tradition::Divine));
+        assert_eq!(ki.dc(), Some(10));
+    }
+
+    #[test]
+    fn crb_ki_spells() {
+        let resources = crb_monk();
+        let rref = |name: &str, rtype: ResourceType| {
+            resources
+                .iter()
+                .find(|r| r.common().name == name && r.resource_type() == rtype)
+                .unwrap()
+                .make_rref_no_mod()
+        };
+        let monk = rref("Monk", ResourceType::Class);
+        let feats = [
+            rref("ki rush", ResourceType::Feat),
+            rref("ki strike", ResourceType::Feat),
+        ];
+        let mut character = Character::new("Test Monk");
+        character.resources.insert(monk.clone());
+        character
+            .set_choice(&monk, "Level".into(), &Level::from(1))
+            .unwrap();
+        character.resources.extend(feats.iter().cloned());
+        character
+            .set_character_choice("ki_spell_tradition".into(), &Tradition::Occult)
+            .unwrap();
+        let storage = TestStorage::new(resources.clone());
+        character.normalize_resources(&storage);
+
+        assert_eq!(character.focus_pool(&storage).max, 2);
+        let spells = character.focus_spells(&storage);
+        let names: Vec<&str> = spells.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Ki Rush", "Ki Strike"]);
+        for spell in spells.iter() {
+            assert_eq!(spell.tradition, Some(Tradition::Occult));
+            assert_eq!(spell.ability, Some(Ability::WIS));
+            // Trained at 1st level, with a WIS of 10.
+            assert_eq!(spell.dc(), Some(13));
+        }
+
+        // Monk expertise raises the same tradition to expert.
+        character
+            .set_choice(&monk, "Level".into(), &Level::from(9))
+            .unwrap();
+        character.normalize_resources(&storage);
+        let spells = character.focus_spells(&storage);
+        assert_eq!(spells.len(), 2);
+        for spell in spells.iter() {
+            assert_eq!(spell.dc(), Some(23));
+        }
+    }
+}
@@ -1,4 +1,3 @@
-use lazy_static::lazy_static;
 #[cfg(test)]
 use proptest::prelude::*;
 #[cfg(test)]
@@ -8,7 +7,7 @@ use smartstring::alias::String;
 use std::{fmt, str::FromStr};
 use thiserror::Error;
 
-use crate::bonuses::Bonus;
+use crate::bonuses::{Bonus, Modifier};
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
@@ -57,6 +56,19 @@ impl Ability {
 
 try_from_str!(Ability);
 
+impl fmt::Display for Ability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::STR => write!(f, "STR"),
+            Self::DEX => write!(f, "DEX"),
+            Self::CON => write!(f, "CON"),
+            Self::INT => write!(f, "INT"),
+            Self::WIS => write!(f, "WIS"),
+            Self::CHA => write!(f, "CHA"),
+        }
+    }
+}
+
 #[derive(Clone, Error, Debug, Deserialize, Serialize)]
 pub enum AbilityFromStrError {
     #[error("Unknown ability value {0:?}, expected one of \"STR\", \"DEX\", \"CON\", \"INT\", \"WIS\", or \"CHA\"")]
@@ -77,7 +89,7 @@ impl FromStr for Ability {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 #[serde(try_from = "smartstring::alias::String")]
 pub enum AbilityBoost {
@@ -85,7 +97,7 @@ pub enum AbilityBoost {
         #[cfg_attr(
             test,
             proptest(
-                strategy = "proptest::collection::vec(any::<Ability>(), 0..=6).prop_map_into()"
+                strategy = "proptest::collection::btree_set(any::<Ability>(), 2..=6).prop_map(|s| s.into_iter().collect())"
             )
         )]
         smallvec::SmallVec<[Ability; 2]>,
@@ -95,6 +107,39 @@ pub enum AbilityBoost {
 }
 
 try_from_str!(AbilityBoost);
+serialize_display!(AbilityBoost);
+
+impl AbilityBoost {
+    /// Whether this boost may be applied to `ability`.
+    pub fn allows(&self, ability: Ability) -> bool {
+        match self {
+            Self::Choice(options) => options.contains(&ability),
+            Self::Fixed(a) => *a == ability,
+            Self::Free => true,
+        }
+    }
+}
+
+impl fmt::Display for AbilityBoost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Choice(options) => {
+                for (i, a) in options.iter().enumerate() {
+                    match (i, options.len()) {
+                        (0, _) => (),
+                        (_, 2) => write!(f, " or ")?,
+                        (i, n) if i + 1 == n => write!(f, ", or ")?,
+                        _ => write!(f, ", ")?,
+                    }
+                    write!(f, "{}", a)?;
+                }
+                Ok(())
+            }
+            Self::Fixed(a) => write!(f, "{}", a),
+            Self::Free => write!(f, "free"),
+        }
+    }
+}
 
 #[derive(Clone, Error, Debug, Deserialize, Serialize)]
 pub enum AbilityBoostFromStrError {
@@ -209,15 +254,65 @@ impl fmt::Display for Alignment {
     }
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(test, derive(Arbitrary))]
-#[serde(try_from = "smartstring::alias::String")]
 pub enum Bulk {
     Negligable,
     Light,
     Heavy(u16),
 }
 
+impl fmt::Display for Bulk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Negligable => write!(f, "-"),
+            Self::Light => write!(f, "L"),
+            Self::Heavy(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+serialize_display!(Bulk);
+
+/// Bulk is usually written as a plain number, so accept those as well as
+/// strings.
+impl<'de> Deserialize<'de> for Bulk {
+    fn deserialize<D>(deserializer: D) -> Result<Bulk, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct BulkVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BulkVisitor {
+            type Value = Bulk;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number of Bulk, \"L\", or \"-\"")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Bulk, E> {
+                match value {
+                    n if n <= u16::MAX as u64 => Ok(Bulk::Heavy(n as u16)),
+                    _ => Err(E::custom("Bulk value is too large")),
+                }
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Bulk, E> {
+                match value {
+                    n if n >= 0 => self.visit_u64(n as u64),
+                    _ => Err(E::custom("Bulk can't be negative")),
+                }
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Bulk, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(BulkVisitor)
+    }
+}
+
 impl Default for Bulk {
     fn default() -> Self {
         Self::Negligable
@@ -246,7 +341,158 @@ impl FromStr for Bulk {
     }
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+impl std::ops::Add for Bulk {
+    type Output = BulkTotal;
+
+    fn add(self, other: Self) -> BulkTotal {
+        BulkTotal::from(self) + other
+    }
+}
+
+impl std::ops::Mul<u32> for Bulk {
+    type Output = BulkTotal;
+
+    fn mul(self, count: u32) -> BulkTotal {
+        BulkTotal {
+            tenths: BulkTotal::from(self).tenths * count,
+        }
+    }
+}
+
+/// A sum of Bulk. It's kept in tenths, since 10 light items add up to 1
+/// Bulk.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct BulkTotal {
+    tenths: u32,
+}
+
+impl BulkTotal {
+    pub fn new(bulk: u32, light: u32) -> Self {
+        Self {
+            tenths: (bulk * 10) + light,
+        }
+    }
+
+    /// The whole Bulk in this total. Light items that don't add up to a
+    /// whole Bulk are left out, which is how limits are checked.
+    pub fn bulk(self) -> u32 {
+        self.tenths / 10
+    }
+
+    /// The light items left over after the whole Bulk.
+    pub fn light(self) -> u32 {
+        self.tenths % 10
+    }
+
+    /// Take off the first `bulk` Bulk, like a backpack does for what's in it.
+    pub fn saturating_sub(self, bulk: u32) -> Self {
+        Self {
+            tenths: self.tenths.saturating_sub(bulk * 10),
+        }
+    }
+}
+
+impl From<Bulk> for BulkTotal {
+    fn from(bulk: Bulk) -> Self {
+        match bulk {
+            Bulk::Negligable => Self::new(0, 0),
+            Bulk::Light => Self::new(0, 1),
+            Bulk::Heavy(n) => Self::new(n as u32, 0),
+        }
+    }
+}
+
+impl fmt::Display for BulkTotal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.bulk(), self.light()) {
+            (0, 0) => write!(f, "-"),
+            (0, l) => write!(f, "{}L", l),
+            (b, 0) => write!(f, "{}", b),
+            (b, l) => write!(f, "{}; {}L", b, l),
+        }
+    }
+}
+
+impl std::ops::Add for BulkTotal {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            tenths: self.tenths + other.tenths,
+        }
+    }
+}
+
+impl std::ops::Add<Bulk> for BulkTotal {
+    type Output = Self;
+
+    fn add(self, other: Bulk) -> Self {
+        self + BulkTotal::from(other)
+    }
+}
+
+impl std::ops::AddAssign for BulkTotal {
+    fn add_assign(&mut self, other: Self) {
+        self.tenths += other.tenths;
+    }
+}
+
+impl std::ops::AddAssign<Bulk> for BulkTotal {
+    fn add_assign(&mut self, other: Bulk) {
+        *self += BulkTotal::from(other);
+    }
+}
+
+impl std::iter::Sum for BulkTotal {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |a, b| a + b)
+    }
+}
+
+impl std::iter::Sum<Bulk> for BulkTotal {
+    fn sum<I: Iterator<Item = Bulk>>(iter: I) -> Self {
+        iter.fold(Self::default(), |a, b| a + b)
+    }
+}
+
+/// How much a character is carrying, against what they can carry.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BulkLimits {
+    pub carried: BulkTotal,
+    /// Carrying more than this makes the character encumbered.
+    pub encumbered: BulkTotal,
+    /// The character can't carry more than this.
+    pub maximum: BulkTotal,
+}
+
+impl BulkLimits {
+    /// The limits for a creature with the given STR modifier and size.
+    pub fn new(carried: BulkTotal, str_mod: i16, size: Size) -> Self {
+        let limit = |base: i16| {
+            let bulk = (base + str_mod).max(0) as u32;
+            match size {
+                Size::Tiny => BulkTotal { tenths: bulk * 5 },
+                Size::Small | Size::Medium => BulkTotal::new(bulk, 0),
+                Size::Large => BulkTotal::new(bulk * 2, 0),
+            }
+        };
+        Self {
+            carried,
+            encumbered: limit(5),
+            maximum: limit(10),
+        }
+    }
+
+    pub fn is_encumbered(&self) -> bool {
+        BulkTotal::new(self.carried.bulk(), 0) > self.encumbered
+    }
+
+    pub fn over_maximum(&self) -> bool {
+        BulkTotal::new(self.carried.bulk(), 0) > self.maximum
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Deserialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 #[serde(try_from = "smartstring::alias::String")]
 pub struct Gold {
@@ -254,12 +500,11 @@ pub struct Gold {
 }
 
 try_from_str!(Gold);
+serialize_display!(Gold);
 
 #[derive(Clone, Error, Debug, Deserialize, Serialize)]
 pub enum GoldFromStrError {
-    #[error("Failed to parse gold value number")]
-    BadNumber,
-    #[error("Invalid gold value, expected either \"-\" or a number followed by gp, sp, or cp.")]
+    #[error("Invalid gold value, expected either \"-\" or numbers followed by pp, gp, sp, or cp.")]
     Invalid,
 }
 
@@ -267,29 +512,11 @@ impl FromStr for Gold {
     type Err = GoldFromStrError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        lazy_static! {
-            static ref GOLD_REGEX: regex::Regex =
-                regex::Regex::new("^(?P<n>[1-9][0-9]*) ?(?P<type>[csg])p$").unwrap();
-        }
         let s = s.trim();
         if s == "-" {
             return Ok(Gold::zero());
         }
-        match GOLD_REGEX.captures(s) {
-            Some(caps) => match &caps["type"] {
-                "c" => Ok(Self::cp(
-                    caps["n"].parse().map_err(|_| GoldFromStrError::BadNumber)?,
-                )),
-                "s" => Ok(Self::sp(
-                    caps["n"].parse().map_err(|_| GoldFromStrError::BadNumber)?,
-                )),
-                "g" => Ok(Self::gp(
-                    caps["n"].parse().map_err(|_| GoldFromStrError::BadNumber)?,
-                )),
-                _ => unreachable!(),
-            },
-            None => Err(GoldFromStrError::Invalid),
-        }
+        crate::parsers::currency(s).map_err(|_| GoldFromStrError::Invalid)
     }
 }
 
@@ -339,20 +566,66 @@ impl Gold {
     pub fn platinum_part(&self) -> u32 {
         self.total_copper / 1000
     }
+
+    /// Subtract `other`, failing if there isn't enough to cover it.
+    pub fn checked_sub(self, other: Self) -> Result<Self, GoldUnderflowError> {
+        match self.total_copper.checked_sub(other.total_copper) {
+            Some(total_copper) => Ok(Self { total_copper }),
+            None => Err(GoldUnderflowError {
+                available: self,
+                needed: other,
+            }),
+        }
+    }
+
+    /// The price of `count` of something, failing if it's too much to count.
+    pub fn checked_mul(self, count: u32) -> Result<Self, GoldOverflowError> {
+        match self.total_copper.checked_mul(count) {
+            Some(total_copper) => Ok(Self { total_copper }),
+            None => Err(GoldOverflowError {
+                amount: self,
+                count,
+            }),
+        }
+    }
+
+    /// Like `checked_mul`, but stopping at the most that can be counted.
+    pub fn saturating_mul(self, count: u32) -> Self {
+        Self {
+            total_copper: self.total_copper.saturating_mul(count),
+        }
+    }
+
+    /// Half the value, rounded down to the copper. This is what items sell
+    /// for.
+    pub fn half(self) -> Self {
+        Self {
+            total_copper: self.total_copper / 2,
+        }
+    }
+}
+
+#[derive(Clone, Error, Debug, Deserialize, Serialize)]
+#[error("Can't take {needed} from {available}")]
+pub struct GoldUnderflowError {
+    pub available: Gold,
+    pub needed: Gold,
+}
+
+#[derive(Clone, Error, Debug, Deserialize, Serialize)]
+#[error("{amount} times {count} is more than can be counted")]
+pub struct GoldOverflowError {
+    pub amount: Gold,
+    pub count: u32,
 }
 
 impl fmt::Display for Gold {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut platinum = self.platinum_part();
-        let mut gold = self.gold_part();
+        let platinum = self.platinum_part();
+        let gold = self.gold_part();
         let copper = self.copper_part();
         let silver = self.silver_part();
 
-        if platinum > 0 && platinum <= 10 && gold != 0 {
-            gold += 10 * (platinum % 10);
-            platinum /= 10;
-        }
-
         match (platinum, gold, silver, copper) {
             (0, 0, 0, 0) => write!(f, "-"),
             (0, 0, 0, c) => write!(f, "{} cp", c),
@@ -382,6 +655,18 @@ impl std::ops::Add for Gold {
     }
 }
 
+impl std::ops::AddAssign for Gold {
+    fn add_assign(&mut self, other: Self) {
+        self.total_copper += other.total_copper;
+    }
+}
+
+impl std::iter::Sum for Gold {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |a, b| a + b)
+    }
+}
+
 #[test]
 fn test_gold_display() {
     assert_eq!(&format!("{}", Gold::cp(0)), "-");
@@ -400,6 +685,94 @@ fn test_gold_display() {
     assert_eq!(&format!("{}", Gold::gp(120) + Gold::cp(3)), "12 pp 3 cp");
     assert_eq!(&format!("{}", Gold::gp(120) + Gold::cp(34)), "12 pp 34 cp");
     assert_eq!(&format!("{}", Gold::gp(123) + Gold::cp(45)), "123 gp 45 cp");
+    assert_eq!(&format!("{}", Gold::gp(103)), "103 gp");
+    assert_eq!(&format!("{}", Gold::gp(1003) + Gold::sp(4)), "1003 gp 4 sp");
+}
+
+#[test]
+fn test_gold_display_round_trip() {
+    for gold in [
+        Gold::gp(103),
+        Gold::gp(1003) + Gold::sp(4),
+        Gold::pp(100) + Gold::cp(7),
+    ]
+    .iter()
+    {
+        let shown = format!("{}", gold);
+        assert_eq!(shown.parse::<Gold>().unwrap(), *gold, "{}", shown);
+    }
+}
+
+#[test]
+fn test_parse_gold() {
+    assert_eq!("-".parse::<Gold>().unwrap(), Gold::zero());
+    assert_eq!("6 gp".parse::<Gold>().unwrap(), Gold::gp(6));
+    assert_eq!("3gp".parse::<Gold>().unwrap(), Gold::gp(3));
+    assert_eq!(
+        "1 gp 2 cp".parse::<Gold>().unwrap(),
+        Gold::gp(1) + Gold::cp(2)
+    );
+    assert_eq!(
+        "12 pp 34 cp".parse::<Gold>().unwrap(),
+        Gold::gp(120) + Gold::cp(34)
+    );
+    assert!("2 cp 1 gp".parse::<Gold>().is_err());
+    assert!("".parse::<Gold>().is_err());
+}
+
+#[test]
+fn test_gold_arithmetic() {
+    assert_eq!(Gold::gp(2).checked_sub(Gold::sp(5)).unwrap(), Gold::sp(15));
+    let err = Gold::sp(5).checked_sub(Gold::gp(1)).unwrap_err();
+    assert_eq!((err.available, err.needed), (Gold::sp(5), Gold::gp(1)));
+    assert_eq!(Gold::sp(3).checked_mul(4).unwrap(), Gold::cp(120));
+    let err = Gold::pp(5_000).checked_mul(1_000).unwrap_err();
+    assert_eq!((err.amount, err.count), (Gold::pp(5_000), 1_000));
+    assert_eq!(Gold::pp(5_000).saturating_mul(1_000), Gold::cp(u32::MAX));
+    assert_eq!(Gold::cp(7).half(), Gold::cp(3));
+    let total: Gold = vec![Gold::gp(1), Gold::sp(2)].into_iter().sum();
+    assert_eq!(total, Gold::cp(120));
+}
+
+#[test]
+fn test_deserialize_bulk() {
+    assert_eq!(serde_json::from_str::<Bulk>("2").unwrap(), Bulk::Heavy(2));
+    assert_eq!(serde_json::from_str::<Bulk>("\"L\"").unwrap(), Bulk::Light);
+    assert_eq!(
+        serde_json::from_str::<Bulk>("\"-\"").unwrap(),
+        Bulk::Negligable
+    );
+    assert!(serde_json::from_str::<Bulk>("-1").is_err());
+}
+
+#[test]
+fn test_bulk_arithmetic() {
+    let total: BulkTotal = vec![Bulk::Light; 13].into_iter().sum();
+    assert_eq!((total.bulk(), total.light()), (1, 3));
+    assert_eq!(&format!("{}", total), "1; 3L");
+    assert_eq!(Bulk::Heavy(2) + Bulk::Light, BulkTotal::new(2, 1));
+    assert_eq!(Bulk::Light * 10, BulkTotal::new(1, 0));
+    assert_eq!(Bulk::Negligable * 100, BulkTotal::default());
+    assert_eq!(BulkTotal::new(3, 4).saturating_sub(2), BulkTotal::new(1, 4));
+    assert_eq!(BulkTotal::new(1, 4).saturating_sub(2), BulkTotal::default());
+}
+
+#[test]
+fn test_bulk_limits() {
+    let limits = BulkLimits::new(BulkTotal::new(7, 9), 2, Size::Medium);
+    assert_eq!(limits.encumbered, BulkTotal::new(7, 0));
+    assert_eq!(limits.maximum, BulkTotal::new(12, 0));
+    assert!(!limits.is_encumbered());
+    let limits = BulkLimits::new(BulkTotal::new(8, 0), 2, Size::Medium);
+    assert!(limits.is_encumbered());
+    assert!(!limits.over_maximum());
+
+    let tiny = BulkLimits::new(BulkTotal::default(), 0, Size::Tiny);
+    assert_eq!(tiny.encumbered, BulkTotal::new(2, 5));
+    let large = BulkLimits::new(BulkTotal::default(), 0, Size::Large);
+    assert_eq!(large.maximum, BulkTotal::new(20, 0));
+    let weak = BulkLimits::new(BulkTotal::default(), -5, Size::Medium);
+    assert_eq!(weak.encumbered, BulkTotal::default());
 }
 
 #[repr(transparent)]
@@ -475,17 +848,86 @@ impl FromStr for Proficiency {
     }
 }
 
+impl fmt::Display for Proficiency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Untrained => write!(f, "untrained"),
+            Self::Trained => write!(f, "trained"),
+            Self::Expert => write!(f, "expert"),
+            Self::Master => write!(f, "master"),
+            Self::Legendary => write!(f, "legendary"),
+        }
+    }
+}
+
 impl Default for Proficiency {
     fn default() -> Self {
         Proficiency::Untrained
     }
 }
 
+/// A character's AC, broken down the way the character sheet shows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArmorClass {
+    /// The worn armor's DEX cap, if it has one.
+    pub dex_cap: Option<u16>,
+    /// The rank in the worn armor's category (or unarmored defense).
+    pub proficiency: Proficiency,
+    pub modifier: Modifier,
+}
+
+impl ArmorClass {
+    pub fn total(&self) -> i16 {
+        self.modifier.total()
+    }
+
+    pub fn item_bonus(&self) -> i16 {
+        self.modifier.item_part().total()
+    }
+
+    pub fn proficiency_bonus(&self) -> i16 {
+        self.modifier.proficiency_part().total()
+    }
+
+    /// Values for the AC fields on the PDF character sheet, by field ID. The
+    /// DEX cap is left out when there isn't one.
+    pub fn pdf_fields(&self) -> Vec<(&'static str, i16)> {
+        let mut fields = vec![];
+        if let Some(cap) = self.dex_cap {
+            fields.push(("ACDexCap", cap as i16));
+        }
+        fields.push(("ACItemBonus", self.item_bonus()));
+        fields.push(("ACArmorProficiency", self.proficiency_bonus()));
+        fields.push(("TotalAC", self.total()));
+        fields
+    }
+}
+
 impl Proficiency {
     #[inline]
     pub fn bonus(self, level: Level) -> Bonus {
         Bonus::proficiency(self, level)
     }
+
+    /// The next rank up, stopping at legendary.
+    pub fn step_up(self) -> Self {
+        match self {
+            Self::Untrained => Self::Trained,
+            Self::Trained => Self::Expert,
+            Self::Expert => Self::Master,
+            Self::Master | Self::Legendary => Self::Legendary,
+        }
+    }
+
+    /// The highest rank a skill increase can reach at `level`.
+    pub fn max_skill_rank(level: Level) -> Self {
+        match level.get() {
+            0..=2 => Self::Trained,
+            3..=6 => Self::Expert,
+            7..=14 => Self::Master,
+            _ => Self::Legendary,
+        }
+    }
 }
 
 // pub trait ProvidesProficiency {
@@ -622,6 +1064,38 @@ impl fmt::Display for Skill {
 }
 
 impl Skill {
+    /// Every skill other than lore.
+    pub fn standard() -> impl Iterator<Item = Self> {
+        vec![
+            Self::Acrobatics,
+            Self::Arcana,
+            Self::Athletics,
+            Self::Crafting,
+            Self::Deception,
+            Self::Diplomacy,
+            Self::Intimidation,
+            Self::Medicine,
+            Self::Nature,
+            Self::Occultism,
+            Self::Performance,
+            Self::Religion,
+            Self::Society,
+            Self::Stealth,
+            Self::Survival,
+            Self::Thievery,
+        ]
+        .into_iter()
+    }
+
+    /// Skills are compared by name, ignoring case, so that "Lore (Circus)"
+    /// and "lore (circus)" are the same skill.
+    pub fn is_same(&self, other: &Skill) -> bool {
+        match (self, other) {
+            (Self::Lore(a), Self::Lore(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a == b,
+        }
+    }
+
     pub fn base_ability(&self) -> Ability {
         use Ability::*;
 
@@ -649,7 +1123,7 @@ impl Skill {
 
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(test, derive(Arbitrary))]
-#[serde(try_from = "smartstring::alias::String")]
+#[serde(rename_all = "lowercase", try_from = "smartstring::alias::String")]
 pub enum Size {
     Tiny,
     Small,
@@ -657,6 +1131,12 @@ pub enum Size {
     Large,
 }
 
+impl Default for Size {
+    fn default() -> Self {
+        Self::Medium
+    }
+}
+
 try_from_str!(Size);
 
 #[derive(Clone, Error, Debug, Deserialize, Serialize)]
@@ -706,7 +1186,7 @@ impl fmt::Display for Range {
     }
 }
 
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Deserialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 #[serde(try_from = "smartstring::alias::String")]
 pub enum Die {
@@ -731,7 +1211,21 @@ impl fmt::Display for Die {
     }
 }
 
+impl Die {
+    pub fn sides(self) -> u16 {
+        match self {
+            Self::D4 => 4,
+            Self::D6 => 6,
+            Self::D8 => 8,
+            Self::D10 => 10,
+            Self::D12 => 12,
+            Self::D20 => 20,
+        }
+    }
+}
+
 try_from_str!(Die);
+serialize_display!(Die);
 
 #[derive(Clone, Error, Debug, Deserialize, Serialize)]
 pub enum DieFromStrError {
@@ -755,7 +1249,7 @@ impl FromStr for Die {
     }
 }
 
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Deserialize)]
 #[cfg_attr(test, derive(Arbitrary))]
 #[serde(try_from = "smartstring::alias::String")]
 pub struct DieRoll {
@@ -770,6 +1264,7 @@ impl fmt::Display for DieRoll {
 }
 
 try_from_str!(DieRoll);
+serialize_display!(DieRoll);
 
 #[derive(Clone, Error, Debug, Deserialize, Serialize)]
 pub enum DieRollFromStrError {
@@ -0,0 +1,547 @@
+use smallvec::SmallVec;
+use smartstring::alias::String;
+use std::fmt;
+
+use crate::{
+    bonuses::{Bonus, Modifier},
+    common::ResourceRef,
+    items::{Weapon, WeaponTrait},
+    stats::{Ability, DamageType, DieRoll, Proficiency},
+    storage::ResourceStorage,
+    Character,
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StrikeKind {
+    Melee,
+    Ranged,
+}
+
+/// The weapon rows on the PDF character sheet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WeaponSlot {
+    Melee1,
+    Melee2,
+    Melee3,
+    Ranged1,
+    Ranged2,
+    Ranged3,
+}
+
+impl fmt::Display for WeaponSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl WeaponSlot {
+    pub fn slots(kind: StrikeKind) -> [Self; 3] {
+        match kind {
+            StrikeKind::Melee => [Self::Melee1, Self::Melee2, Self::Melee3],
+            StrikeKind::Ranged => [Self::Ranged1, Self::Ranged2, Self::Ranged3],
+        }
+    }
+}
+
+/// Trait effects that only apply to some Strikes, so they can't be folded
+/// into the attack or damage modifiers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StrikeRider {
+    /// Extra dice of damage on a critical hit.
+    Deadly(DieRoll),
+    /// On a critical hit, the damage dice become `dice`, and `extra` is added
+    /// after doubling.
+    Fatal { dice: DieRoll, extra: DieRoll },
+    /// Extra damage on the second and third attack of the turn.
+    Forceful { second: i16, third: i16 },
+    /// A penalty to attack targets within `range` feet.
+    Volley { range: u16, penalty: i16 },
+}
+
+impl StrikeRider {
+    fn from_trait(t: &WeaponTrait, weapon: &Weapon) -> Option<Self> {
+        let dice = weapon.damage_dice();
+        match t {
+            WeaponTrait::Deadly(die) => Some(Self::Deadly(DieRoll {
+                count: (weapon.striking as u16).max(1),
+                size: *die,
+            })),
+            WeaponTrait::Fatal(die) => Some(Self::Fatal {
+                dice: DieRoll { size: *die, ..dice },
+                extra: DieRoll {
+                    count: 1,
+                    size: *die,
+                },
+            }),
+            WeaponTrait::Forceful => Some(Self::Forceful {
+                second: dice.count as i16,
+                third: 2 * dice.count as i16,
+            }),
+            WeaponTrait::Volley(range) => Some(Self::Volley {
+                range: *range,
+                penalty: -2,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StrikeRider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Deadly(dice) => write!(f, "deadly {}", dice),
+            Self::Fatal { dice, extra } => write!(f, "fatal {} + {}", dice, extra),
+            Self::Forceful { second, third } => {
+                write!(f, "forceful +{}/+{}", second, third)
+            }
+            Self::Volley { range, penalty } => write!(f, "volley {} ft. ({})", range, penalty),
+        }
+    }
+}
+
+/// One way of attacking with a weapon, with everything the character sheet
+/// needs to show for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Strike {
+    pub name: String,
+    pub weapon: ResourceRef,
+    pub kind: StrikeKind,
+    /// The range increment, for ranged and thrown Strikes.
+    pub range: Option<u16>,
+    pub proficiency: Proficiency,
+    pub attack_ability: Ability,
+    pub attack_ability_bonus: i16,
+    /// The first attack of the turn, before the multiple attack penalty.
+    pub attack: Modifier,
+    pub agile: bool,
+    pub damage_dice: DieRoll,
+    pub damage_ability: Option<Ability>,
+    pub damage_ability_bonus: i16,
+    pub damage: Modifier,
+    pub damage_type: DamageType,
+    pub riders: SmallVec<[StrikeRider; 2]>,
+    pub traits: SmallVec<[WeaponTrait; 4]>,
+}
+
+impl Strike {
+    pub fn new(
+        character: &Character,
+        rref: &ResourceRef,
+        name: &str,
+        weapon: &Weapon,
+        kind: StrikeKind,
+        resources: &dyn ResourceStorage,
+    ) -> Self {
+        let scores = character.ability_scores(resources);
+        let thrown = weapon.thrown_range();
+
+        let attack_ability = match kind {
+            StrikeKind::Ranged => Ability::DEX,
+            StrikeKind::Melee
+                if weapon.has_trait(&WeaponTrait::Finesse)
+                    && scores.modifier(Ability::DEX) > scores.modifier(Ability::STR) =>
+            {
+                Ability::DEX
+            }
+            StrikeKind::Melee => Ability::STR,
+        };
+        let attack_ability_bonus = scores.modifier(attack_ability);
+        let mut attack = Modifier::untyped(attack_ability_bonus);
+        attack += character.get_modifier("attack", Some(rref), resources);
+        attack += character.ability_check_modifier(attack_ability, Some(rref), resources);
+        attack += Bonus::item(weapon.potency as u16);
+
+        let str_mod = scores.modifier(Ability::STR);
+        let (damage_ability, damage_ability_bonus) = match kind {
+            StrikeKind::Melee => (Some(Ability::STR), str_mod),
+            StrikeKind::Ranged if thrown.is_some() => (Some(Ability::STR), str_mod),
+            StrikeKind::Ranged if weapon.has_trait(&WeaponTrait::Propulsive) => {
+                // Only half of a bonus applies, but all of a penalty does.
+                let half = if str_mod > 0 { str_mod / 2 } else { str_mod };
+                (Some(Ability::STR), half)
+            }
+            StrikeKind::Ranged => (None, 0),
+        };
+        let mut damage = Modifier::untyped(damage_ability_bonus);
+        damage += character.get_modifier("weapon damage", Some(rref), resources);
+        if let Some(ability) = damage_ability {
+            let label = format!("{}-based damage", ability);
+            damage += character.get_modifier(&label, Some(rref), resources);
+        }
+
+        let range = match kind {
+            StrikeKind::Melee => None,
+            StrikeKind::Ranged => weapon.range.or(thrown),
+        };
+        let riders = weapon
+            .traits
+            .iter()
+            .filter_map(|t| StrikeRider::from_trait(t, weapon))
+            .filter(|r| kind == StrikeKind::Ranged || !matches!(r, StrikeRider::Volley { .. }))
+            .collect();
+
+        Self {
+            name: name.into(),
+            weapon: rref.clone(),
+            kind,
+            range,
+            proficiency: character.weapon_proficiency(rref, resources),
+            attack_ability,
+            attack_ability_bonus,
+            attack,
+            agile: weapon.has_trait(&WeaponTrait::Agile),
+            damage_dice: weapon.damage_dice(),
+            damage_ability,
+            damage_ability_bonus,
+            damage,
+            damage_type: weapon.damage_type,
+            riders,
+            traits: weapon.traits.clone(),
+        }
+    }
+
+    pub fn attack_bonus(&self) -> i16 {
+        self.attack.total()
+    }
+
+    /// The penalty to the first, second and third attack of a turn.
+    pub fn multiple_attack_penalties(&self) -> [i16; 3] {
+        if self.agile {
+            [0, -4, -8]
+        } else {
+            [0, -5, -10]
+        }
+    }
+
+    /// The attack bonus for the first, second and third attack of a turn.
+    pub fn attack_bonuses(&self) -> [i16; 3] {
+        let total = self.attack_bonus();
+        let [first, second, third] = self.multiple_attack_penalties();
+        [total + first, total + second, total + third]
+    }
+
+    pub fn damage_bonus(&self) -> i16 {
+        self.damage.total()
+    }
+
+    /// Values for the weapon fields on the PDF character sheet, by field ID.
+    /// The caller picks which slot they go in.
+    pub fn pdf_fields(&self) -> Vec<(&'static str, String)> {
+        let traits = self
+            .traits
+            .iter()
+            .map(|t| format!("{}", t))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let riders = self
+            .riders
+            .iter()
+            .map(|r| format!("{}", r))
+            .collect::<Vec<_>>()
+            .join(", ");
+        vec![
+            ("WeaponName", self.name.clone()),
+            ("WeaponAttackBonus", format!("{}", self.attack_bonus())),
+            (
+                "WeaponAttackAbilityBonus",
+                format!("{}", self.attack_ability_bonus),
+            ),
+            (
+                "WeaponProficiency",
+                format!("{}", self.attack.proficiency_part().total()),
+            ),
+            (
+                "WeaponAttackItemBonus",
+                format!("{}", self.attack.item_part().total()),
+            ),
+            ("WeaponDamageDice", format!("{}", self.damage_dice)),
+            (
+                "WeaponDamageAbilityBonus",
+                format!("{}", self.damage_ability_bonus),
+            ),
+            (
+                "WeaponDamageSpecial",
+                format!("{}", self.damage_bonus() - self.damage_ability_bonus),
+            ),
+            ("WeaponDamageOther", riders.as_str().into()),
+            ("WeaponTraits", traits.as_str().into()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bonuses::BonusType,
+        calc::Calculation,
+        common::{Feat, Item, Resource, ResourceCommon},
+        cond::{Condition, Conditions, SingleCondition, WeaponProficiencyCondition},
+        effects::{BonusEffect, EffectCommon},
+        items::{ItemType, WeaponCategory},
+        stats::Die,
+        test_helpers::{crb_monk_at, proficiency_feat, setup},
+    };
+
+    fn weapon_item(name: &str, weapon: Weapon) -> Resource {
+        Resource::Item(Item {
+            weapon: Some(weapon),
+            ..Item::new(ResourceCommon::new(name), ItemType::Weapon)
+        })
+    }
+
+    fn longsword() -> Weapon {
+        Weapon {
+            potency: 1,
+            striking: 1,
+            ..Weapon::new(WeaponCategory::Martial, Die::D8, DamageType::S)
+        }
+    }
+
+    fn dagger() -> Weapon {
+        let mut dagger = Weapon::new(WeaponCategory::Simple, Die::D4, DamageType::P);
+        dagger.traits.extend(vec![
+            WeaponTrait::Agile,
+            WeaponTrait::Finesse,
+            WeaponTrait::Thrown(Some(10)),
+            WeaponTrait::Other("versatile S".into()),
+        ]);
+        dagger
+    }
+
+    #[test]
+    fn melee_strike() {
+        let (mut character, storage) = setup(vec![
+            weapon_item("Longsword", longsword()),
+            proficiency_feat("Initial", &[("martial weapons", Proficiency::Trained)]),
+        ]);
+        character
+            .set_character_choice("Ability Boosts".into(), &vec![Ability::STR])
+            .unwrap();
+        let strikes = character.strikes(&storage);
+        assert_eq!(strikes.len(), 1);
+        let strike = &strikes[0];
+        assert_eq!(strike.kind, StrikeKind::Melee);
+        assert_eq!(strike.proficiency, Proficiency::Trained);
+        assert_eq!(strike.attack_ability, Ability::STR);
+        assert_eq!(strike.attack_bonuses(), [5, 0, -5]);
+        assert_eq!(
+            strike.damage_dice,
+            DieRoll {
+                count: 2,
+                size: Die::D8
+            }
+        );
+        assert_eq!(strike.damage_bonus(), 1);
+        assert!(strike.riders.is_empty());
+    }
+
+    #[test]
+    fn finesse_agile_and_thrown() {
+        let (mut character, storage) = setup(vec![
+            weapon_item("Dagger", dagger()),
+            proficiency_feat("Initial", &[("simple weapons", Proficiency::Trained)]),
+        ]);
+        character
+            .set_character_choice("Ability Boosts".into(), &vec![Ability::DEX])
+            .unwrap();
+        let strikes = character.strikes(&storage);
+        assert_eq!(strikes.len(), 2);
+        let melee = &strikes[0];
+        assert_eq!(melee.kind, StrikeKind::Melee);
+        assert_eq!(melee.attack_ability, Ability::DEX);
+        assert_eq!(melee.attack_bonuses(), [4, 0, -4]);
+        assert_eq!(melee.damage_ability, Some(Ability::STR));
+        assert_eq!(melee.damage_bonus(), 0);
+        assert_eq!(melee.range, None);
+
+        let thrown = &strikes[1];
+        assert_eq!(thrown.kind, StrikeKind::Ranged);
+        assert_eq!(thrown.range, Some(10));
+        assert_eq!(thrown.attack_ability, Ability::DEX);
+        assert_eq!(thrown.damage_ability, Some(Ability::STR));
+    }
+
+    #[test]
+    fn ranged_riders() {
+        let mut longbow = Weapon {
+            range: Some(100),
+            striking: 2,
+            ..Weapon::new(WeaponCategory::Martial, Die::D8, DamageType::P)
+        };
+        longbow
+            .traits
+            .extend(vec![WeaponTrait::Deadly(Die::D10), WeaponTrait::Volley(30)]);
+        let mut shortbow = Weapon {
+            range: Some(60),
+            ..Weapon::new(WeaponCategory::Martial, Die::D6, DamageType::P)
+        };
+        shortbow.traits.push(WeaponTrait::Propulsive);
+        let mut pick = Weapon::new(WeaponCategory::Martial, Die::D6, DamageType::P);
+        pick.traits.push(WeaponTrait::Fatal(Die::D10));
+        pick.traits.push(WeaponTrait::Forceful);
+        let (mut character, storage) = setup(vec![
+            weapon_item("Longbow", longbow),
+            weapon_item("Composite Shortbow", shortbow),
+            weapon_item("Pick", pick),
+        ]);
+        character
+            .set_character_choice("Ability Boosts".into(), &vec![Ability::STR, Ability::DEX])
+            .unwrap();
+        let strikes = character.strikes(&storage);
+        let names: Vec<&str> = strikes.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Composite Shortbow", "Longbow", "Pick"]);
+
+        let shortbow = &strikes[0];
+        assert_eq!(shortbow.damage_ability, Some(Ability::STR));
+        assert_eq!(shortbow.damage_bonus(), 0);
+
+        let longbow = &strikes[1];
+        assert_eq!(longbow.attack_ability, Ability::DEX);
+        assert_eq!(longbow.attack_bonuses(), [1, -4, -9]);
+        assert_eq!(longbow.damage_ability, None);
+        assert_eq!(longbow.damage_dice.count, 3);
+        assert_eq!(
+            longbow.riders.as_slice(),
+            &[
+                StrikeRider::Deadly(DieRoll {
+                    count: 2,
+                    size: Die::D10
+                }),
+                StrikeRider::Volley {
+                    range: 30,
+                    penalty: -2
+                },
+            ]
+        );
+
+        let pick = &strikes[2];
+        assert_eq!(
+            pick.riders.as_slice(),
+            &[
+                StrikeRider::Fatal {
+                    dice: DieRoll {
+                        count: 1,
+                        size: Die::D10
+                    },
+                    extra: DieRoll {
+                        count: 1,
+                        size: Die::D10
+                    },
+                },
+                StrikeRider::Forceful {
+                    second: 1,
+                    third: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn weapon_specialization() {
+        let mut common = ResourceCommon::new("Weapon Specialization");
+        for (rank, value) in [
+            (Proficiency::Expert, 2),
+            (Proficiency::Master, 3),
+            (Proficiency::Legendary, 4),
+        ]
+        .iter()
+        {
+            let mut conditions = Conditions::default();
+            conditions &= Condition::from(SingleCondition::WeaponProficiency(
+                WeaponProficiencyCondition::Exactly { exactly: *rank },
+            ));
+            common.add_effect(BonusEffect {
+                common: EffectCommon { conditions },
+                bonus_type: BonusType::Untyped,
+                target: "weapon damage".into(),
+                value: Calculation::from_number(*value),
+            });
+        }
+        let specialization = Resource::Feat(Feat {
+            common,
+            level: Default::default(),
+        });
+        let mut fist = Weapon::new(WeaponCategory::Unarmed, Die::D4, DamageType::B);
+        fist.traits.push(WeaponTrait::Agile);
+        let (character, storage) = setup(vec![
+            weapon_item("Fist", fist),
+            weapon_item("Longsword", longsword()),
+            proficiency_feat("Initial", &[("unarmed attacks", Proficiency::Master)]),
+            specialization,
+        ]);
+        let strikes = character.strikes(&storage);
+        assert_eq!(strikes[0].proficiency, Proficiency::Master);
+        assert_eq!(strikes[0].damage_bonus(), 3);
+        assert_eq!(strikes[1].proficiency, Proficiency::Untrained);
+        assert_eq!(strikes[1].damage_bonus(), 0);
+
+        let fields = strikes[0].pdf_fields();
+        assert!(fields.contains(&("WeaponDamageSpecial", "3".into())));
+        assert!(fields.contains(&("WeaponProficiency", "7".into())));
+    }
+
+    #[test]
+    fn crb_monk_weapon_specialization() {
+        let fist_damage = |level: u8| {
+            let (character, storage) = crb_monk_at(level);
+            let strikes = character.strikes(&storage);
+            assert_eq!(strikes[0].name, "Fist");
+            (strikes[0].proficiency, strikes[0].damage_bonus())
+        };
+
+        assert_eq!(fist_damage(6), (Proficiency::Expert, 0));
+        assert_eq!(fist_damage(7), (Proficiency::Expert, 2));
+        assert_eq!(fist_damage(13), (Proficiency::Master, 3));
+        // Greater weapon specialization stacks with weapon specialization.
+        assert_eq!(fist_damage(15), (Proficiency::Master, 6));
+    }
+
+    #[test]
+    fn crb_monk_strike_traits() {
+        let fist_traits = |level: u8| {
+            let (character, storage) = crb_monk_at(level);
+            let strikes = character.strikes(&storage);
+            assert_eq!(strikes[0].name, "Fist");
+            let added = ["magical", "cold iron", "silver", "adamantine"];
+            added
+                .iter()
+                .filter(|t| {
+                    strikes[0]
+                        .traits
+                        .contains(&WeaponTrait::Other((**t).into()))
+                })
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        assert!(fist_traits(2).is_empty());
+        assert_eq!(fist_traits(3), vec!["magical"]);
+        assert_eq!(fist_traits(11), vec!["magical", "cold iron", "silver"]);
+        assert_eq!(
+            fist_traits(17),
+            vec!["magical", "cold iron", "silver", "adamantine"]
+        );
+    }
+
+    #[test]
+    fn sheet_slots() {
+        let (character, storage) = setup(vec![
+            weapon_item("Longsword", longsword()),
+            weapon_item("Dagger", dagger()),
+        ]);
+        let slots: Vec<(WeaponSlot, String)> = character
+            .weapon_slots(&storage)
+            .into_iter()
+            .map(|(slot, strike)| (slot, strike.name))
+            .collect();
+        assert_eq!(
+            slots,
+            vec![
+                (WeaponSlot::Melee1, "Dagger".into()),
+                (WeaponSlot::Melee2, "Longsword".into()),
+                (WeaponSlot::Ranged1, "Dagger".into()),
+            ]
+        );
+    }
+}
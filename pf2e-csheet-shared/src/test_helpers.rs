@@ -1,5 +1,138 @@
 #![cfg(test)]
 
-use proptest::prelude::*;
-use smallvec::SmallVec;
+use async_trait::async_trait;
 use smartstring::alias::String;
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    character::Character,
+    common::{Feat, Resource, ResourceCommon, ResourceRef, ResourceType},
+    effects::{EffectCommon, IncreaseProficiencyEffect},
+    stats::{Level, Proficiency},
+    storage::ResourceStorage,
+};
+
+/// An in-memory `ResourceStorage` for tests. Lookups ignore the modifier on
+/// the requested reference.
+#[derive(Debug, Default)]
+pub(crate) struct TestStorage {
+    resources: Vec<Arc<Resource>>,
+}
+
+impl TestStorage {
+    pub(crate) fn new(resources: impl IntoIterator<Item = Resource>) -> Self {
+        Self {
+            resources: resources.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    fn find(&self, rref: &ResourceRef) -> Option<Arc<Resource>> {
+        self.resources
+            .iter()
+            .find(|r| {
+                r.common().name.eq_ignore_ascii_case(&rref.name)
+                    && rref
+                        .resource_type
+                        .map_or(true, |rt| rt == r.resource_type())
+            })
+            .cloned()
+    }
+}
+
+#[async_trait(?Send)]
+impl ResourceStorage for TestStorage {
+    async fn lookup_async(&self, rrefs: &[&ResourceRef]) -> Vec<Option<Arc<Resource>>> {
+        rrefs.iter().map(|rref| self.find(rref)).collect()
+    }
+
+    fn lookup_immediate(&self, rref: &ResourceRef) -> Option<Arc<Resource>> {
+        self.find(rref)
+    }
+
+    async fn all_by_type(&self, rtype: ResourceType) -> HashSet<ResourceRef> {
+        self.resources
+            .iter()
+            .filter(|r| r.resource_type() == rtype)
+            .map(|r| r.make_rref_no_mod())
+            .collect()
+    }
+
+    async fn register(&mut self, resource: Resource) -> Result<(), String> {
+        self.resources.push(Arc::new(resource));
+        Ok(())
+    }
+}
+
+/// A feat that gives each of `proficiencies`, like `("FORT",
+/// Proficiency::Trained)`.
+pub(crate) fn proficiency_feat(name: &str, proficiencies: &[(&str, Proficiency)]) -> Resource {
+    let mut common = ResourceCommon::new(name);
+    for (target, level) in proficiencies {
+        common.add_effect(IncreaseProficiencyEffect {
+            common: EffectCommon::default(),
+            target: (*target).into(),
+            level: *level,
+        });
+    }
+    Resource::Feat(Feat {
+        common,
+        level: Default::default(),
+    })
+}
+
+/// A feat of `level` with each of `traits`, like "monk" or "skill".
+pub(crate) fn traited_feat(name: &str, traits: &[&str], level: u8) -> Resource {
+    let mut common = ResourceCommon::new(name);
+    common.add_traits(traits);
+    Resource::Feat(Feat {
+        common,
+        level: Level::from(level),
+    })
+}
+
+/// A character with each of `resources`.
+pub(crate) fn character_with(resources: &[Resource]) -> Character {
+    let mut character = Character::new("Test Character");
+    for r in resources {
+        character.resources.insert(r.make_rref_no_mod());
+    }
+    character
+}
+
+/// A character with each of `resources`, along with storage that holds them.
+pub(crate) fn setup(resources: Vec<Resource>) -> (Character, TestStorage) {
+    (character_with(&resources), TestStorage::new(resources))
+}
+
+/// The monk class, its class features and its feats from the Core Rulebook
+/// resources.
+pub(crate) fn crb_monk() -> Vec<Resource> {
+    serde_yaml::from_str(include_str!("../../resources/crb/monk.yaml"))
+        .expect("Failed to parse monk.yaml")
+}
+
+/// The conditions from the Core Rulebook resources.
+pub(crate) fn crb_conditions() -> Vec<Resource> {
+    serde_yaml::from_str(include_str!("../../resources/crb/conditions.yaml"))
+        .expect("Failed to parse conditions.yaml")
+}
+
+/// The items from the Core Rulebook resources.
+pub(crate) fn crb_items() -> Vec<Resource> {
+    serde_yaml::from_str(include_str!("../../resources/crb/items.yaml"))
+        .expect("Failed to parse items.yaml")
+}
+
+/// A monk of `level` with the Core Rulebook monk resources, along with
+/// storage that holds them.
+pub(crate) fn crb_monk_at(level: u8) -> (Character, TestStorage) {
+    let storage = TestStorage::new(crb_monk());
+    let monk = ResourceRef::new("Monk", None::<&str>).with_type(Some(ResourceType::Class));
+    let mut character = Character::new("Test Monk");
+    character.resources.insert(monk.clone());
+    character
+        .set_choice(&monk, "Level".into(), &Level::from(level))
+        .unwrap();
+    character.normalize_resources(&storage);
+    (character, storage)
+}
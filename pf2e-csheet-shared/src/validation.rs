@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smartstring::alias::String;
+use std::fmt;
+use thiserror::Error;
+
+use crate::{
+    abilities::AbilityBoostError,
+    calc::EvalCycle,
+    choices::{Choice, ChoiceKind},
+    common::ResourceRef,
+    feats::{FeatSlot, FeatSlotError},
+    stats::{Level, Proficiency, Skill},
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+pub enum Severity {
+    /// Something the player still has to do, like a choice they haven't made
+    /// yet.
+    Incomplete,
+    /// Something that breaks the rules or can't be read.
+    Error,
+}
+
+/// A single problem found when validating a character.
+#[derive(Clone, Debug, Eq, PartialEq, Error, Deserialize, Serialize)]
+pub enum Diagnostic {
+    #[error("The character doesn't meet the prerequisites for {resource}")]
+    UnmetPrerequisites { resource: ResourceRef },
+    #[error("No choice has been made for ${choice} on {resource}")]
+    MissingChoice {
+        resource: ResourceRef,
+        choice: Choice,
+        kind: ChoiceKind,
+    },
+    #[error("Couldn't find {resource}")]
+    MissingResource { resource: ResourceRef },
+    #[error("The value {value} for ${choice} on {resource} isn't {expected}: {message}")]
+    InvalidChoice {
+        resource: ResourceRef,
+        choice: Choice,
+        expected: ChoiceKind,
+        value: Value,
+        message: String,
+    },
+    #[error("The character has more than one class ({})", list_rrefs(.classes))]
+    MultipleClasses { classes: Vec<ResourceRef> },
+    #[error(transparent)]
+    AbilityBoost(AbilityBoostError),
+    #[error("No feat has been chosen for the {0}")]
+    EmptyFeatSlot(FeatSlot),
+    #[error(transparent)]
+    FeatSlot(FeatSlotError),
+    #[error("{skill} would be {rank}, but characters can only be {max} at level {level}")]
+    SkillRankTooHigh {
+        skill: Skill,
+        rank: Proficiency,
+        max: Proficiency,
+        level: Level,
+    },
+    #[error(transparent)]
+    Cycle(EvalCycle),
+}
+
+fn list_rrefs(rrefs: &[ResourceRef]) -> String {
+    let names: Vec<String> = rrefs.iter().map(|r| format!("{}", r)).collect();
+    names.join(", ").into()
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::MissingChoice { .. } | Self::EmptyFeatSlot(_) => Severity::Incomplete,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// Everything wrong with a character, from [`Character::validate`].
+///
+/// [`Character::validate`]: crate::Character::validate
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Whether the character follows the rules, even if some choices haven't
+    /// been made yet.
+    pub fn is_legal(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> + '_ {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity() == Severity::Error)
+    }
+
+    pub fn incomplete(&self) -> impl Iterator<Item = &Diagnostic> + '_ {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity() == Severity::Incomplete)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No problems found");
+        }
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+#[cfg(test)]
+use chrono::TimeZone;
+use chrono::{DateTime, Utc};
+#[cfg(test)]
+use proptest::prelude::*;
+#[cfg(test)]
+use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use smartstring::alias::String;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    common::ResourceRef,
+    stats::{Gold, GoldOverflowError, GoldUnderflowError, Level},
+};
+
+/// What a ledger entry was for.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+#[cfg_attr(test, derive(Arbitrary))]
+pub enum Transaction {
+    /// Money gained some other way, like treasure or starting wealth.
+    Income,
+    /// Money spent on something that isn't an item.
+    Expense,
+    Buy {
+        item: ResourceRef,
+        quantity: u32,
+    },
+    /// Items sell for half their price.
+    Sell {
+        item: ResourceRef,
+        quantity: u32,
+    },
+}
+
+impl Transaction {
+    /// Whether the entry adds to the character's money.
+    pub fn is_credit(&self) -> bool {
+        matches!(self, Self::Income | Self::Sell { .. })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(test, derive(Arbitrary))]
+pub struct LedgerEntry {
+    #[cfg_attr(test, proptest(strategy = "any::<u128>().prop_map(Uuid::from_u128)"))]
+    pub id: Uuid,
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "(0i64..4_000_000_000).prop_map(|s| Utc.timestamp_opt(s, 0).unwrap())"
+        )
+    )]
+    pub time: DateTime<Utc>,
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    pub amount: Gold,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[cfg_attr(
+        test,
+        proptest(strategy = "any::<std::string::String>().prop_map_into()")
+    )]
+    pub note: String,
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum LedgerError {
+    #[error("Not enough money: {0}")]
+    InsufficientFunds(#[from] GoldUnderflowError),
+    #[error("The price is too high: {0}")]
+    PriceOverflow(#[from] GoldOverflowError),
+    #[error("{0} isn't an item")]
+    NotAnItem(ResourceRef),
+    #[error("There's no inventory entry {0}")]
+    NoSuchEntry(Uuid),
+    #[error("Can't sell {wanted} of an item when there are only {owned}")]
+    NotEnoughOwned { wanted: u32, owned: u32 },
+}
+
+/// Every change to a character's money, oldest first.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Ledger(Vec<LedgerEntry>);
+
+impl Ledger {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LedgerEntry> + '_ {
+        self.0.iter()
+    }
+
+    /// The money the character has after every entry.
+    pub fn balance(&self) -> Gold {
+        let credits: Gold = self.credits().map(|entry| entry.amount).sum();
+        let debits: Gold = self.debits().map(|entry| entry.amount).sum();
+        credits.checked_sub(debits).unwrap_or_else(|e| {
+            warn!("Ledger spends more than it has: {}", e);
+            Gold::zero()
+        })
+    }
+
+    fn credits(&self) -> impl Iterator<Item = &LedgerEntry> + '_ {
+        self.0.iter().filter(|entry| entry.transaction.is_credit())
+    }
+
+    fn debits(&self) -> impl Iterator<Item = &LedgerEntry> + '_ {
+        self.0.iter().filter(|entry| !entry.transaction.is_credit())
+    }
+
+    /// Record a transaction, making sure the character can afford it.
+    pub fn record(
+        &mut self,
+        transaction: Transaction,
+        amount: Gold,
+        time: DateTime<Utc>,
+        note: impl Into<String>,
+    ) -> Result<Uuid, LedgerError> {
+        if !transaction.is_credit() {
+            self.balance().checked_sub(amount)?;
+        }
+        let id = Uuid::new_v4();
+        self.0.push(LedgerEntry {
+            id,
+            time,
+            transaction,
+            amount,
+            note: note.into(),
+        });
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> Option<LedgerEntry> {
+        let index = self.0.iter().position(|entry| entry.id == id)?;
+        Some(self.0.remove(index))
+    }
+}
+
+/// The lump sum a character built above 1st level starts with, from the
+/// Character Wealth table in the GM chapter of the Core Rulebook.
+pub fn starting_wealth(level: Level) -> Gold {
+    let gp = match level.get() {
+        0 | 1 => 15,
+        2 => 30,
+        3 => 75,
+        4 => 140,
+        5 => 270,
+        6 => 450,
+        7 => 720,
+        8 => 1_100,
+        9 => 1_600,
+        10 => 2_300,
+        11 => 3_200,
+        12 => 4_500,
+        13 => 6_400,
+        14 => 9_300,
+        15 => 13_500,
+        16 => 20_000,
+        17 => 30_000,
+        18 => 45_000,
+        19 => 69_000,
+        _ => 112_000,
+    };
+    Gold::gp(gp)
+}
+
+/// How a character's gear and money compare to what they should start
+/// with at their level.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct WealthCheck {
+    pub level: Level,
+    pub allowed: Gold,
+    /// The price of everything in the inventory.
+    pub items: Gold,
+    pub currency: Gold,
+}
+
+impl WealthCheck {
+    pub fn total(&self) -> Gold {
+        self.items + self.currency
+    }
+
+    pub fn within_budget(&self) -> bool {
+        self.total() <= self.allowed
+    }
+
+    /// How much more the character has than they should, if any.
+    pub fn excess(&self) -> Option<Gold> {
+        self.total()
+            .checked_sub(self.allowed)
+            .ok()
+            .filter(|g| *g > Gold::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::{Item, Resource, ResourceCommon},
+        inventory::{InventoryItem, ItemState},
+        items::{ItemType, Weapon, WeaponCategory},
+        stats::{DamageType, Die},
+        test_helpers::TestStorage,
+        Character,
+    };
+
+    fn priced(name: &str, price: Gold) -> Resource {
+        let mut item = Item::new(ResourceCommon::new(name), ItemType::Other);
+        item.price = price;
+        Resource::Item(item)
+    }
+
+    fn time(s: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(s, 0).unwrap()
+    }
+
+    #[test]
+    fn ledger_balance() {
+        let rope = ResourceRef::new("Rope", None::<&str>);
+        let mut ledger = Ledger::default();
+        ledger
+            .record(Transaction::Income, Gold::gp(15), time(0), "Starting gold")
+            .unwrap();
+        let buy = Transaction::Buy {
+            item: rope.clone(),
+            quantity: 2,
+        };
+        ledger.record(buy, Gold::sp(10), time(1), "").unwrap();
+        assert_eq!(ledger.balance(), Gold::gp(14));
+
+        let err = ledger
+            .record(Transaction::Expense, Gold::gp(20), time(2), "Inn")
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::InsufficientFunds(_)));
+        assert_eq!(ledger.iter().count(), 2);
+    }
+
+    #[test]
+    fn buy_and_sell() {
+        let sword = priced("Longsword", Gold::gp(1));
+        let storage = TestStorage::new(vec![sword.clone()]);
+        let mut character = Character::new("Test Character");
+        character
+            .ledger
+            .record(Transaction::Income, Gold::gp(15), time(0), "")
+            .unwrap();
+
+        let rref = sword.make_rref_no_mod();
+        let id = character
+            .buy_item(&rref, 3, ItemState::Stowed, &storage, time(1), "")
+            .unwrap();
+        assert_eq!(character.ledger.balance(), Gold::gp(12));
+        assert_eq!(character.inventory.get(id).unwrap().quantity, 3);
+
+        let err = character
+            .sell_item(id, 4, &storage, time(2), "")
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::NotEnoughOwned { .. }));
+        let gained = character
+            .sell_item(id, 1, &storage, time(2), "Too many")
+            .unwrap();
+        assert_eq!(gained, Gold::sp(5));
+        assert_eq!(character.ledger.balance(), Gold::cp(1250));
+        assert_eq!(character.inventory.get(id).unwrap().quantity, 2);
+        character.sell_item(id, 2, &storage, time(3), "").unwrap();
+        assert!(character.inventory.get(id).is_none());
+
+        let err = character
+            .buy_item(&rref, 20, ItemState::Stowed, &storage, time(4), "")
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::InsufficientFunds(_)));
+        let err = character
+            .buy_item(&rref, u32::MAX, ItemState::Stowed, &storage, time(4), "")
+            .unwrap_err();
+        assert!(matches!(err, LedgerError::PriceOverflow(_)));
+        assert!(character.inventory.is_empty());
+    }
+
+    #[test]
+    fn wealth_check() {
+        assert_eq!(starting_wealth(Level::from(1)), Gold::gp(15));
+        assert_eq!(starting_wealth(Level::from(5)), Gold::gp(270));
+        assert_eq!(starting_wealth(Level::from(20)), Gold::gp(112_000));
+
+        let plate = priced("Full Plate", Gold::gp(30));
+        let storage = TestStorage::new(vec![plate.clone()]);
+        let mut character = Character::new("Test Character");
+        let rref = plate.make_rref_no_mod();
+        character.inventory.add(InventoryItem::new(rref));
+        character
+            .ledger
+            .record(Transaction::Income, Gold::gp(10), time(0), "")
+            .unwrap();
+        let check = character.wealth_check(&storage);
+        assert_eq!(check.total(), Gold::gp(40));
+        assert!(!check.within_budget());
+        assert_eq!(check.excess(), Some(Gold::gp(25)));
+
+        // Runes count for every item in the stack.
+        let mut sword = Item::new(ResourceCommon::new("Longsword"), ItemType::Weapon);
+        sword.price = Gold::gp(1);
+        sword.weapon = Some(Weapon::new(WeaponCategory::Martial, Die::D8, DamageType::S));
+        let rune = priced("Flaming", Gold::gp(500));
+        let sword = Resource::Item(sword);
+        let storage = TestStorage::new(vec![plate, sword.clone(), rune.clone()]);
+        let mut entry = InventoryItem::new(sword.make_rref_no_mod());
+        entry.quantity = 2;
+        entry.runes.potency = 1;
+        entry.runes.striking = 1;
+        entry.runes.property.push(rune.make_rref_no_mod());
+        character.inventory.add(entry);
+        let check = character.wealth_check(&storage);
+        assert_eq!(check.items, Gold::gp(30 + 2 * (1 + 35 + 65 + 500)));
+    }
+}